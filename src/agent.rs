@@ -0,0 +1,348 @@
+//! `--agent` mode: an external program plays the game by exchanging
+//! line-delimited JSON, over stdin/stdout or a Unix socket.
+//!
+//! After every step the game writes one line describing the world:
+//!
+//! ```text
+//! {"episode":0,"tick":4,"reward":0.4,"done":false,"obstacles":[{"kind":"small_cactus",
+//!  "distance":92.0,"width":3,"height":2,"elevation":0}],"speed":4.008,"dino_y":0.0,
+//!  "dino_velocity":0.0,"dino_state":"running","score":0}
+//! ```
+//!
+//! and the agent answers with an action, `{"action":"jump"}` (`noop`, `jump`
+//! or `crouch`), or asks for a fresh episode with `{"reset":42}`. Lines the
+//! game cannot parse are answered with `{"error":"..."}`.
+//!
+//! In lockstep mode (the default) the game waits for each answer, and the
+//! line after a `done` observation starts the next episode. In realtime mode
+//! the game ticks at its normal rate, reuses the last action until a new one
+//! arrives and starts the next episode by itself. The session ends when the
+//! agent closes its end.
+//!
+//! A drawn session plays the `env` playfield, the same course a headless
+//! one plays on the same seed, so the terminal must be at least that big.
+
+use {
+    crate::{
+        cli::Cli,
+        input::InputHandler,
+        render_thread::{RenderFrame, RenderThread},
+    },
+    color_eyre::{
+        Result,
+        eyre::{WrapErr, bail},
+    },
+    dino_rs::{
+        backend::BackendKind,
+        config::Cfg,
+        display::DisplaySettings,
+        env::{Action, Env, Observation},
+        rendering::Renderer,
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        io::{self, BufRead, BufReader, Write},
+        path::Path,
+        sync::mpsc::{self, Receiver, RecvTimeoutError},
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+/// How often a waiting lockstep session checks the keyboard for quit.
+const KEY_POLL: Duration = Duration::from_millis(50);
+
+#[derive(Serialize)]
+struct Update<'a> {
+    episode: u64,
+    tick: u64,
+    reward: f32,
+    done: bool,
+    #[serde(flatten)]
+    observation: &'a Observation,
+}
+
+#[derive(Serialize)]
+struct Problem {
+    error: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Command {
+    #[serde(default)]
+    action: Option<Action>,
+    #[serde(default)]
+    reset: Option<u64>,
+}
+
+enum Incoming {
+    Command(Command),
+    Invalid(String),
+}
+
+type Reader = Box<dyn BufRead + Send>;
+type Writer = Box<dyn Write>;
+
+pub fn run(cfg: Cfg, cli: &Cli) -> Result<()> {
+    let draws = cli.draws();
+    if draws {
+        // The course is the env's size whether or not it is drawn, so the
+        // same seed plays the same way headless.
+        let display = DisplaySettings::detect()?;
+        let (width, height) = (cfg.env.width, cfg.env.height);
+        if display.width < width || display.height < height {
+            bail!(
+                "The agent's game is drawn at {}x{} (env.width x env.height), but this \
+                 terminal is {}x{}; make the window larger or pass --headless",
+                width,
+                height,
+                display.width,
+                display.height
+            );
+        }
+    }
+    let (reader, writer) = connect(cli.socket.as_deref())?;
+
+    let backend = BackendKind::from_config(&cfg.render.backend);
+    let mut session = Session::new(cfg, writer, draws)?;
+    if draws {
+        crate::setup_terminal(backend)?;
+    }
+    let incoming = spawn_reader(reader);
+    let result = if cli.realtime {
+        session.realtime(incoming)
+    } else {
+        session.lockstep(incoming)
+    };
+
+    if draws {
+        crate::cleanup_terminal(backend)?;
+    }
+    if let Some(path) = &cli.socket {
+        std::fs::remove_file(path).ok();
+    }
+    result
+}
+
+fn connect(socket: Option<&Path>) -> Result<(Reader, Writer)> {
+    let Some(path) = socket else {
+        return Ok((
+            Box::new(BufReader::new(io::stdin())),
+            Box::new(io::stdout()),
+        ));
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        // A socket left behind by a run that crashed would block the bind.
+        if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = std::os::unix::net::UnixListener::bind(path)
+            .wrap_err_with(|| format!("Failed to listen on {}", path.display()))?;
+        eprintln!("Waiting for an agent on {}", path.display());
+        let (stream, _) = listener.accept()?;
+        let reader = stream.try_clone()?;
+        Ok((Box::new(BufReader::new(reader)), Box::new(stream)))
+    }
+
+    #[cfg(not(unix))]
+    {
+        color_eyre::eyre::bail!("--socket {} needs Unix sockets", path.display())
+    }
+}
+
+/// Parses agent lines on a separate thread so the game can keep ticking or
+/// watch the keyboard while it waits. The channel closes when the agent
+/// hangs up.
+fn spawn_reader(reader: Reader) -> Receiver<Incoming> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let incoming = match serde_json::from_str(&line) {
+                Ok(command) => Incoming::Command(command),
+                Err(e) => Incoming::Invalid(e.to_string()),
+            };
+            if sender.send(incoming).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+struct Session {
+    env: Env,
+    writer: Writer,
+    render: Option<RenderThread>,
+    episode: u64,
+    seed: u64,
+    best: usize,
+}
+
+impl Session {
+    fn new(cfg: Cfg, writer: Writer, draws: bool) -> Result<Self> {
+        let seed = cfg.game.seed.unwrap_or(0);
+        let env = Env::new(cfg.clone())?;
+        let render = if draws {
+            let renderer = Renderer::new(cfg, env.config().width, env.config().height)?;
+            crate::warn(renderer.warnings());
+            Some(RenderThread::spawn(renderer)?)
+        } else {
+            crate::warn(env.warnings());
+            None
+        };
+
+        Ok(Self {
+            env,
+            writer,
+            render,
+            episode: 0,
+            seed,
+            best: 0,
+        })
+    }
+
+    fn lockstep(&mut self, incoming: Receiver<Incoming>) -> Result<()> {
+        self.start_episode(self.seed)?;
+
+        loop {
+            let command = match incoming.recv_timeout(KEY_POLL) {
+                Ok(Incoming::Command(command)) => command,
+                Ok(Incoming::Invalid(error)) => {
+                    self.send(&Problem { error })?;
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.quit_requested()? {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            };
+
+            if let Some(seed) = command.reset {
+                self.episode += 1;
+                self.start_episode(seed)?;
+            } else if self.env.is_done() {
+                self.episode += 1;
+                self.start_episode(self.seed.wrapping_add(1))?;
+            } else {
+                self.step(command.action.unwrap_or_default())?;
+            }
+
+            if self.quit_requested()? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn realtime(&mut self, incoming: Receiver<Incoming>) -> Result<()> {
+        let interval = self.env.step_duration();
+        let mut action = Action::Noop;
+        let mut next_step = Instant::now();
+
+        self.start_episode(self.seed)?;
+
+        loop {
+            loop {
+                match incoming.try_recv() {
+                    Ok(Incoming::Command(command)) => {
+                        if let Some(seed) = command.reset {
+                            self.episode += 1;
+                            self.start_episode(seed)?;
+                        }
+                        if let Some(next) = command.action {
+                            action = next;
+                        }
+                    }
+                    Ok(Incoming::Invalid(error)) => self.send(&Problem { error })?,
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+
+            if self.quit_requested()? {
+                return Ok(());
+            }
+
+            if self.env.is_done() {
+                self.episode += 1;
+                self.start_episode(self.seed.wrapping_add(1))?;
+                action = Action::Noop;
+            } else {
+                self.step(action)?;
+            }
+
+            next_step += interval;
+            let now = Instant::now();
+            if next_step > now {
+                thread::sleep(next_step - now);
+            } else {
+                next_step = now;
+            }
+        }
+    }
+
+    fn start_episode(&mut self, seed: u64) -> Result<()> {
+        self.seed = seed;
+        let observation = self.env.reset(seed);
+        self.publish(&observation, 0.0, false, 0)
+    }
+
+    fn step(&mut self, action: Action) -> Result<()> {
+        let (observation, reward, done, info) = self.env.step(action);
+        self.best = self.best.max(info.score);
+        self.publish(&observation, reward, done, info.ticks)
+    }
+
+    fn publish(
+        &mut self,
+        observation: &Observation,
+        reward: f32,
+        done: bool,
+        tick: u64,
+    ) -> Result<()> {
+        if let Some(render) = &mut self.render {
+            render.frame(RenderFrame {
+                snapshot: self.env.snapshot(),
+                score: self.env.score(),
+                highscore: self.best,
+                banner: None,
+                ghosts: Vec::new(),
+            })?;
+        }
+
+        self.send(&Update {
+            episode: self.episode,
+            tick,
+            reward,
+            done,
+            observation,
+        })
+    }
+
+    fn send(&mut self, message: &impl Serialize) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, message)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Only a drawn session reads the keyboard; headless ones end when the
+    /// agent hangs up.
+    fn quit_requested(&self) -> Result<bool> {
+        if self.render.is_none() {
+            return Ok(false);
+        }
+        Ok(InputHandler::terminal().poll()?.is_none())
+    }
+}
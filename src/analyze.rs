@@ -0,0 +1,360 @@
+//! The `analyze` command: plays batches of seeded games with the reference
+//! [`Bot`], or plays recorded replays again, and writes balance reports for
+//! one config or two side by side.
+//!
+//! Reports land in the output directory as `survival.csv` (share of runs
+//! still alive at each score), `deaths.csv` (deaths by obstacle and speed),
+//! `gaps.csv` (spawn gaps between consecutive obstacles) and `summary.json`,
+//! which also counts obstacle pairs the bot cannot clear from a clean start.
+//! Replays recorded under other gameplay settings are skipped and counted,
+//! since their inputs would not line up with the config's obstacles.
+
+use {
+    crate::cli::AnalyzeArgs,
+    color_eyre::{
+        Result,
+        eyre::{WrapErr, bail},
+    },
+    dino_rs::{
+        bot::Bot,
+        config::Cfg,
+        replay::{self, Replay},
+        types::ObstacleType,
+        world::{Input, World},
+    },
+    serde::Serialize,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt::Write as _,
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Score step between rows of the survival curve.
+const SURVIVAL_STEP: usize = 100;
+
+/// Width in cells of each spawn gap bucket.
+const GAP_BUCKET: usize = 10;
+
+/// Speed buckets per multiple of `initial_speed`.
+const SPEED_BUCKETS: f32 = 2.0;
+
+/// What one game showed.
+#[derive(Default)]
+struct Run {
+    score: usize,
+    /// The obstacle that ended the run and the speed multiplier then.
+    death: Option<(ObstacleType, f32)>,
+    gaps: Vec<f32>,
+    impossible: usize,
+}
+
+/// Everything gathered under one config.
+#[derive(Serialize)]
+struct Report {
+    label: String,
+    runs: usize,
+    /// Replays recorded under other gameplay settings, which would not
+    /// line up with this config's obstacles.
+    skipped: usize,
+    mean_score: f32,
+    median_score: usize,
+    max_score: usize,
+    deaths: BTreeMap<String, usize>,
+    /// Consecutive obstacle pairs seen, and how many the bot could not
+    /// clear.
+    pairs: usize,
+    impossible: usize,
+    impossible_share: f32,
+    #[serde(skip)]
+    scores: Vec<usize>,
+    #[serde(skip)]
+    deaths_by_speed: BTreeMap<(String, u32), usize>,
+    #[serde(skip)]
+    gaps: BTreeMap<usize, usize>,
+}
+
+pub fn run(cfg: Cfg, args: &AnalyzeArgs) -> Result<()> {
+    let replays = match &args.replays {
+        Some(dir) => Some(load_replays(dir)?),
+        None => None,
+    };
+
+    let mut configs = vec![("current".to_string(), cfg)];
+    if let Some(path) = &args.compare {
+        let label = path
+            .file_stem()
+            .map_or("compare".to_string(), |s| s.to_string_lossy().into_owned());
+        configs.push((label, Cfg::load_from(path)?));
+    }
+
+    let reports: Vec<Report> = configs
+        .into_iter()
+        .map(|(label, cfg)| {
+            let (runs, skipped) = match &replays {
+                Some(replays) => {
+                    let hash = replay::config_hash(&cfg);
+                    let (matching, foreign): (Vec<&Replay>, Vec<&Replay>) = replays
+                        .iter()
+                        .partition(|replay| replay.config_hash == hash);
+                    let runs = matching
+                        .into_iter()
+                        .map(|replay| play_replay(&cfg, replay))
+                        .collect();
+                    (runs, foreign.len())
+                }
+                None => {
+                    let runs = (0..args.runs as u64)
+                        .map(|i| play_bot(&cfg, i, args.max_score))
+                        .collect();
+                    (runs, 0)
+                }
+            };
+            Report::new(label, runs, skipped)
+        })
+        .collect();
+
+    fs::create_dir_all(&args.out)
+        .wrap_err_with(|| format!("Failed to create {}", args.out.display()))?;
+    write(&args.out, "survival.csv", &survival_csv(&reports))?;
+    write(&args.out, "deaths.csv", &deaths_csv(&reports))?;
+    write(&args.out, "gaps.csv", &gaps_csv(&reports))?;
+    write(
+        &args.out,
+        "summary.json",
+        &serde_json::to_string_pretty(&reports)?,
+    )?;
+
+    print_summary(&reports);
+    println!("Wrote reports to {}", args.out.display());
+    Ok(())
+}
+
+/// Every `*.json` replay in `dir`, in file name order.
+fn load_replays(dir: &Path) -> Result<Vec<Replay>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .wrap_err_with(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!("no replays found in {}", dir.display());
+    }
+
+    paths
+        .iter()
+        .map(|path| {
+            let json = fs::read_to_string(path)
+                .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&json)
+                .wrap_err_with(|| format!("{} is not a replay", path.display()))
+        })
+        .collect()
+}
+
+fn play_bot(cfg: &Cfg, index: u64, max_score: usize) -> Run {
+    let mut cfg = cfg.clone();
+    cfg.game.seed = Some(cfg.game.seed.unwrap_or(0).wrapping_add(index));
+    let world = World::new(cfg.clone(), cfg.env.width, cfg.env.height);
+
+    play(&cfg, world, max_score, u64::MAX, |world| {
+        vec![Input::from(Bot::decide(world.physics(), world.height()))]
+    })
+}
+
+/// Plays `replay` for as long as it was recorded; a run that ended by
+/// quitting stops where the player left.
+fn play_replay(cfg: &Cfg, replay: &Replay) -> Run {
+    play(cfg, replay.world(cfg), usize::MAX, replay.ticks, |world| {
+        replay.inputs_at(world.ticks()).collect()
+    })
+}
+
+/// Runs `world` tick by tick until the dino dies, reaches `max_score` or
+/// has run `max_ticks`, noting every spawn gap and checking each new pair
+/// of obstacles.
+fn play(
+    cfg: &Cfg,
+    mut world: World,
+    max_score: usize,
+    max_ticks: u64,
+    mut inputs: impl FnMut(&World) -> Vec<Input>,
+) -> Run {
+    let (width, height) = (world.width() as f32, world.height());
+    let mut last_id = world.physics().obstacles.last().map_or(0, |obs| obs.id);
+    let mut run = Run::default();
+
+    while !world.is_over() && world.score() < max_score && world.ticks() < max_ticks {
+        let next = inputs(&world);
+        world.tick(&next);
+
+        let physics = world.physics();
+        for pair in physics.obstacles.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if b.id <= last_id {
+                continue;
+            }
+            last_id = b.id;
+
+            let gap = b.x - a.x;
+            run.gaps.push(gap);
+            let sequence = [(a.obstacle_type, width), (b.obstacle_type, width + gap)];
+            if !Bot::clears(&cfg.physics, height, physics.speed, &sequence) {
+                run.impossible += 1;
+            }
+        }
+    }
+
+    run.score = world.score();
+    if world.is_over() {
+        let physics = world.physics();
+        run.death = physics
+            .hit
+            .map(|kind| (kind, physics.speed / cfg.physics.initial_speed));
+    }
+    run
+}
+
+impl Report {
+    fn new(label: String, runs: Vec<Run>, skipped: usize) -> Self {
+        let mut scores: Vec<usize> = runs.iter().map(|run| run.score).collect();
+        scores.sort_unstable();
+
+        let mut deaths = BTreeMap::new();
+        let mut deaths_by_speed = BTreeMap::new();
+        let mut gaps = BTreeMap::new();
+        let mut pairs = 0;
+        let mut impossible = 0;
+
+        for run in &runs {
+            if let Some((kind, speed)) = run.death {
+                *deaths.entry(kind_name(kind)).or_insert(0) += 1;
+                let bucket = (speed * SPEED_BUCKETS).floor() as u32;
+                *deaths_by_speed
+                    .entry((kind_name(kind), bucket))
+                    .or_insert(0) += 1;
+            }
+            for &gap in &run.gaps {
+                let bucket = gap.max(0.0) as usize / GAP_BUCKET * GAP_BUCKET;
+                *gaps.entry(bucket).or_insert(0) += 1;
+            }
+            pairs += run.gaps.len();
+            impossible += run.impossible;
+        }
+
+        Self {
+            label,
+            runs: runs.len(),
+            skipped,
+            mean_score: scores.iter().sum::<usize>() as f32 / scores.len().max(1) as f32,
+            median_score: scores.get(scores.len() / 2).copied().unwrap_or(0),
+            max_score: scores.last().copied().unwrap_or(0),
+            deaths,
+            pairs,
+            impossible,
+            impossible_share: impossible as f32 / pairs.max(1) as f32,
+            scores,
+            deaths_by_speed,
+            gaps,
+        }
+    }
+
+    /// Share of runs that reached `score`.
+    fn surviving(&self, score: usize) -> f32 {
+        let alive = self.scores.iter().filter(|&&s| s >= score).count();
+        alive as f32 / self.scores.len().max(1) as f32
+    }
+}
+
+fn kind_name(kind: ObstacleType) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn header(first: &str, reports: &[Report]) -> String {
+    let labels: Vec<&str> = reports.iter().map(|r| r.label.as_str()).collect();
+    format!("{},{}\n", first, labels.join(","))
+}
+
+fn survival_csv(reports: &[Report]) -> String {
+    let top = reports.iter().map(|r| r.max_score).max().unwrap_or(0);
+    let mut csv = header("score", reports);
+    for score in (0..=top + SURVIVAL_STEP).step_by(SURVIVAL_STEP) {
+        let shares: Vec<String> = reports
+            .iter()
+            .map(|r| format!("{:.3}", r.surviving(score)))
+            .collect();
+        writeln!(csv, "{},{}", score, shares.join(",")).ok();
+    }
+    csv
+}
+
+fn deaths_csv(reports: &[Report]) -> String {
+    let keys: BTreeSet<&(String, u32)> = reports
+        .iter()
+        .flat_map(|r| r.deaths_by_speed.keys())
+        .collect();
+
+    let mut csv = header("obstacle,speed", reports);
+    for key in keys {
+        let counts: Vec<String> = reports
+            .iter()
+            .map(|r| r.deaths_by_speed.get(key).copied().unwrap_or(0).to_string())
+            .collect();
+        writeln!(
+            csv,
+            "{},{:.1}x,{}",
+            key.0,
+            key.1 as f32 / SPEED_BUCKETS,
+            counts.join(",")
+        )
+        .ok();
+    }
+    csv
+}
+
+fn gaps_csv(reports: &[Report]) -> String {
+    let buckets: BTreeSet<usize> = reports
+        .iter()
+        .flat_map(|r| r.gaps.keys().copied())
+        .collect();
+
+    let mut csv = header("gap", reports);
+    for bucket in buckets {
+        let counts: Vec<String> = reports
+            .iter()
+            .map(|r| r.gaps.get(&bucket).copied().unwrap_or(0).to_string())
+            .collect();
+        writeln!(csv, "{},{}", bucket, counts.join(",")).ok();
+    }
+    csv
+}
+
+fn write(dir: &Path, name: &str, contents: &str) -> Result<()> {
+    let path = dir.join(name);
+    fs::write(&path, contents).wrap_err_with(|| format!("Failed to write {}", path.display()))
+}
+
+fn print_summary(reports: &[Report]) {
+    let row = |name: &str, cell: &dyn Fn(&Report) -> String| {
+        let cells: String = reports.iter().map(|r| format!("{:>12}", cell(r))).collect();
+        println!("{:<20}{}", name, cells);
+    };
+
+    row("", &|r| r.label.clone());
+    row("runs", &|r| r.runs.to_string());
+    if reports.iter().any(|r| r.skipped > 0) {
+        row("skipped replays", &|r| r.skipped.to_string());
+    }
+    row("mean score", &|r| format!("{:.1}", r.mean_score));
+    row("median score", &|r| r.median_score.to_string());
+    row("max score", &|r| r.max_score.to_string());
+    row("impossible pairs", &|r| {
+        format!("{:.2}%", r.impossible_share * 100.0)
+    });
+}
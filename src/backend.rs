@@ -0,0 +1,284 @@
+//! Where finished frames go.
+//!
+//! The renderer composes every frame into a [`Buffer`] and hands it to a
+//! backend: the crossterm backend paints it on a full-screen terminal,
+//! whether that is this process's or one at the far end of a socket, the
+//! line backend prints plain rows for terminals without cursor control, and
+//! the memory backend keeps frames as text so tests can inspect them.
+
+use {
+    crate::buffer::Buffer,
+    std::io::{Write, stdout},
+};
+#[cfg(feature = "tui")]
+use {
+    crate::theme::{self, ColorSupport, Style},
+    crossterm::{
+        cursor, queue,
+        style::{self, Attribute, SetAttribute, SetBackgroundColor, SetForegroundColor},
+        terminal::{Clear, ClearType},
+    },
+    std::{env, io::BufWriter},
+    unicode_width::UnicodeWidthChar,
+};
+
+#[cfg(feature = "tui")]
+const ANSI16_COLORS: [style::Color; 16] = [
+    style::Color::Black,
+    style::Color::DarkRed,
+    style::Color::DarkGreen,
+    style::Color::DarkYellow,
+    style::Color::DarkBlue,
+    style::Color::DarkMagenta,
+    style::Color::DarkCyan,
+    style::Color::Grey,
+    style::Color::DarkGrey,
+    style::Color::Red,
+    style::Color::Green,
+    style::Color::Yellow,
+    style::Color::Blue,
+    style::Color::Magenta,
+    style::Color::Cyan,
+    style::Color::White,
+];
+
+/// Receives finished frames and text screens from a
+/// [`Renderer`](crate::rendering::Renderer).
+pub trait Backend: Send {
+    /// Shows a fully composed frame.
+    fn draw(&mut self, screen: &Buffer) -> color_eyre::Result<()>;
+
+    /// Replaces the screen with plain text, for menus and messages.
+    fn show_text(&mut self, lines: &[String]) -> color_eyre::Result<()>;
+
+    fn clear(&mut self) -> color_eyre::Result<()>;
+}
+
+#[cfg(feature = "tui")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BackendKind {
+    Crossterm,
+    Line,
+}
+
+#[cfg(feature = "tui")]
+impl BackendKind {
+    /// Parses the `render.backend` setting. `auto` picks the line backend
+    /// for `TERM=dumb` and crossterm everywhere else.
+    pub fn from_config(backend: &str) -> Self {
+        match backend {
+            "line" => Self::Line,
+            "crossterm" => Self::Crossterm,
+            _ if env::var("TERM").is_ok_and(|term| term == "dumb") => Self::Line,
+            _ => Self::Crossterm,
+        }
+    }
+
+    pub fn create(self, color_support: ColorSupport) -> Box<dyn Backend> {
+        match self {
+            Self::Crossterm => Box::new(CrosstermBackend::new(color_support)),
+            Self::Line => Box::new(LineBackend),
+        }
+    }
+}
+
+/// Paints frames on a full-screen terminal, in as many colors as it has.
+#[cfg(feature = "tui")]
+pub struct CrosstermBackend {
+    color_support: ColorSupport,
+    /// Flushed once per frame or screen.
+    out: BufWriter<Box<dyn Write + Send>>,
+}
+
+#[cfg(feature = "tui")]
+impl CrosstermBackend {
+    /// Paints on this process's terminal.
+    pub fn new(color_support: ColorSupport) -> Self {
+        Self::with_writer(color_support, Box::new(stdout()))
+    }
+
+    /// Sends the same escape sequences to `out` instead, such as a socket
+    /// with a terminal at the other end.
+    pub fn with_writer(color_support: ColorSupport, out: Box<dyn Write + Send>) -> Self {
+        Self {
+            color_support,
+            out: BufWriter::new(out),
+        }
+    }
+
+    fn apply_style(
+        color_support: ColorSupport,
+        out: &mut impl Write,
+        cell_style: Style,
+    ) -> color_eyre::Result<()> {
+        queue!(out, SetAttribute(Attribute::Reset))?;
+
+        if cell_style.attrs.bold {
+            queue!(out, SetAttribute(Attribute::Bold))?;
+        }
+        if cell_style.attrs.dim {
+            queue!(out, SetAttribute(Attribute::Dim))?;
+        }
+        if cell_style.attrs.reverse {
+            queue!(out, SetAttribute(Attribute::Reverse))?;
+        }
+
+        let fg = cell_style
+            .fg
+            .and_then(|c| Self::terminal_color(color_support, c));
+        let bg = cell_style
+            .bg
+            .and_then(|c| Self::terminal_color(color_support, c));
+        queue!(
+            out,
+            SetForegroundColor(fg.unwrap_or(style::Color::Reset)),
+            SetBackgroundColor(bg.unwrap_or(style::Color::Reset))
+        )?;
+
+        Ok(())
+    }
+
+    /// Converts a theme color to the best the terminal supports, or `None`
+    /// when colors are disabled.
+    fn terminal_color(color_support: ColorSupport, color: theme::Color) -> Option<style::Color> {
+        match color_support {
+            ColorSupport::TrueColor => Some(style::Color::Rgb {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            }),
+            ColorSupport::Ansi256 => Some(style::Color::AnsiValue(theme::to_ansi256(color))),
+            ColorSupport::Ansi16 => Some(ANSI16_COLORS[theme::to_ansi16(color) as usize]),
+            ColorSupport::None => None,
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+impl Backend for CrosstermBackend {
+    /// Writes the buffer in one pass, only emitting color and attribute
+    /// escapes where the style changes from the previous cell. Each row starts
+    /// with an explicit cursor move, and the cursor is re-placed after any
+    /// glyph whose width is ambiguous, so a terminal that disagrees about a
+    /// glyph's width cannot shift the rest of the row.
+    fn draw(&mut self, screen: &Buffer) -> color_eyre::Result<()> {
+        let out = &mut self.out;
+        queue!(out, SetAttribute(Attribute::Reset))?;
+
+        let mut current: Option<Style> = None;
+        for (y, row) in screen.rows().enumerate() {
+            queue!(out, cursor::MoveTo(0, y as u16))?;
+
+            for (x, cell) in row.iter().enumerate() {
+                if cell.is_continuation() {
+                    continue;
+                }
+                if current != Some(cell.style) {
+                    Self::apply_style(self.color_support, out, cell.style)?;
+                    current = Some(cell.style);
+                }
+                write!(out, "{}", cell.ch)?;
+
+                if cell.ch.width() != cell.ch.width_cjk() {
+                    let next = x + cell.ch.width().unwrap_or(1);
+                    queue!(out, cursor::MoveTo(next as u16, y as u16))?;
+                }
+            }
+        }
+
+        queue!(out, SetAttribute(Attribute::Reset), style::ResetColor)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn show_text(&mut self, lines: &[String]) -> color_eyre::Result<()> {
+        let out = &mut self.out;
+        queue!(out, Clear(ClearType::All))?;
+        for (y, line) in lines.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, y as u16))?;
+            write!(out, "{}", line)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> color_eyre::Result<()> {
+        let out = &mut self.out;
+        queue!(out, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Prints every frame as plain rows with no escape sequences, for terminals
+/// that cannot move the cursor. Frames simply scroll past.
+pub struct LineBackend;
+
+impl LineBackend {
+    fn print(lines: impl IntoIterator<Item = String>) -> color_eyre::Result<()> {
+        let mut out = stdout().lock();
+        for line in lines {
+            // Raw mode turns off newline translation, so return explicitly.
+            write!(out, "{}\r\n", line.trim_end())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+impl Backend for LineBackend {
+    fn draw(&mut self, screen: &Buffer) -> color_eyre::Result<()> {
+        Self::print(screen.lines().into_iter().chain([String::new()]))
+    }
+
+    fn show_text(&mut self, lines: &[String]) -> color_eyre::Result<()> {
+        Self::print(lines.iter().cloned().chain([String::new()]))
+    }
+
+    fn clear(&mut self) -> color_eyre::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps every frame as rows of text. Clones share the same frames, so a
+/// test can hold one while the renderer owns the other.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    frames: std::sync::Arc<std::sync::Mutex<Vec<Vec<String>>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn frames(&self) -> Vec<Vec<String>> {
+        self.frames
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn push(&self, frame: Vec<String>) {
+        self.frames
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(frame);
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn draw(&mut self, screen: &Buffer) -> color_eyre::Result<()> {
+        self.push(screen.lines());
+        Ok(())
+    }
+
+    fn show_text(&mut self, lines: &[String]) -> color_eyre::Result<()> {
+        self.push(lines.to_vec());
+        Ok(())
+    }
+
+    fn clear(&mut self) -> color_eyre::Result<()> {
+        Ok(())
+    }
+}
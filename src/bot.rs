@@ -0,0 +1,279 @@
+//! A rule-based player for attract mode and `--autoplay`.
+//!
+//! Each tick [`Bot::decide`] replays the next stretch of the game in its
+//! head, using the configured `gravity` and `jump_velocity` for the jump
+//! arc, and picks the first action of a plan that gets past every obstacle
+//! already on screen.
+
+use crate::{
+    config::PhysicsConfig,
+    env::Action,
+    physics::{DINO_X, Hitbox, PhysicsEngine, collides},
+    types::{DinoState, ObstacleType},
+};
+
+/// Longest jump the bot will plan for, in ticks, in case gravity is set so
+/// low that the dino never comes down.
+const MAX_AIRTIME: usize = 200;
+
+/// Ticks [`Bot::clears`] plays before giving up on obstacles that never
+/// arrive.
+const MAX_CLEAR_TICKS: usize = 10_000;
+
+pub struct Bot;
+
+/// Hold `lead` until tick `jump_at`, jump there, then keep running.
+#[derive(Clone, Copy)]
+struct Plan {
+    lead: Action,
+    jump_at: Option<usize>,
+    ticks: usize,
+}
+
+impl Plan {
+    fn action(&self, tick: usize) -> Action {
+        match self.jump_at {
+            Some(at) if tick == at => Action::Jump,
+            Some(at) if tick > at => Action::Noop,
+            _ => self.lead,
+        }
+    }
+}
+
+/// The bot's copy of what matters for one dino: its motion, the speed and
+/// the obstacles on screen.
+#[derive(Clone)]
+struct Sim {
+    state: DinoState,
+    y: f32,
+    velocity: f32,
+    speed: f32,
+    obstacles: Vec<(ObstacleType, f32)>,
+}
+
+impl Sim {
+    fn capture(physics: &PhysicsEngine) -> Self {
+        Self {
+            state: physics.dino.state,
+            y: physics.dino.y,
+            velocity: physics.dino.velocity_y,
+            speed: physics.speed,
+            obstacles: physics
+                .obstacles
+                .iter()
+                .map(|obs| (obs.obstacle_type, obs.x))
+                .collect(),
+        }
+    }
+
+    /// Applies `action` and runs one tick, mirroring
+    /// [`PhysicsEngine::update`]. Returns `false` on a collision.
+    fn step(&mut self, action: Action, cfg: &PhysicsConfig, ground_y: usize) -> bool {
+        match action {
+            Action::Noop => {
+                if self.state == DinoState::Crouching {
+                    self.state = DinoState::Running;
+                }
+            }
+            Action::Jump => {
+                if matches!(self.state, DinoState::Running | DinoState::Crouching) {
+                    self.state = DinoState::Jumping;
+                    self.velocity = cfg.jump_velocity;
+                }
+            }
+            Action::Crouch => {
+                if self.state != DinoState::Dead {
+                    self.state = DinoState::Crouching;
+                }
+            }
+        }
+
+        match self.state {
+            DinoState::Jumping => {
+                self.velocity += cfg.gravity;
+                self.y -= self.velocity;
+                if self.y <= 0.0 {
+                    self.y = 0.0;
+                    self.velocity = 0.0;
+                    self.state = DinoState::Running;
+                }
+            }
+            DinoState::Running | DinoState::Crouching => {
+                self.y = 0.0;
+                self.velocity = 0.0;
+            }
+            DinoState::Dead => return false,
+        }
+
+        for (_, x) in &mut self.obstacles {
+            *x -= self.speed;
+        }
+        self.speed += cfg.speed_increment;
+
+        !self
+            .obstacles
+            .iter()
+            .any(|&(kind, x)| collides(self.state, self.y, kind, x, ground_y))
+    }
+
+    /// Plays `plan` from here and returns how many ticks the dino lives.
+    fn survive(&self, plan: Plan, cfg: &PhysicsConfig, ground_y: usize) -> usize {
+        let mut sim = self.clone();
+        (0..plan.ticks)
+            .find(|&tick| !sim.step(plan.action(tick), cfg, ground_y))
+            .unwrap_or(plan.ticks)
+    }
+}
+
+impl Bot {
+    /// Picks the action for the next tick of `physics`, played on a screen
+    /// `screen_height` rows tall.
+    ///
+    /// Running on is preferred over crouching, and both over jumping now
+    /// when a later jump would also clear what is coming. With no safe
+    /// plan the bot takes the one that survives longest.
+    pub fn decide(physics: &PhysicsEngine, screen_height: usize) -> Action {
+        let cfg = &physics.cfg.physics;
+        let ground_y = screen_height.saturating_sub(cfg.ground_height);
+        Self::choose(&Sim::capture(physics), cfg, ground_y)
+    }
+
+    /// Whether the bot gets past `obstacles`, given as kind and left column,
+    /// running from the ground at `speed`. Sequences it cannot clear are
+    /// most likely impossible for anyone.
+    pub fn clears(
+        cfg: &PhysicsConfig,
+        screen_height: usize,
+        speed: f32,
+        obstacles: &[(ObstacleType, f32)],
+    ) -> bool {
+        let ground_y = screen_height.saturating_sub(cfg.ground_height);
+        let mut sim = Sim {
+            state: DinoState::Running,
+            y: 0.0,
+            velocity: 0.0,
+            speed,
+            obstacles: obstacles.to_vec(),
+        };
+
+        for _ in 0..MAX_CLEAR_TICKS {
+            let passed = sim
+                .obstacles
+                .iter()
+                .all(|&(kind, x)| x + (Hitbox::obstacle(kind).width as f32) < DINO_X as f32);
+            if passed {
+                return true;
+            }
+            let action = Self::choose(&sim, cfg, ground_y);
+            if !sim.step(action, cfg, ground_y) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn choose(sim: &Sim, cfg: &PhysicsConfig, ground_y: usize) -> Action {
+        let airtime = Self::airtime(cfg);
+        let hold = airtime + 1;
+
+        let mut plans = vec![
+            Plan {
+                lead: Action::Noop,
+                jump_at: None,
+                ticks: hold,
+            },
+            Plan {
+                lead: Action::Crouch,
+                jump_at: None,
+                ticks: hold,
+            },
+        ];
+        for at in (1..=airtime).rev() {
+            for lead in [Action::Noop, Action::Crouch] {
+                plans.push(Plan {
+                    lead,
+                    jump_at: Some(at),
+                    ticks: at + hold,
+                });
+            }
+        }
+        plans.push(Plan {
+            lead: Action::Jump,
+            jump_at: Some(0),
+            ticks: hold,
+        });
+
+        let mut best = (0, Action::Noop);
+        for plan in plans {
+            let survived = sim.survive(plan, cfg, ground_y);
+            if survived == plan.ticks {
+                return plan.action(0);
+            }
+            if survived > best.0 {
+                best = (survived, plan.action(0));
+            }
+        }
+        best.1
+    }
+
+    /// Ticks from take-off to landing.
+    fn airtime(cfg: &PhysicsConfig) -> usize {
+        let (mut y, mut velocity) = (0.0, cfg.jump_velocity);
+        for tick in 1..=MAX_AIRTIME {
+            velocity += cfg.gravity;
+            y -= velocity;
+            if y <= 0.0 {
+                return tick;
+            }
+        }
+        MAX_AIRTIME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{config::Cfg, spawner::ObstacleSpawner},
+    };
+
+    #[test]
+    fn bot_clears_a_seeded_run() {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(11);
+        let (width, height) = (80, 24);
+
+        let mut physics = PhysicsEngine::new(cfg.clone(), width, height);
+        let mut spawner = ObstacleSpawner::new(cfg);
+        let mut score = 0;
+
+        for _ in 0..5000 {
+            match Bot::decide(&physics, height) {
+                Action::Noop => physics.stand(),
+                Action::Jump => {
+                    physics.jump();
+                }
+                Action::Crouch => physics.crouch(),
+            }
+            spawner.update_difficulty(score);
+            assert!(
+                physics.update(&mut spawner, width, height, &mut score),
+                "died at score {}",
+                score
+            );
+        }
+    }
+
+    #[test]
+    fn clears_tells_possible_from_impossible() {
+        let cfg = Cfg::default().physics;
+        let cactus = [(ObstacleType::SmallCactus, 60.0)];
+        assert!(Bot::clears(&cfg, 24, cfg.initial_speed, &cactus));
+
+        // Wider than a whole jump at crawling speed.
+        let wall: Vec<_> = (0..4)
+            .map(|i| (ObstacleType::WideCactus, 40.0 + 7.0 * i as f32))
+            .collect();
+        assert!(!Bot::clears(&cfg, 24, 1.0, &wall));
+    }
+}
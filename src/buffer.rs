@@ -0,0 +1,120 @@
+use {crate::theme::Style, unicode_width::UnicodeWidthChar};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cell {
+    pub ch: char,
+    pub style: Style,
+}
+
+impl Cell {
+    /// Placeholder for the right half of a double-width glyph.
+    const CONTINUATION: char = '\0';
+
+    pub fn new(ch: char, style: Style) -> Self {
+        Self { ch, style }
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.ch == Self::CONTINUATION
+    }
+}
+
+pub struct Buffer {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Buffer {
+    pub fn new(width: usize, height: usize, background: Style) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::new(' ', background); width * height],
+        }
+    }
+
+    /// Writes a glyph into the cell at `(x, y)`, keeping the cell's background
+    /// when the style does not set one. Out-of-bounds writes and zero-width
+    /// glyphs are ignored. Double-width glyphs also claim the cell to their
+    /// right and become a space if there is no room for them.
+    pub fn set(&mut self, x: usize, y: usize, ch: char, style: Style) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let (ch, width) = match ch.width() {
+            Some(0) | None => return,
+            Some(2) if x + 1 >= self.width => (' ', 1),
+            Some(width) => (ch, width),
+        };
+
+        self.clear_wide_neighbours(x, y);
+        self.put(x, y, ch, style);
+
+        if width == 2 {
+            self.clear_wide_neighbours(x + 1, y);
+            self.put(x + 1, y, Cell::CONTINUATION, style);
+        }
+    }
+
+    pub fn set_str(&mut self, x: usize, y: usize, text: &str, style: Style) {
+        let mut column = x;
+        for ch in text.chars() {
+            self.set(column, y, ch, style);
+            column += ch.width().unwrap_or(0);
+        }
+    }
+
+    /// Copies `rows` rows of `source`, starting at its row `from`, into this
+    /// buffer starting at row `to`. Rows and columns outside either buffer
+    /// are skipped.
+    pub fn copy_rows(&mut self, source: &Buffer, from: usize, to: usize, rows: usize) {
+        let width = self.width.min(source.width);
+        for row in 0..rows {
+            let (src_y, dst_y) = (from + row, to + row);
+            if src_y >= source.height || dst_y >= self.height {
+                break;
+            }
+            let src = src_y * source.width;
+            let dst = dst_y * self.width;
+            self.cells[dst..dst + width].copy_from_slice(&source.cells[src..src + width]);
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// The glyphs row by row, without styling.
+    pub fn lines(&self) -> Vec<String> {
+        self.rows()
+            .map(|row| {
+                row.iter()
+                    .filter(|cell| !cell.is_continuation())
+                    .map(|cell| cell.ch)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn put(&mut self, x: usize, y: usize, ch: char, style: Style) {
+        let cell = &mut self.cells[y * self.width + x];
+        let bg = style.bg.or(cell.style.bg);
+        cell.ch = ch;
+        cell.style = Style { bg, ..style };
+    }
+
+    /// Blanks the other half of any double-width glyph overlapping `(x, y)`
+    /// so overwriting half of one never leaves a dangling half behind.
+    fn clear_wide_neighbours(&mut self, x: usize, y: usize) {
+        let index = y * self.width + x;
+
+        if self.cells[index].is_continuation() && x > 0 {
+            self.cells[index - 1].ch = ' ';
+        }
+        if x + 1 < self.width && self.cells[index + 1].is_continuation() {
+            self.cells[index + 1].ch = ' ';
+        }
+    }
+}
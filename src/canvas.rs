@@ -0,0 +1,161 @@
+//! Sub-cell rendering.
+//!
+//! Moving sprites are rasterised onto a canvas with 2x4 dots per terminal
+//! cell, the resolution of a braille glyph, and then packed back into cells
+//! either as braille (full resolution) or as half blocks (2x vertical).
+//! Braille glyphs in sprites keep their exact dots; other glyphs are mapped to
+//! a small dot pattern approximating their shape.
+
+use crate::{buffer::Buffer, theme::Style};
+
+pub const DOTS_X: usize = 2;
+pub const DOTS_Y: usize = 4;
+
+const BRAILLE_BASE: u32 = 0x2800;
+/// Braille bits for the dot at `[x][y]` within a cell.
+const DOT_BITS: [[u8; DOTS_Y]; DOTS_X] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+const TOP_HALF: u8 = 0x01 | 0x02 | 0x08 | 0x10;
+const BOTTOM_HALF: u8 = 0x04 | 0x40 | 0x20 | 0x80;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SubcellMode {
+    HalfBlock,
+    Braille,
+}
+
+impl SubcellMode {
+    /// Parses the `render.mode` setting; `cell` and unknown values give `None`.
+    pub fn from_config(mode: &str) -> Option<Self> {
+        match mode {
+            "halfblock" | "half-block" => Some(Self::HalfBlock),
+            "braille" => Some(Self::Braille),
+            _ => None,
+        }
+    }
+}
+
+/// Dot pattern of a glyph in braille bit order.
+pub fn glyph_dots(ch: char) -> u8 {
+    let code = ch as u32;
+    if (BRAILLE_BASE..BRAILLE_BASE + 0x100).contains(&code) {
+        return (code - BRAILLE_BASE) as u8;
+    }
+
+    match ch {
+        ' ' => 0x00,
+        '|' => 0x47,
+        '_' => 0xC0,
+        '-' | '─' | '~' => 0x12,
+        '=' => 0x36,
+        '/' => 0x5C,
+        '\\' => 0xA3,
+        '(' | '<' => 0x8E,
+        ')' | '>' => 0x71,
+        'o' | 'O' | '0' => 0x36,
+        '.' | ',' => 0x40,
+        '\'' | '`' => 0x01,
+        '^' => 0x0A,
+        'v' => 0x84,
+        _ => 0xFF,
+    }
+}
+
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    dots: Vec<Option<Style>>,
+}
+
+impl Canvas {
+    /// Creates a canvas covering `cols` x `rows` terminal cells.
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let (width, height) = (cols * DOTS_X, rows * DOTS_Y);
+        Self {
+            width,
+            height,
+            dots: vec![None; width * height],
+        }
+    }
+
+    pub fn set(&mut self, x: isize, y: isize, style: Style) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.dots[y as usize * self.width + x as usize] = Some(style);
+        }
+    }
+
+    /// Stamps a glyph's dot pattern with its top-left dot at `(x, y)`,
+    /// dropping dots at or below row `clip_y`.
+    pub fn stamp(&mut self, x: isize, y: isize, ch: char, style: Style, clip_y: isize) {
+        let dots = glyph_dots(ch);
+        for (dx, column) in DOT_BITS.iter().enumerate() {
+            for (dy, bit) in column.iter().enumerate() {
+                let py = y + dy as isize;
+                if dots & bit != 0 && py < clip_y {
+                    self.set(x + dx as isize, py, style);
+                }
+            }
+        }
+    }
+
+    fn dot(&self, x: usize, y: usize) -> Option<Style> {
+        self.dots[y * self.width + x]
+    }
+
+    /// Packs the canvas into `buffer`, leaving cells with no dots untouched.
+    pub fn pack_into(&self, buffer: &mut Buffer, mode: SubcellMode) {
+        for row in 0..self.height / DOTS_Y {
+            for col in 0..self.width / DOTS_X {
+                match mode {
+                    SubcellMode::Braille => self.pack_braille(buffer, col, row),
+                    SubcellMode::HalfBlock => self.pack_half_block(buffer, col, row),
+                }
+            }
+        }
+    }
+
+    fn pack_braille(&self, buffer: &mut Buffer, col: usize, row: usize) {
+        let mut bits = 0u8;
+        let mut style = None;
+
+        for (dx, column) in DOT_BITS.iter().enumerate() {
+            for (dy, bit) in column.iter().enumerate() {
+                if let Some(dot_style) = self.dot(col * DOTS_X + dx, row * DOTS_Y + dy) {
+                    bits |= bit;
+                    style.get_or_insert(dot_style);
+                }
+            }
+        }
+
+        if let Some(style) = style
+            && let Some(ch) = char::from_u32(BRAILLE_BASE + bits as u32)
+        {
+            buffer.set(col, row, ch, style);
+        }
+    }
+
+    fn pack_half_block(&self, buffer: &mut Buffer, col: usize, row: usize) {
+        let half = |mask: u8| {
+            DOT_BITS.iter().enumerate().find_map(|(dx, column)| {
+                column
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, bit)| mask & **bit != 0)
+                    .find_map(|(dy, _)| self.dot(col * DOTS_X + dx, row * DOTS_Y + dy))
+            })
+        };
+
+        match (half(TOP_HALF), half(BOTTOM_HALF)) {
+            (Some(top), Some(bottom)) if top.fg != bottom.fg && bottom.fg.is_some() => {
+                let style = Style {
+                    bg: bottom.fg,
+                    ..top
+                };
+                buffer.set(col, row, '▀', style);
+            }
+            (Some(top), Some(_)) => buffer.set(col, row, '█', top),
+            (Some(top), None) => buffer.set(col, row, '▀', top),
+            (None, Some(bottom)) => buffer.set(col, row, '▄', bottom),
+            (None, None) => {}
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use {
+    clap::{Args, Parser, Subcommand},
+    dino_rs::config::Cfg,
+    std::path::PathBuf,
+};
+
+#[derive(Parser)]
+#[command(version, about = "The Chrome dinosaur game in your terminal")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Draw with ASCII-only sprites, for fonts or terminals without braille
+    #[arg(long)]
+    pub ascii: bool,
+
+    /// Target render frame rate, overriding `render.target_fps`
+    #[arg(long, value_name = "FPS")]
+    pub fps: Option<f32>,
+
+    /// Stream the game to `watch` viewers on ADDR, a TCP address or Unix
+    /// socket path, overriding `render.broadcast`
+    #[arg(long, value_name = "ADDR")]
+    pub broadcast: Option<String>,
+
+    /// Seed the random number generator so a run can be repeated exactly
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Start with the bot playing a demo; any key returns to the title
+    /// screen
+    #[arg(long, conflicts_with = "agent")]
+    pub autoplay: bool,
+
+    /// Watch a network saved by `train` play instead of the bot
+    #[arg(long, value_name = "GENOME", conflicts_with = "agent")]
+    pub watch: Option<PathBuf>,
+
+    /// Let an external program play, exchanging JSON lines over stdin/stdout
+    #[arg(long)]
+    pub agent: bool,
+
+    /// Talk to the agent over a Unix socket at PATH instead of stdin/stdout
+    #[arg(long, value_name = "PATH", requires = "agent")]
+    pub socket: Option<PathBuf>,
+
+    /// Keep the game running at normal speed, reusing the agent's last
+    /// action, instead of waiting for an action every step
+    #[arg(long, requires = "agent")]
+    pub realtime: bool,
+
+    /// Don't draw the game in agent mode; always the case over stdin/stdout
+    #[arg(long, requires = "agent")]
+    pub headless: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Evolve a network that plays the game, without drawing it
+    Train(TrainArgs),
+    /// Play batches of games with the bot and write balance reports
+    Analyze(AnalyzeArgs),
+    /// Host a race that other players join over TCP
+    Host(HostArgs),
+    /// Join a race someone is hosting
+    Join(JoinArgs),
+    /// Let remote terminals play over telnet, one game per connection
+    Serve(ServeArgs),
+    /// Watch a game someone is streaming with `--broadcast`
+    Watch(WatchArgs),
+    /// Play a saved replay again and check the score it claims
+    Verify(VerifyArgs),
+    /// Keep a leaderboard that games submit their runs to
+    LeaderboardServer(LeaderboardServerArgs),
+}
+
+#[derive(Args)]
+pub struct TrainArgs {
+    /// Where to save the best genome
+    #[arg(long, value_name = "PATH", default_value = "genome.json")]
+    pub out: PathBuf,
+
+    /// Generations to run, overriding `train.generations`
+    #[arg(long)]
+    pub generations: Option<usize>,
+
+    /// Genomes per generation, overriding `train.population`
+    #[arg(long)]
+    pub population: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// Seeded bot games per config
+    #[arg(long, default_value_t = 100)]
+    pub runs: usize,
+
+    /// End bot games that reach this score
+    #[arg(long, value_name = "SCORE", default_value_t = 5000)]
+    pub max_score: usize,
+
+    /// Play the replays saved in DIR instead of bot games
+    #[arg(long, value_name = "DIR")]
+    pub replays: Option<PathBuf>,
+
+    /// Also analyze the config file at PATH, side by side with the
+    /// current one
+    #[arg(long, value_name = "PATH")]
+    pub compare: Option<PathBuf>,
+
+    /// Directory to write the reports to
+    #[arg(long, value_name = "DIR", default_value = "analysis")]
+    pub out: PathBuf,
+}
+
+#[derive(Args)]
+pub struct HostArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 7878)]
+    pub port: u16,
+}
+
+#[derive(Args)]
+pub struct JoinArgs {
+    /// The host's address and port, such as 192.168.1.20:7878
+    pub addr: String,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Port to listen on, overriding `serve.port`
+    #[arg(long)]
+    pub port: Option<u16>,
+}
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// The broadcast's address, such as 127.0.0.1:7879, or its socket path
+    pub addr: String,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// The replay file, as saved in `replay_dir`
+    pub replay: PathBuf,
+
+    /// Require a signature made with the key in FILE, overriding
+    /// `key_file`
+    #[arg(long, value_name = "FILE")]
+    pub key: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct LeaderboardServerArgs {
+    /// Port to listen on, overriding `leaderboard.port`
+    #[arg(long)]
+    pub port: Option<u16>,
+}
+
+impl Cli {
+    /// Whether the game is drawn in the terminal. Agent mode over
+    /// stdin/stdout needs both streams for the protocol.
+    pub fn draws(&self) -> bool {
+        !self.agent || (!self.headless && self.socket.is_some())
+    }
+
+    /// Applies command-line overrides on top of the loaded config.
+    pub fn apply(&self, cfg: &mut Cfg) {
+        if self.ascii {
+            cfg.sprites.charset = "ascii".to_string();
+        }
+        if let Some(fps) = self.fps {
+            cfg.render.target_fps = fps;
+        }
+        if let Some(addr) = &self.broadcast {
+            cfg.render.broadcast = addr.clone();
+        }
+        if let Some(seed) = self.seed {
+            cfg.game.seed = Some(seed);
+        }
+        match &self.command {
+            Some(Command::Train(args)) => {
+                if let Some(generations) = args.generations {
+                    cfg.train.generations = generations;
+                }
+                if let Some(population) = args.population {
+                    cfg.train.population = population;
+                }
+            }
+            Some(Command::Serve(args)) => {
+                if let Some(port) = args.port {
+                    cfg.serve.port = port;
+                }
+            }
+            Some(Command::LeaderboardServer(args)) => {
+                if let Some(port) = args.port {
+                    cfg.leaderboard.port = port;
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -2,27 +2,87 @@ use {
     color_eyre::eyre::{Context, Result},
     config::Config,
     serde::{Deserialize, Serialize},
-    std::{fs, path::Path},
+    std::{collections::HashMap, fs, path::Path},
 };
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Cfg {
+    /// File the high score is kept in; empty keeps none.
     #[serde(default)]
     pub highscore_file: String,
 
+    /// Directory each finished run's replay is saved to; empty saves none.
+    #[serde(default)]
+    pub replay_dir: String,
+
+    /// Directory of personal-best replays, one per seed, raced as ghosts;
+    /// empty turns ghosts off.
+    #[serde(default)]
+    pub ghost_dir: String,
+
+    /// The daily challenge's leaderboard: each day's best score, seed and
+    /// attempt count; empty keeps none.
+    #[serde(default)]
+    pub daily_file: String,
+
+    /// File holding the key saved replays are signed with, shared by
+    /// everyone whose scores are compared; empty leaves them unsigned.
+    #[serde(default)]
+    pub key_file: String,
+
     #[serde(default)]
     pub physics: PhysicsConfig,
 
     #[serde(default)]
     pub game: GameConfig,
+
+    #[serde(default)]
+    pub spawner: SpawnerConfig,
+
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
+    #[serde(default)]
+    pub day_night: DayNightConfig,
+
+    #[serde(default)]
+    pub sprites: SpritesConfig,
+
+    #[serde(default)]
+    pub render: RenderConfig,
+
+    #[serde(default)]
+    pub env: EnvConfig,
+
+    #[serde(default)]
+    pub train: TrainConfig,
+
+    #[serde(default)]
+    pub serve: ServeConfig,
+
+    #[serde(default)]
+    pub leaderboard: LeaderboardConfig,
 }
 
 impl Default for Cfg {
     fn default() -> Self {
         Self {
             highscore_file: "highscore.txt".to_string(),
+            replay_dir: String::new(),
+            ghost_dir: "ghosts".to_string(),
+            daily_file: "daily.toml".to_string(),
+            key_file: String::new(),
             physics: PhysicsConfig::default(),
             game: GameConfig::default(),
+            spawner: SpawnerConfig::default(),
+            theme: ThemeConfig::default(),
+            day_night: DayNightConfig::default(),
+            sprites: SpritesConfig::default(),
+            render: RenderConfig::default(),
+            env: EnvConfig::default(),
+            train: TrainConfig::default(),
+            serve: ServeConfig::default(),
+            leaderboard: LeaderboardConfig::default(),
         }
     }
 }
@@ -32,8 +92,6 @@ pub struct PhysicsConfig {
     #[serde(default)]
     pub ground_height: usize,
     #[serde(default)]
-    pub default_fps: f32,
-    #[serde(default)]
     pub physics_fps: f32,
     #[serde(default)]
     pub fixed_timestep: f32,
@@ -59,6 +117,208 @@ pub struct GameConfig {
     pub initial_cloud_count: usize,
     #[serde(default)]
     pub cloud_speed_divisor: f32,
+    /// Fixes the random number generator so runs can be repeated.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Seconds on the title or game-over screen before the bot starts
+    /// playing a demo; 0 turns attract mode off.
+    #[serde(default)]
+    pub attract_after: f32,
+}
+
+/// Spacing between obstacles, in cells. Gaps are picked uniformly from
+/// `min_gap..=max_gap` and both ends shrink as the score climbs towards
+/// `ramp_score`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpawnerConfig {
+    #[serde(default)]
+    pub min_gap: f32,
+    #[serde(default)]
+    pub max_gap: f32,
+    #[serde(default)]
+    pub min_gap_shrink: f32,
+    #[serde(default)]
+    pub max_gap_shrink: f32,
+    #[serde(default)]
+    pub ramp_score: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub name: String,
+    /// One of `auto`, `truecolor`, `256`, `16` or `none`.
+    #[serde(default)]
+    pub color_mode: String,
+    /// Per-element `#rrggbb` overrides keyed by element name, e.g. `dino`,
+    /// `tall_cactus`, `ptero_high`, `cloud`, `ground`, `hud`, `background`.
+    #[serde(default)]
+    pub colors: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DayNightConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// `score` cycles every `period` points, `clock` follows local time.
+    #[serde(default)]
+    pub mode: String,
+    #[serde(default)]
+    pub period: usize,
+    #[serde(default)]
+    pub night_length: usize,
+    #[serde(default)]
+    pub night_start_hour: u32,
+    #[serde(default)]
+    pub night_end_hour: u32,
+    #[serde(default)]
+    pub fade_frames: usize,
+    #[serde(default)]
+    pub star_count: usize,
+    #[serde(default)]
+    pub star_speed_divisor: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpritesConfig {
+    /// Pack name, looked up as `<dir>/<pack>/` before the built-in packs.
+    #[serde(default)]
+    pub pack: String,
+    #[serde(default)]
+    pub dir: String,
+    /// `auto` guesses from the locale and `TERM`, `unicode` or `ascii` force
+    /// a choice. In ASCII mode the `classic` pack is swapped for `ascii`.
+    #[serde(default)]
+    pub charset: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RenderConfig {
+    /// `cell` snaps sprites to whole cells, `halfblock` doubles the vertical
+    /// resolution and `braille` draws on a 2x4 dot grid per cell.
+    #[serde(default)]
+    pub mode: String,
+    /// Frames per second to aim for; the terminal's repaint speed, not the
+    /// monitor's, is what limits this in practice.
+    #[serde(default)]
+    pub target_fps: f32,
+    /// Lower the frame rate while terminal writes cannot keep up.
+    #[serde(default)]
+    pub adaptive: bool,
+    /// `crossterm` paints a full-screen terminal, `line` prints plain rows
+    /// for dumb terminals and `auto` picks from `TERM`.
+    #[serde(default)]
+    pub backend: String,
+    /// A TCP address such as `127.0.0.1:7879`, or a Unix socket path, to
+    /// stream the game to `watch` viewers on; empty for none.
+    #[serde(default)]
+    pub broadcast: String,
+}
+
+/// Settings for the training environment, [`crate::env::Env`].
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EnvConfig {
+    /// Playfield size in cells, which also sets the raw frame size.
+    #[serde(default)]
+    pub width: usize,
+    #[serde(default)]
+    pub height: usize,
+    /// Physics ticks per `step`; the action is repeated on each.
+    #[serde(default)]
+    pub frame_skip: usize,
+    /// How many upcoming obstacles observations describe.
+    #[serde(default)]
+    pub lookahead: usize,
+    /// Render a text frame into every observation.
+    #[serde(default)]
+    pub render_frames: bool,
+    /// Reward per tick survived.
+    #[serde(default)]
+    pub alive_reward: f32,
+    /// Reward per point scored.
+    #[serde(default)]
+    pub score_reward: f32,
+    /// Added once when the dino dies; usually negative.
+    #[serde(default)]
+    pub death_reward: f32,
+    /// Added per jump started; a small negative value discourages
+    /// jumping for no reason.
+    #[serde(default)]
+    pub jump_reward: f32,
+}
+
+/// Settings for the `train` command, which evolves a
+/// [`crate::network::Network`] over [`crate::env::Env`] episodes.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrainConfig {
+    #[serde(default)]
+    pub population: usize,
+    #[serde(default)]
+    pub generations: usize,
+    /// Seeded games each genome plays per generation; every genome gets
+    /// the same seeds.
+    #[serde(default)]
+    pub episodes: usize,
+    /// Env steps before an episode is cut short.
+    #[serde(default)]
+    pub max_steps: usize,
+    #[serde(default)]
+    pub hidden: usize,
+    /// Best genomes copied unchanged into the next generation.
+    #[serde(default)]
+    pub elite: usize,
+    /// Chance that each weight of a child is mutated.
+    #[serde(default)]
+    pub mutation_rate: f64,
+    /// Standard deviation of a mutation.
+    #[serde(default)]
+    pub mutation_strength: f32,
+    /// Worker threads; 0 uses every core.
+    #[serde(default)]
+    pub threads: usize,
+}
+
+/// Settings for the `serve` command, which plays the game with remote
+/// terminals over telnet.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServeConfig {
+    #[serde(default)]
+    pub port: u16,
+    /// Games running at once; later connections are turned away.
+    #[serde(default)]
+    pub max_sessions: usize,
+    /// Seconds without a key press before a session is closed.
+    #[serde(default)]
+    pub idle_timeout: f32,
+    /// Longest a session may last, in seconds; 0 means no limit.
+    #[serde(default)]
+    pub session_timeout: f32,
+}
+
+/// Settings for the shared leaderboard: where the game submits runs, and
+/// how the `leaderboard-server` command keeps them.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LeaderboardConfig {
+    /// The server's address, such as `scores.example.org:7880`; empty
+    /// submits nothing. Free play then runs on the `env` playfield, the
+    /// only size the server accepts, or unranked on a smaller terminal.
+    #[serde(default)]
+    pub server: String,
+    /// The name runs are submitted under; empty uses the login name.
+    #[serde(default)]
+    pub name: String,
+    /// Runs listed on the game-over screen.
+    #[serde(default)]
+    pub top: usize,
+    /// Runs waiting for the server to be reachable, one per line.
+    #[serde(default)]
+    pub queue_file: String,
+    /// Port the server listens on.
+    #[serde(default)]
+    pub port: u16,
+    /// Where the server keeps accepted runs.
+    #[serde(default)]
+    pub store_file: String,
 }
 
 impl Default for PhysicsConfig {
@@ -66,7 +326,6 @@ impl Default for PhysicsConfig {
         let physics_fps = 60.0;
         Self {
             ground_height: 3,
-            default_fps: 60.0,
             physics_fps,
             fixed_timestep: 0.03,
             gravity: 1.5,
@@ -85,6 +344,124 @@ impl Default for GameConfig {
             hard_obstacles_score: 500,
             initial_cloud_count: 8,
             cloud_speed_divisor: 4.0,
+            seed: None,
+            attract_after: 20.0,
+        }
+    }
+}
+
+impl Default for SpawnerConfig {
+    fn default() -> Self {
+        Self {
+            min_gap: 50.0,
+            max_gap: 120.0,
+            min_gap_shrink: 15.0,
+            max_gap_shrink: 30.0,
+            ramp_score: 1000,
+        }
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "classic".to_string(),
+            color_mode: "auto".to_string(),
+            colors: HashMap::new(),
+        }
+    }
+}
+
+impl Default for DayNightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: "score".to_string(),
+            period: 700,
+            night_length: 250,
+            night_start_hour: 19,
+            night_end_hour: 7,
+            fade_frames: 30,
+            star_count: 24,
+            star_speed_divisor: 12.0,
+        }
+    }
+}
+
+impl Default for SpritesConfig {
+    fn default() -> Self {
+        Self {
+            pack: "classic".to_string(),
+            dir: "sprites".to_string(),
+            charset: "auto".to_string(),
+        }
+    }
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            height: 24,
+            frame_skip: 1,
+            lookahead: 3,
+            render_frames: false,
+            alive_reward: 0.1,
+            score_reward: 1.0,
+            death_reward: -10.0,
+            jump_reward: 0.0,
+        }
+    }
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        Self {
+            population: 100,
+            generations: 50,
+            episodes: 3,
+            max_steps: 20000,
+            hidden: 8,
+            elite: 5,
+            mutation_rate: 0.1,
+            mutation_strength: 0.3,
+            threads: 0,
+        }
+    }
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            port: 2323,
+            max_sessions: 8,
+            idle_timeout: 300.0,
+            session_timeout: 3600.0,
+        }
+    }
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self {
+            server: String::new(),
+            name: String::new(),
+            top: 5,
+            queue_file: "leaderboard-queue.jsonl".to_string(),
+            port: 7880,
+            store_file: "leaderboard.jsonl".to_string(),
+        }
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            mode: "cell".to_string(),
+            target_fps: 60.0,
+            adaptive: true,
+            backend: "auto".to_string(),
+            broadcast: String::new(),
         }
     }
 }
@@ -140,6 +517,24 @@ impl Cfg {
         Ok(cfg)
     }
 
+    /// Loads the config file at `path` over the defaults, ignoring
+    /// `dino.toml` and the environment. Unlike [`Cfg::load`], a file that
+    /// does not parse is an error.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let settings = Config::builder()
+            .add_source(
+                Config::try_from(&Self::default())
+                    .context("Failed to build default config source")?,
+            )
+            .add_source(config::File::from(path))
+            .build()
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        settings
+            .try_deserialize()
+            .with_context(|| format!("Invalid configuration in {}", path.display()))
+    }
+
     pub fn init(&self, path: &str) -> Result<()> {
         let toml_str =
             toml::to_string_pretty(self).context("Failed to serialize config to TOML")?;
@@ -0,0 +1,171 @@
+//! The daily challenge: one course a day, picked by the local date so
+//! every copy of the game plays the same one, and a leaderboard that keeps
+//! the best run of each day.
+
+use {
+    chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime},
+    color_eyre::eyre::{Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{fs, path::Path, time::Duration},
+};
+
+/// The seed of `date`'s course. Small enough to store as a TOML integer.
+pub fn seed(date: NaiveDate) -> u64 {
+    let day = date.year() as u64 * 10_000 + date.month() as u64 * 100 + date.day() as u64;
+    // The splitmix64 finalizer, so neighbouring days get unrelated seeds.
+    let mut z = day.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (z ^ (z >> 31)) >> 1
+}
+
+/// Time from `now` until the next challenge starts, at midnight.
+pub fn until_next(now: NaiveDateTime) -> Duration {
+    let tomorrow = now.date().succ_opt().unwrap_or(NaiveDate::MAX);
+    (tomorrow.and_time(NaiveTime::MIN) - now)
+        .to_std()
+        .unwrap_or_default()
+}
+
+/// `remaining` as hours and minutes, such as `5h 07m`.
+pub fn countdown(remaining: Duration) -> String {
+    let minutes = remaining.as_secs().div_ceil(60);
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// One day's challenge, as played on this machine.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DailyEntry {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub seed: u64,
+    /// The best score of the day.
+    pub best: usize,
+    /// Runs played that day, counting the best.
+    pub attempts: u32,
+}
+
+/// Every day the challenge was played, oldest first, kept in
+/// `daily_file` apart from the high score.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct DailyBoard {
+    #[serde(default)]
+    days: Vec<DailyEntry>,
+}
+
+fn key(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+impl DailyBoard {
+    /// Reads the board at `path`; a missing file is an empty board.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string_pretty(self).context("Failed to serialize daily board")?;
+        fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn days(&self) -> &[DailyEntry] {
+        &self.days
+    }
+
+    pub fn day(&self, date: NaiveDate) -> Option<&DailyEntry> {
+        let key = key(date);
+        self.days.iter().find(|entry| entry.date == key)
+    }
+
+    /// Counts a run of `date`'s challenge, keeping `score` if it is the
+    /// day's best.
+    pub fn record(&mut self, date: NaiveDate, score: usize) -> &DailyEntry {
+        let key = key(date);
+        let index = match self.days.binary_search_by(|entry| entry.date.cmp(&key)) {
+            Ok(index) => index,
+            Err(index) => {
+                self.days.insert(
+                    index,
+                    DailyEntry {
+                        date: key,
+                        seed: seed(date),
+                        best: 0,
+                        attempts: 0,
+                    },
+                );
+                index
+            }
+        };
+        let entry = &mut self.days[index];
+        entry.best = entry.best.max(score);
+        entry.attempts += 1;
+        entry
+    }
+
+    /// Days in a row the challenge was played, up to `date`. A streak that
+    /// ran until yesterday still counts until `date` is over.
+    pub fn streak(&self, date: NaiveDate) -> u32 {
+        let mut day = match self.day(date) {
+            Some(_) => date,
+            None => match date.pred_opt() {
+                Some(yesterday) => yesterday,
+                None => return 0,
+            },
+        };
+        let mut streak = 0;
+        while self.day(day).is_some() {
+            streak += 1;
+            match day.pred_opt() {
+                Some(before) => day = before,
+                None => break,
+            }
+        }
+        streak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).expect("valid date")
+    }
+
+    #[test]
+    fn seeds_follow_the_date_alone() {
+        assert_eq!(seed(date(18)), seed(date(18)));
+        assert_ne!(seed(date(18)), seed(date(19)));
+        assert!(seed(date(18)) <= i64::MAX as u64);
+
+        let evening = date(18).and_hms_opt(18, 30, 0).expect("valid time");
+        assert_eq!(countdown(until_next(evening)), "5h 30m");
+    }
+
+    #[test]
+    fn board_keeps_each_days_best_and_the_streak() {
+        let mut board = DailyBoard::default();
+        board.record(date(16), 40);
+        board.record(date(18), 120);
+        board.record(date(17), 90);
+        let entry = board.record(date(18), 60).clone();
+        assert_eq!(entry.best, 120);
+        assert_eq!(entry.attempts, 2);
+        assert_eq!(entry.seed, seed(date(18)));
+
+        assert_eq!(board.streak(date(18)), 3);
+        // Not played yet today, but yesterday's streak still stands.
+        assert_eq!(board.streak(date(19)), 3);
+        assert_eq!(board.streak(date(20)), 0);
+
+        let text = toml::to_string_pretty(&board).expect("serialize");
+        let loaded: DailyBoard = toml::from_str(&text).expect("parse");
+        assert_eq!(loaded.days(), board.days());
+        assert_eq!(loaded.days()[0].date, "2026-10-16");
+    }
+}
@@ -0,0 +1,62 @@
+use {
+    crate::config::DayNightConfig,
+    chrono::{Local, Timelike},
+};
+
+/// Tracks how far the palette has faded towards night, in `0.0..=1.0`.
+pub struct DayNightCycle {
+    cfg: DayNightConfig,
+    blend: f32,
+}
+
+impl DayNightCycle {
+    pub fn new(cfg: DayNightConfig) -> Self {
+        let mut cycle = Self { cfg, blend: 0.0 };
+        if cycle.is_night(0) {
+            cycle.blend = 1.0;
+        }
+        cycle
+    }
+
+    pub fn is_night(&self, score: usize) -> bool {
+        if !self.cfg.enabled {
+            return false;
+        }
+
+        match self.cfg.mode.as_str() {
+            "clock" => {
+                let hour = Local::now().hour();
+                let (start, end) = (self.cfg.night_start_hour, self.cfg.night_end_hour);
+                if start <= end {
+                    hour >= start && hour < end
+                } else {
+                    hour >= start || hour < end
+                }
+            }
+            _ => {
+                self.cfg.period > 0
+                    && score >= self.cfg.period
+                    && score % self.cfg.period < self.cfg.night_length
+            }
+        }
+    }
+
+    /// Steps the fade one frame towards the phase for `score` and returns the
+    /// new blend.
+    pub fn advance(&mut self, score: usize) -> f32 {
+        let target = if self.is_night(score) { 1.0 } else { 0.0 };
+        let step = 1.0 / self.cfg.fade_frames.max(1) as f32;
+
+        if self.blend < target {
+            self.blend = (self.blend + step).min(target);
+        } else {
+            self.blend = (self.blend - step).max(target);
+        }
+
+        self.blend
+    }
+
+    pub fn reset(&mut self) {
+        self.blend = if self.is_night(0) { 1.0 } else { 0.0 };
+    }
+}
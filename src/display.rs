@@ -1,39 +1,83 @@
-use crate::config::Cfg;
-use display_info::DisplayInfo;
-use std::time::Duration;
+use std::env;
+#[cfg(feature = "tui")]
+use std::sync::{Arc, Mutex};
 
+/// The terminal's size in cells.
+#[cfg(feature = "tui")]
 pub struct DisplaySettings {
     pub width: usize,
     pub height: usize,
-    pub render_frame_duration: Duration,
+    /// The latest size reported by a remote terminal, if this is one.
+    remote: Option<Arc<Mutex<(usize, usize)>>>,
 }
 
+#[cfg(feature = "tui")]
 impl DisplaySettings {
     pub fn detect() -> color_eyre::Result<Self> {
         let (cols, rows) = crossterm::terminal::size()?;
-        let refresh_rate = Self::detect_refresh_rate();
 
         Ok(Self {
             width: cols as usize,
             height: rows as usize,
-            render_frame_duration: Duration::from_secs_f32(1.0 / refresh_rate),
+            remote: None,
         })
     }
 
-    fn detect_refresh_rate() -> f32 {
-        let cfg = Cfg::load().expect("Failed to load config");
-        DisplayInfo::all()
-            .ok()
-            .map(|displays| displays[0].clone())
-            .filter(|display| display.frequency > 0.0)
-            .map(|display| display.frequency)
-            .unwrap_or(cfg.physics.default_fps)
+    /// A terminal at the far end of a connection. Whoever reads its size
+    /// reports, such as telnet window size updates, stores them in `size`.
+    pub fn remote(size: Arc<Mutex<(usize, usize)>>) -> Self {
+        let (width, height) = *size.lock().unwrap_or_else(|e| e.into_inner());
+        Self {
+            width,
+            height,
+            remote: Some(size),
+        }
     }
 
     pub fn update_size(&mut self) -> color_eyre::Result<()> {
-        let (cols, rows) = crossterm::terminal::size()?;
-        self.width = cols as usize;
-        self.height = rows as usize;
+        (self.width, self.height) = match &self.remote {
+            Some(size) => *size.lock().unwrap_or_else(|e| e.into_inner()),
+            None => {
+                let (cols, rows) = crossterm::terminal::size()?;
+                (cols as usize, rows as usize)
+            }
+        };
         Ok(())
     }
 }
+
+/// Guesses whether the terminal can show braille and box-drawing glyphs. The
+/// Linux console and old serial terminals cannot, and neither can anything
+/// running outside a UTF-8 locale.
+pub fn supports_unicode() -> bool {
+    if cfg!(windows) {
+        return env::var_os("WT_SESSION").is_some();
+    }
+
+    let term = env::var("TERM").unwrap_or_default();
+    if matches!(
+        term.as_str(),
+        "linux" | "dumb" | "vt100" | "vt102" | "vt220" | "ansi"
+    ) {
+        return false;
+    }
+
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+        .unwrap_or(false)
+}
+
+/// Resolves the `charset` setting to whether ASCII-only output is wanted.
+pub fn wants_ascii(charset: &str) -> bool {
+    match charset {
+        "ascii" => true,
+        "unicode" => false,
+        _ => !supports_unicode(),
+    }
+}
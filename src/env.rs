@@ -0,0 +1,324 @@
+//! A gym-style environment for training agents.
+//!
+//! [`Env`] drives [`PhysicsEngine::update`] and an [`ObstacleSpawner`]
+//! directly, one action per step, with frame skip and reward shaping taken
+//! from [`EnvConfig`]. Runs are fully determined by the seed given to
+//! [`Env::reset`].
+
+use {
+    crate::{
+        config::{Cfg, EnvConfig},
+        physics::{DINO_X, Hitbox, PhysicsEngine, obstacle_top},
+        rendering::{DrawArgs, Scene},
+        snapshot::Snapshot,
+        spawner::ObstacleSpawner,
+        types::{DinoState, ObstacleType},
+        world::Input,
+    },
+    serde::{Deserialize, Serialize},
+    std::time::Duration,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Keep running, standing up if crouched.
+    #[default]
+    Noop,
+    Jump,
+    Crouch,
+}
+
+impl From<Action> for Input {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Noop => Input::Stand,
+            Action::Jump => Input::Jump,
+            Action::Crouch => Input::Crouch,
+        }
+    }
+}
+
+/// One upcoming obstacle as seen from the dino.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObstacleView {
+    pub kind: ObstacleType,
+    /// Cells from the dino's left edge to the obstacle's left edge.
+    pub distance: f32,
+    pub width: usize,
+    pub height: usize,
+    /// Cells between the ground and the bottom of the obstacle; zero for
+    /// cacti.
+    pub elevation: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Observation {
+    /// The next obstacles ahead of the dino, nearest first, at most
+    /// `env.lookahead` of them.
+    pub obstacles: Vec<ObstacleView>,
+    pub speed: f32,
+    /// Height of the dino's feet above the ground.
+    pub dino_y: f32,
+    pub dino_velocity: f32,
+    pub dino_state: DinoState,
+    pub score: usize,
+    /// The rendered screen as text rows, when `env.render_frames` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<Vec<String>>,
+}
+
+/// Extra detail about a step that is not part of the observation.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StepInfo {
+    pub score: usize,
+    /// Physics ticks since the last reset.
+    pub ticks: u64,
+    pub jumped: bool,
+}
+
+pub struct Env {
+    cfg: Cfg,
+    physics: PhysicsEngine,
+    spawner: ObstacleSpawner,
+    scene: Option<Scene>,
+    score: usize,
+    ticks: u64,
+    done: bool,
+}
+
+impl Env {
+    /// Creates an environment seeded from `game.seed`, or zero when unset.
+    /// Fails only if frames are rendered and the sprite pack cannot load.
+    pub fn new(cfg: Cfg) -> color_eyre::Result<Self> {
+        let mut cfg = cfg;
+        cfg.game.seed.get_or_insert(0);
+
+        let (width, height) = (cfg.env.width, cfg.env.height);
+        let scene = if cfg.env.render_frames {
+            Some(Scene::new(cfg.clone(), width, height)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            physics: PhysicsEngine::new(cfg.clone(), width, height),
+            spawner: ObstacleSpawner::new(cfg.clone()),
+            cfg,
+            scene,
+            score: 0,
+            ticks: 0,
+            done: false,
+        })
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.cfg.env
+    }
+
+    /// Problems with the theme config; always empty unless frames are
+    /// rendered.
+    pub fn warnings(&self) -> &[String] {
+        self.scene.as_ref().map_or(&[], |scene| scene.warnings())
+    }
+
+    /// Game time one step covers at normal speed.
+    pub fn step_duration(&self) -> Duration {
+        Duration::from_secs_f32(
+            self.cfg.physics.fixed_timestep * self.cfg.env.frame_skip.max(1) as f32,
+        )
+    }
+
+    /// Starts a new episode. The same seed always plays out the same way
+    /// for the same actions.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.cfg.game.seed = Some(seed);
+        self.physics =
+            PhysicsEngine::new(self.cfg.clone(), self.cfg.env.width, self.cfg.env.height);
+        self.spawner = ObstacleSpawner::new(self.cfg.clone());
+        if let Some(scene) = &mut self.scene {
+            scene.reset_day_night();
+        }
+        self.score = 0;
+        self.ticks = 0;
+        self.done = false;
+        self.observe()
+    }
+
+    /// Applies `action` for `env.frame_skip` ticks, stopping early if the
+    /// dino dies. Returns the observation, the shaped reward, whether the
+    /// episode is over, and extra info.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, StepInfo) {
+        let env = self.cfg.env.clone();
+        let mut reward = 0.0;
+        let mut jumped = false;
+
+        for _ in 0..env.frame_skip.max(1) {
+            if self.done {
+                break;
+            }
+
+            match action {
+                Action::Noop => self.physics.stand(),
+                Action::Jump => {
+                    if self.physics.jump() {
+                        jumped = true;
+                        reward += env.jump_reward;
+                    }
+                }
+                Action::Crouch => self.physics.crouch(),
+            }
+
+            let before = self.score;
+            self.spawner.update_difficulty(self.score);
+            let alive =
+                self.physics
+                    .update(&mut self.spawner, env.width, env.height, &mut self.score);
+            self.ticks += 1;
+
+            reward += env.score_reward * (self.score - before) as f32;
+            if alive {
+                reward += env.alive_reward;
+            } else {
+                reward += env.death_reward;
+                self.done = true;
+            }
+        }
+
+        let info = StepInfo {
+            score: self.score,
+            ticks: self.ticks,
+            jumped,
+        };
+        (self.observe(), reward, self.done, info)
+    }
+
+    /// Positions of everything in the playfield, for drawing the episode.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::capture(&self.physics)
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// The current state, without advancing the game.
+    pub fn observe(&mut self) -> Observation {
+        let mut observation = Observation::capture(
+            &self.physics,
+            self.cfg.env.height,
+            self.cfg.env.lookahead,
+            self.score,
+        );
+        observation.frame = self.scene.as_mut().map(|scene| {
+            let snapshot = Snapshot::capture(&self.physics);
+            scene
+                .compose(DrawArgs::from_snapshot(&snapshot, self.score, self.score))
+                .lines()
+        });
+        observation
+    }
+}
+
+impl Observation {
+    /// Describes `physics` on a screen `screen_height` rows tall, listing at
+    /// most `lookahead` obstacles. Leaves `frame` empty.
+    pub fn capture(
+        physics: &PhysicsEngine,
+        screen_height: usize,
+        lookahead: usize,
+        score: usize,
+    ) -> Self {
+        let ground_y = screen_height.saturating_sub(physics.cfg.physics.ground_height);
+
+        let mut obstacles: Vec<ObstacleView> = physics
+            .obstacles
+            .iter()
+            .filter(|obs| obs.x + Hitbox::obstacle(obs.obstacle_type).width as f32 > DINO_X as f32)
+            .map(|obs| {
+                let hitbox = Hitbox::obstacle(obs.obstacle_type);
+                let bottom =
+                    obstacle_top(obs.obstacle_type, ground_y) + hitbox.y_offset + hitbox.height;
+                ObstacleView {
+                    kind: obs.obstacle_type,
+                    distance: obs.x - DINO_X as f32,
+                    width: hitbox.width,
+                    height: hitbox.height,
+                    elevation: ground_y.saturating_sub(bottom),
+                }
+            })
+            .collect();
+        obstacles.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        obstacles.truncate(lookahead);
+
+        Self {
+            obstacles,
+            speed: physics.speed,
+            dino_y: physics.dino.y,
+            dino_velocity: physics.dino.velocity_y,
+            dino_state: physics.dino.state,
+            score,
+            frame: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Trace = Vec<(f32, usize, Vec<f32>, Option<Vec<String>>)>;
+
+    fn run(seed: u64, render_frames: bool) -> Trace {
+        let mut cfg = Cfg::default();
+        cfg.sprites.charset = "unicode".to_string();
+        cfg.env.render_frames = render_frames;
+        cfg.env.frame_skip = 4;
+
+        let mut env = Env::new(cfg).expect("env");
+        env.reset(seed);
+
+        let mut trace = Vec::new();
+        for step in 0..200 {
+            let action = if step % 7 == 0 {
+                Action::Jump
+            } else {
+                Action::Noop
+            };
+            let (obs, reward, done, info) = env.step(action);
+            let distances = obs.obstacles.iter().map(|o| o.distance).collect();
+            trace.push((reward, info.score, distances, obs.frame));
+            if done {
+                break;
+            }
+        }
+        trace
+    }
+
+    #[test]
+    fn same_seed_same_episode() {
+        assert_eq!(run(3, true), run(3, true));
+    }
+
+    #[test]
+    fn seed_changes_the_episode() {
+        assert_ne!(run(3, false), run(4, false));
+    }
+
+    #[test]
+    fn observation_lists_nearest_obstacle_first() {
+        let mut env = Env::new(Cfg::default()).expect("env");
+        let obs = env.reset(1);
+        assert!(!obs.obstacles.is_empty());
+        assert!(
+            obs.obstacles
+                .windows(2)
+                .all(|pair| pair[0].distance <= pair[1].distance)
+        );
+        assert!(obs.frame.is_none());
+    }
+}
@@ -1,186 +1,847 @@
 use {
     crate::{
-        config::Cfg,
-        display::DisplaySettings,
         input::InputHandler,
-        physics::PhysicsEngine,
-        rendering::{DrawArgs, Renderer},
+        render_thread::{RenderCommand, RenderFrame, RenderThread},
         sound::SoundPlayer,
-        spawner::ObstacleSpawner,
     },
-    color_eyre::Result,
+    chrono::{Local, NaiveDate},
+    color_eyre::{Result, eyre::WrapErr},
     crossterm::event::KeyCode,
+    dino_rs::{
+        backend::BackendKind,
+        bot::Bot,
+        config::Cfg,
+        daily::{self, DailyBoard, DailyEntry},
+        display::DisplaySettings,
+        env::{Action, Observation},
+        ghost::Ghost,
+        leaderboard::{LeaderboardClient, Submitted},
+        network::Network,
+        pacing::FrameScheduler,
+        rendering::Renderer,
+        replay::{self, Replay},
+        spectate::SpectatorBackend,
+        theme::ColorSupport,
+        versus::Tally,
+        world::{Event, Input, World},
+    },
     std::{
-        fs,
+        env, fs,
+        path::{Path, PathBuf},
+        sync::mpsc::{self, Receiver, Sender},
+        thread,
         time::{Duration, Instant},
     },
 };
 
+/// How long a finished demo run stays on screen before the next one.
+const DEMO_RESTART: Duration = Duration::from_secs(1);
+
+/// Fewest rows a two-player world gets. Lanes on shorter terminals show
+/// only the bottom of their world, so jumps still have room.
+const LANE_MIN_HEIGHT: usize = 22;
+
+/// Lane labels while each player is alive and once they are out.
+const LANE_LABELS: [[&str; 2]; 2] = [
+    ["PLAYER 1 (W/S)", "PLAYER 1 OUT"],
+    ["PLAYER 2 (UP/DOWN)", "PLAYER 2 OUT"],
+];
+
+/// Where to go after the title screen or a finished run.
+pub enum Next {
+    Play,
+    /// Let the bot play a demo, chosen by the player or after idling.
+    Watch,
+    /// Two players on one keyboard.
+    Versus,
+    /// Today's daily challenge.
+    Daily,
+    Quit,
+}
+
+/// Who plays in demos.
+pub enum Pilot {
+    Bot,
+    /// A network trained by the `train` command.
+    Network(Network),
+}
+
+impl Pilot {
+    fn decide(&self, world: &World) -> Action {
+        match self {
+            Pilot::Bot => Bot::decide(world.physics(), world.height()),
+            Pilot::Network(network) => network.decide(&Observation::capture(
+                world.physics(),
+                world.height(),
+                world.cfg().env.lookahead,
+                world.score(),
+            )),
+        }
+    }
+
+    fn banner(&self) -> &'static str {
+        match self {
+            Pilot::Bot => "DEMO",
+            Pilot::Network(_) => "DEMO: TRAINED NETWORK",
+        }
+    }
+}
+
+/// Where a game is shown and played from: this process's terminal by
+/// default, or a remote one.
+pub struct Terminal {
+    pub input: InputHandler,
+    pub display: DisplaySettings,
+    pub renderer: Renderer,
+    pub player: SoundPlayer,
+}
+
+impl Terminal {
+    /// This process's terminal, also streamed to viewers when
+    /// `render.broadcast` is set.
+    pub fn local(cfg: &Cfg) -> Result<Self> {
+        let display = DisplaySettings::detect()?;
+        let color_support = ColorSupport::detect(&cfg.theme.color_mode);
+        let mut backend = BackendKind::from_config(&cfg.render.backend).create(color_support);
+        if !cfg.render.broadcast.is_empty() {
+            let addr = &cfg.render.broadcast;
+            backend = Box::new(
+                SpectatorBackend::bind(backend, addr)
+                    .wrap_err_with(|| format!("Failed to broadcast on {}", addr))?,
+            );
+        }
+
+        Ok(Self {
+            input: InputHandler::terminal(),
+            renderer: Renderer::with_backend(cfg.clone(), display.width, display.height, backend)?,
+            display,
+            player: SoundPlayer::new(),
+        })
+    }
+}
+
+/// Hosts a [`World`] in a [`Terminal`]: reads keys, plays sounds, keeps
+/// the high score file and feeds frames to the render thread.
 pub struct Game {
     cfg: Cfg,
-    physics: PhysicsEngine,
-    renderer: Renderer,
-    spawner: ObstacleSpawner,
+    world: World,
+    render: RenderThread,
+    input: InputHandler,
     display: DisplaySettings,
-    score: usize,
-    highscore: usize,
-    physics_accumulator: f32,
+    scheduler: FrameScheduler,
     player: SoundPlayer,
-    last_highscore: usize,
+    pilot: Pilot,
+    ghost: Option<Ghost>,
+    /// The day whose challenge is being played, in daily mode.
+    daily: Option<NaiveDate>,
+    /// Whether the current run goes to the leaderboard.
+    ranked: bool,
+    /// Sends finished runs to the leaderboard one at a time, started with
+    /// the first.
+    submitter: Option<Sender<Submission>>,
+    /// The leaderboard's answer to the last run, while it is on its way.
+    leaderboard: Option<Receiver<Vec<String>>>,
 }
 
+/// A run for the leaderboard, and where its game-over lines go.
+type Submission = (Replay, Sender<Vec<String>>);
+
 impl Game {
-    pub fn new() -> Result<Self> {
-        let cfg = Cfg::load()?;
-        let highscore = load_highscore();
-        let display = DisplaySettings::detect()?;
-        let physics = PhysicsEngine::new(cfg.clone(), display.width, display.height);
-        let renderer = Renderer::new(cfg.clone(), display.width, display.height);
-        let spawner = ObstacleSpawner::new(cfg.clone());
-        let player = SoundPlayer::new();
+    pub fn new(cfg: Cfg, pilot: Pilot, terminal: Terminal) -> Result<Self> {
+        let Terminal {
+            input,
+            display,
+            renderer,
+            player,
+        } = terminal;
+        let mut world = World::new(cfg.clone(), display.width, display.height);
+        world.set_highscore(load_highscore(&cfg));
+        let render = RenderThread::spawn(renderer)?;
+        let scheduler = FrameScheduler::new(cfg.render.target_fps, cfg.render.adaptive);
 
         Ok(Self {
             cfg,
-            physics,
-            renderer,
-            spawner,
+            world,
+            render,
+            input,
             display,
-            score: 0,
-            highscore,
-            physics_accumulator: 0.0,
+            scheduler,
             player,
-            last_highscore: highscore,
+            pilot,
+            ghost: None,
+            daily: None,
+            ranked: false,
+            submitter: None,
+            leaderboard: None,
         })
     }
 
-    pub fn show_countdown(&self) -> Result<()> {
-        self.renderer
-            .show_countdown(self.display.render_frame_duration)
+    pub fn title(&mut self) -> Result<Next> {
+        let idle = Instant::now();
+        let mut shown = None;
+        loop {
+            // Redrawn whenever the daily countdown ticks over.
+            let next_daily = daily::until_next(Local::now().naive_local());
+            let countdown = daily::countdown(next_daily);
+            if shown.as_ref() != Some(&countdown) {
+                self.render.send(RenderCommand::Title {
+                    highscore: self.world.highscore(),
+                    next_daily,
+                })?;
+                shown = Some(countdown);
+            }
+
+            if let Some(key) = self.input.wait_for_key()? {
+                match key {
+                    KeyCode::Char(' ')
+                    | KeyCode::Char('p')
+                    | KeyCode::Char('P')
+                    | KeyCode::Enter => {
+                        return Ok(Next::Play);
+                    }
+                    KeyCode::Char('w') | KeyCode::Char('W') => return Ok(Next::Watch),
+                    KeyCode::Char('2') => return Ok(Next::Versus),
+                    KeyCode::Char('d') | KeyCode::Char('D') => return Ok(Next::Daily),
+                    KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
+                        return Ok(Next::Quit);
+                    }
+                    _ => {}
+                }
+            }
+            if self.attract_due(idle) {
+                return Ok(Next::Watch);
+            }
+        }
+    }
+
+    /// Plays runs until the player quits or leaves the game-over screen
+    /// idle. Runs sent to a leaderboard are played on the `env` playfield,
+    /// the only size the server accepts; on a terminal too small for it
+    /// they are played unranked at the terminal's size.
+    pub fn play(&mut self) -> Result<Next> {
+        loop {
+            self.reset()?;
+            self.show_countdown()?;
+
+            if !self.run()? {
+                return Ok(Next::Quit);
+            }
+
+            match self.wait_for_restart()? {
+                Next::Play => {}
+                next => return Ok(next),
+            }
+        }
+    }
+
+    /// Plays the daily challenge until the player quits or leaves the
+    /// game-over screen idle. Every run is on the day's course at the
+    /// `env` playfield size, so everyone meets the same obstacles, and
+    /// counts toward the daily board instead of the high score. A
+    /// terminal too small for that playfield is told so instead.
+    pub fn daily(&mut self) -> Result<Next> {
+        if !self.fits_env("DAILY CHALLENGE")? {
+            return self.title();
+        }
+
+        let highscore = self.world.highscore();
+        self.daily = Some(Local::now().date_naive());
+        let next = self.play();
+
+        self.daily = None;
+        self.world = World::new(self.cfg.clone(), self.display.width, self.display.height);
+        self.world.set_highscore(highscore);
+        next
+    }
+
+    /// Whether the terminal can show the `env` playfield. If not, says so
+    /// under the `mode` played on it and waits for a key.
+    fn fits_env(&mut self, mode: &str) -> Result<bool> {
+        self.display.update_size()?;
+        let (width, height) = (self.cfg.env.width, self.cfg.env.height);
+        if self.display.width >= width && self.display.height >= height {
+            return Ok(true);
+        }
+        self.render.send(RenderCommand::Message(vec![
+            mode.to_string(),
+            String::new(),
+            format!(
+                "It needs a {}x{} terminal; this one is {}x{}.",
+                width, height, self.display.width, self.display.height
+            ),
+            String::new(),
+            "Make the window larger and try again.".to_string(),
+            "Press any key to go back".to_string(),
+        ]))?;
+        while self.input.wait_for_key()?.is_none() {}
+        Ok(false)
+    }
+
+    /// Lets the pilot play under a DEMO banner until any key is pressed.
+    /// Demo runs restart by themselves, make no sound and never touch the
+    /// high score.
+    pub fn demo(&mut self) -> Result<()> {
+        self.player.stop_music();
+        self.display.update_size()?;
+        let (width, height) = (self.display.width, self.display.height);
+        let mut world = World::new(self.cfg.clone(), width, height);
+        self.render.send(RenderCommand::Reset { width, height })?;
+        self.render.send(RenderCommand::Clear)?;
+
+        let mut last_step = Instant::now();
+        self.scheduler.restart();
+
+        loop {
+            let until_tick = world.time_until_tick().saturating_sub(last_step.elapsed());
+            self.input
+                .wait(self.scheduler.time_until_frame().min(until_tick))?;
+            if self.input.any_key()? {
+                return Ok(());
+            }
+
+            let input = Input::from(self.pilot.decide(&world));
+            let now = Instant::now();
+            world.step(now.duration_since(last_step), &[input]);
+            last_step = now;
+
+            if world.is_over() {
+                self.render.send(RenderCommand::Frame(render_frame(
+                    &world,
+                    Some(self.pilot.banner()),
+                )))?;
+                self.input.wait(DEMO_RESTART)?;
+                if self.input.any_key()? {
+                    return Ok(());
+                }
+                world.reset(width, height);
+                self.render.send(RenderCommand::Reset { width, height })?;
+                last_step = Instant::now();
+                continue;
+            }
+
+            if self.scheduler.frame_due() {
+                self.render
+                    .frame(render_frame(&world, Some(self.pilot.banner())))?;
+                self.scheduler.record_frame(self.render.last_write_time());
+            }
+        }
     }
 
-    pub fn run(&mut self) -> Result<bool> {
-        let mut last_frame = Instant::now();
+    /// Two players race identical seeded courses in lanes stacked one
+    /// above the other, round after round, until either quits. Each round
+    /// ends once both have died; whoever lasted more ticks wins it. The
+    /// high score file is left alone.
+    pub fn versus(&mut self) -> Result<()> {
+        let mut tally = Tally::default();
+        let mut best = [0; 2];
+        let mut round = 0;
+
+        loop {
+            round += 1;
+            self.display.update_size()?;
+            let (width, height) = (self.display.width, self.display.height);
+            let lane_height = (height / 2).max(LANE_MIN_HEIGHT);
+
+            // The second world takes the first one's seed, so both meet
+            // the same obstacles even when no seed is configured.
+            let first = World::new(self.cfg.clone(), width, lane_height);
+            let mut cfg = self.cfg.clone();
+            cfg.game.seed = Some(first.seed());
+            let mut worlds = [first, World::new(cfg, width, lane_height)];
+            for (world, best) in worlds.iter_mut().zip(best) {
+                world.set_highscore(best);
+            }
+
+            for remaining in (1..=3).rev() {
+                self.render.send(RenderCommand::RoundCountdown {
+                    round,
+                    remaining,
+                    wins: tally.wins,
+                })?;
+                thread::sleep(Duration::from_secs(1));
+            }
+            self.render.send(RenderCommand::Reset { width, height })?;
+            self.render.send(RenderCommand::Clear)?;
+
+            if !self.run_round(&mut worlds, lane_height)? {
+                return Ok(());
+            }
+
+            let winner = tally.record(&worlds);
+            best = worlds.each_ref().map(World::highscore);
+
+            self.render.send(RenderCommand::RoundOver {
+                winner,
+                scores: worlds.each_ref().map(World::score),
+                wins: tally.wins,
+            })?;
+            loop {
+                match self.input.wait_for_key()? {
+                    Some(KeyCode::Char('r') | KeyCode::Char('R')) => break,
+                    Some(KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc) => {
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Steps both worlds until both dinos have died. Returns `false` if a
+    /// player quit.
+    fn run_round(&mut self, worlds: &mut [World; 2], lane_height: usize) -> Result<bool> {
+        let mut last_step = Instant::now();
 
         if let Err(e) = self.player.play_bg_music() {
             eprintln!("Failed to play background music: {}", e);
         }
 
-        let mut played_new_highscore = false;
+        self.scheduler.restart();
 
         loop {
-            if !InputHandler::handle_input(&mut self.physics.dino, &self.player)? {
+            let until_tick = worlds
+                .iter()
+                .filter(|world| !world.is_over())
+                .map(World::time_until_tick)
+                .min()
+                .unwrap_or_default()
+                .saturating_sub(last_step.elapsed());
+            self.input
+                .wait(self.scheduler.time_until_frame().min(until_tick))?;
+
+            let Some(inputs) = self.input.poll_versus()? else {
                 self.player.stop_music();
                 return Ok(false);
+            };
+
+            let now = Instant::now();
+            let dt = now.duration_since(last_step);
+            last_step = now;
+
+            for (world, inputs) in worlds.iter_mut().zip(&inputs) {
+                for event in world.step(dt, inputs) {
+                    let played = match event {
+                        Event::Jumped => self.player.play_jump_sound(),
+                        Event::Died => self.player.play_death_sound(),
+                        Event::NewHighscore => Ok(()),
+                    };
+                    if let Err(e) = played {
+                        eprintln!("Failed to play sound: {}", e);
+                    }
+                }
             }
 
-            let current_time = Instant::now();
-            let frame_time = current_time.duration_since(last_frame).as_secs_f32();
-            last_frame = current_time;
+            if worlds.iter().all(World::is_over) {
+                self.render.send(RenderCommand::Lanes {
+                    height: lane_height,
+                    lanes: lane_frames(worlds),
+                })?;
+                thread::sleep(Duration::from_secs_f32(0.25));
+                self.player.stop_music();
+                return Ok(true);
+            }
 
-            self.physics_accumulator += frame_time;
-            self.physics_accumulator = self.physics_accumulator.min(0.25);
+            if self.scheduler.frame_due() {
+                self.render.lanes(lane_height, lane_frames(worlds))?;
+                self.scheduler.record_frame(self.render.last_write_time());
+            }
+        }
+    }
 
-            while self.physics_accumulator >= self.cfg.physics.fixed_timestep {
-                self.spawner.update_difficulty(self.score);
+    fn attract_due(&self, idle_since: Instant) -> bool {
+        let after = self.cfg.game.attract_after;
+        after > 0.0 && idle_since.elapsed().as_secs_f32() >= after
+    }
 
-                if !self.physics.update(
-                    &self.spawner,
-                    self.display.width,
-                    self.display.height,
-                    &mut self.score,
-                ) {
-                    self.handle_game_over()?;
-                    return Ok(true);
-                }
+    fn show_countdown(&mut self) -> Result<()> {
+        for remaining in (1..=3).rev() {
+            self.render.send(RenderCommand::Countdown {
+                remaining,
+                frame_duration: self.scheduler.target(),
+            })?;
+            thread::sleep(Duration::from_secs(1));
+        }
+        self.render.send(RenderCommand::Clear)
+    }
 
-                if self.score > self.highscore {
-                    let was_old_highscore = self.highscore == self.last_highscore;
-                    self.highscore = self.score;
+    fn run(&mut self) -> Result<bool> {
+        let mut last_step = Instant::now();
+        let mut personal_best = false;
 
-                    if was_old_highscore {
-                        if !played_new_highscore && let Err(e) = self.player.play_high_score_sound()
-                        {
-                            eprintln!("Failed to play high score sound: {}", e);
-                        }
+        if let Err(e) = self.player.play_bg_music() {
+            eprintln!("Failed to play background music: {}", e);
+        }
+
+        self.scheduler.restart();
+
+        loop {
+            let until_tick = self
+                .world
+                .time_until_tick()
+                .saturating_sub(last_step.elapsed());
+            self.input
+                .wait(self.scheduler.time_until_frame().min(until_tick))?;
+
+            let Some(inputs) = self.input.poll()? else {
+                self.player.stop_music();
+                return Ok(false);
+            };
 
-                        played_new_highscore = true;
+            let now = Instant::now();
+            let events = self.world.step(now.duration_since(last_step), &inputs);
+            last_step = now;
+            if let Some(ghost) = &mut self.ghost {
+                ghost.follow(self.world.ticks());
+            }
 
-                        self.last_highscore = self.highscore;
+            for event in events {
+                match event {
+                    Event::Jumped => {
+                        if let Err(e) = self.player.play_jump_sound() {
+                            eprintln!("Failed to play jump sound: {}", e);
+                        }
+                    }
+                    Event::NewHighscore => {
+                        personal_best = true;
+                        if let Err(e) = self.player.play_high_score_sound() {
+                            eprintln!("Failed to play high score sound: {}", e);
+                        }
+                    }
+                    Event::Died => {
+                        self.render.send(RenderCommand::Frame(self.frame()))?;
+                        self.handle_game_over(personal_best)?;
+                        return Ok(true);
                     }
                 }
+            }
 
-                self.physics_accumulator -= self.cfg.physics.fixed_timestep;
+            if self.scheduler.frame_due() {
+                self.render.frame(self.frame())?;
+                self.scheduler.record_frame(self.render.last_write_time());
             }
+        }
+    }
 
-            self.renderer.draw(DrawArgs::new(
-                self.physics.dino.state,
-                self.physics.dino.y,
-                &self.physics.obstacles,
-                &self.physics.clouds,
-                self.score,
-                self.highscore,
-                self.physics.speed,
-            ))?;
+    fn frame(&self) -> RenderFrame {
+        let banner = self.daily.map(|_| "DAILY CHALLENGE");
+        let mut frame = render_frame(&self.world, banner);
+        frame.ghosts = self
+            .ghost
+            .iter()
+            .map(|ghost| ghost.args(self.world.score()))
+            .collect();
+        frame
+    }
 
-            std::thread::sleep(Duration::from_millis(1));
-        }
+    /// Submits the finished run to the leaderboard in the background. The
+    /// answer comes back as lines for the game-over screen: where the run
+    /// placed, then the board's best runs. Runs go through one worker, so
+    /// only it touches the queue file.
+    fn submit_run(&mut self) -> Receiver<Vec<String>> {
+        let (sender, answer) = mpsc::channel();
+        let cfg = &self.cfg;
+        let submitter = self.submitter.get_or_insert_with(|| {
+            let (submitter, submissions) = mpsc::channel::<Submission>();
+            let cfg = cfg.clone();
+            thread::spawn(move || {
+                for (replay, answer) in submissions {
+                    answer.send(leaderboard_lines(&cfg, &replay)).ok();
+                }
+            });
+            submitter
+        });
+        submitter.send((self.world.replay().clone(), sender)).ok();
+        answer
     }
 
-    pub fn wait_for_restart(&mut self) -> Result<bool> {
+    /// Stops the render thread once everything queued has been written.
+    pub fn finish(self) -> Result<()> {
+        self.render.finish()
+    }
+
+    fn wait_for_restart(&mut self) -> Result<Next> {
+        let idle = Instant::now();
         loop {
-            if let Some(key) = InputHandler::wait_for_key()? {
+            if let Some(board) = self
+                .leaderboard
+                .as_ref()
+                .and_then(|answer| answer.try_recv().ok())
+            {
+                self.leaderboard = None;
+                self.render.send(RenderCommand::GameOver {
+                    score: self.world.score(),
+                    highscore: self.world.highscore(),
+                    board,
+                })?;
+            }
+            if let Some(key) = self.input.wait_for_key()? {
                 match key {
-                    KeyCode::Char('r') | KeyCode::Char('R') => {
-                        self.reset()?;
-                        return Ok(true);
-                    }
+                    KeyCode::Char('r') | KeyCode::Char('R') => return Ok(Next::Play),
                     KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
-                        return Ok(false);
+                        return Ok(Next::Quit);
                     }
                     _ => {}
                 }
             }
+            if self.attract_due(idle) {
+                return Ok(Next::Watch);
+            }
         }
     }
 
     fn reset(&mut self) -> Result<()> {
         self.display.update_size()?;
-        self.physics.reset(self.display.width, self.display.height);
-        self.renderer
-            .update_dimensions(self.display.width, self.display.height);
-        self.score = 0;
-        self.physics_accumulator = 0.0;
-        self.spawner = ObstacleSpawner::new(self.cfg.clone());
-        Ok(())
+        // An answer still on its way belongs to the last run's screen.
+        self.leaderboard = None;
+        let (env_width, env_height) = (self.cfg.env.width, self.cfg.env.height);
+        self.ranked = self.daily.is_none()
+            && !self.cfg.leaderboard.server.is_empty()
+            && self.display.width >= env_width
+            && self.display.height >= env_height;
+        let (width, height) = match self.daily {
+            Some(_) => {
+                // A run counts for the day it starts on.
+                let today = Local::now().date_naive();
+                self.daily = Some(today);
+                let mut cfg = self.cfg.clone();
+                cfg.game.seed = Some(daily::seed(today));
+                let (width, height) = (cfg.env.width, cfg.env.height);
+                let best = match DailyBoard::load(Path::new(&self.cfg.daily_file)) {
+                    Ok(board) => board.day(today).map_or(0, |entry| entry.best),
+                    Err(e) => {
+                        eprintln!("Failed to load the daily board: {}", e);
+                        0
+                    }
+                };
+                self.world = World::new(cfg, width, height);
+                self.world.set_highscore(best);
+                (width, height)
+            }
+            None => {
+                let (width, height) = if self.ranked {
+                    (env_width, env_height)
+                } else {
+                    (self.display.width, self.display.height)
+                };
+                self.world.reset(width, height);
+                (width, height)
+            }
+        };
+        self.ghost = match load_ghost(&self.cfg, &self.world) {
+            Ok(ghost) => ghost,
+            Err(e) => {
+                eprintln!("Failed to load ghost: {}", e);
+                None
+            }
+        };
+        self.render.send(RenderCommand::Reset { width, height })
     }
 
-    fn handle_game_over(&self) -> Result<()> {
+    /// `personal_best` says whether the run beat the high score it started
+    /// with.
+    fn handle_game_over(&mut self, personal_best: bool) -> Result<()> {
         if let Err(e) = self.player.play_death_sound() {
             eprintln!("Failed to play death sound: {}", e);
         }
 
-        std::thread::sleep(Duration::from_secs_f32(0.25));
+        thread::sleep(Duration::from_secs_f32(0.25));
 
         self.player.stop_music();
         if let Err(e) = self.player.play_death_screen_music() {
             eprintln!("Failed to play death screen music: {}", e);
         }
 
-        self.renderer.show_game_over(self.score, self.highscore)?;
-        save_highscore(self.highscore);
+        let score = self.world.score();
+        match self.daily {
+            Some(date) => {
+                let (entry, streak) = record_daily(&self.cfg, date, score).unwrap_or_else(|e| {
+                    eprintln!("Failed to update the daily board: {}", e);
+                    let mut board = DailyBoard::default();
+                    (board.record(date, score).clone(), 1)
+                });
+                self.render.send(RenderCommand::DailyOver {
+                    score,
+                    entry,
+                    streak,
+                    next_daily: daily::until_next(Local::now().naive_local()),
+                })?;
+            }
+            None => {
+                let board = if self.ranked {
+                    self.leaderboard = Some(self.submit_run());
+                    vec!["Sending your run to the leaderboard...".to_string()]
+                } else if !self.cfg.leaderboard.server.is_empty() {
+                    vec![format!(
+                        "Not ranked: the leaderboard takes {}x{} runs, more than fits here",
+                        self.cfg.env.width, self.cfg.env.height
+                    )]
+                } else {
+                    Vec::new()
+                };
+                self.render.send(RenderCommand::GameOver {
+                    score,
+                    highscore: self.world.highscore(),
+                    board,
+                })?;
+                save_highscore(&self.cfg, self.world.highscore());
+            }
+        }
+        if let Err(e) = save_replay(&self.cfg, self.world.replay()) {
+            eprintln!("Failed to save replay: {}", e);
+        }
+
+        let beat_ghost = self
+            .ghost
+            .as_ref()
+            .is_some_and(|ghost| self.world.score() > ghost.score());
+        if (personal_best || beat_ghost)
+            && let Err(e) = save_ghost(&self.cfg, self.world.replay())
+        {
+            eprintln!("Failed to save ghost: {}", e);
+        }
         Ok(())
     }
 }
 
-fn load_highscore() -> usize {
-    let cfg = Cfg::load().expect("Failed to load config");
-    fs::read_to_string(cfg.highscore_file)
+pub fn render_frame(world: &World, banner: Option<&'static str>) -> RenderFrame {
+    RenderFrame {
+        snapshot: world.frame(),
+        score: world.score(),
+        highscore: world.highscore(),
+        banner,
+        ghosts: Vec::new(),
+    }
+}
+
+fn lane_frames(worlds: &[World; 2]) -> Vec<(RenderFrame, &'static str)> {
+    worlds
+        .iter()
+        .zip(LANE_LABELS)
+        .map(|(world, [alive, out])| {
+            let label = if world.is_over() { out } else { alive };
+            (render_frame(world, None), label)
+        })
+        .collect()
+}
+
+fn load_highscore(cfg: &Cfg) -> usize {
+    if cfg.highscore_file.is_empty() {
+        return 0;
+    }
+    fs::read_to_string(&cfg.highscore_file)
         .ok()
         .and_then(|s| s.trim().parse().ok())
         .unwrap_or(0)
 }
 
-fn save_highscore(highscore: usize) {
-    let cfg = Cfg::load().expect("Failed to load config");
-    fs::write(cfg.highscore_file, highscore.to_string()).ok();
+fn save_highscore(cfg: &Cfg, highscore: usize) {
+    if cfg.highscore_file.is_empty() {
+        return;
+    }
+    fs::write(&cfg.highscore_file, highscore.to_string()).ok();
+}
+
+/// Writes `replay` into `replay_dir`, if one is set, named by the time and
+/// score, and signed when there is a `key_file`.
+fn save_replay(cfg: &Cfg, replay: &Replay) -> Result<()> {
+    if cfg.replay_dir.is_empty() {
+        return Ok(());
+    }
+    let dir = Path::new(&cfg.replay_dir);
+    fs::create_dir_all(dir)?;
+    let replay = signed(cfg, replay)?;
+    let name = format!(
+        "{}-{}.json",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        replay.score
+    );
+    fs::write(dir.join(name), serde_json::to_string(&replay)?)?;
+    Ok(())
+}
+
+/// A copy of `replay`, signed when there is a `key_file`.
+fn signed(cfg: &Cfg, replay: &Replay) -> Result<Replay> {
+    let mut replay = replay.clone();
+    if !cfg.key_file.is_empty() {
+        replay.sign(&replay::load_key(Path::new(&cfg.key_file))?);
+    }
+    Ok(replay)
+}
+
+fn leaderboard_lines(cfg: &Cfg, replay: &Replay) -> Vec<String> {
+    let settings = &cfg.leaderboard;
+    let client = LeaderboardClient::new(settings.server.clone(), &settings.queue_file);
+    let submitted = signed(cfg, replay).and_then(|replay| {
+        client
+            .submit(&player_name(&settings.name), replay)
+            .wrap_err("Failed to queue the run")
+    });
+    let mut lines = vec![match &submitted {
+        Ok(Submitted::Accepted { rank }) => format!("Leaderboard: your run placed #{}", rank),
+        Ok(Submitted::Rejected { reason }) => format!("Leaderboard rejected the run: {}", reason),
+        Ok(Submitted::Queued) => "Leaderboard unreachable; the run will be sent later".to_string(),
+        Err(e) => format!("Leaderboard: {}", e),
+    }];
+    if settings.top == 0 {
+        return lines;
+    }
+    match client.top(settings.top) {
+        Ok(entries) => lines.extend(
+            entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| format!("{:>2}. {:<20} {:>6}", i + 1, entry.name, entry.score)),
+        ),
+        Err(e) => lines.push(format!("Failed to fetch the leaderboard: {}", e)),
+    }
+    lines
+}
+
+/// `name`, or the login name when it is empty.
+fn player_name(name: &str) -> String {
+    if !name.is_empty() {
+        return name.to_string();
+    }
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "anonymous".to_string())
+}
+
+/// Counts a run of `date`'s challenge on the daily board and returns the
+/// day's entry and the streak. A board that fails to load is left as is.
+fn record_daily(cfg: &Cfg, date: NaiveDate, score: usize) -> Result<(DailyEntry, u32)> {
+    let path = Path::new(&cfg.daily_file);
+    let mut board = DailyBoard::load(path)?;
+    let entry = board.record(date, score).clone();
+    if !cfg.daily_file.is_empty() {
+        board.save(path)?;
+    }
+    Ok((entry, board.streak(date)))
+}
+
+fn ghost_path(cfg: &Cfg, seed: u64) -> Option<PathBuf> {
+    if cfg.ghost_dir.is_empty() {
+        return None;
+    }
+    Some(Path::new(&cfg.ghost_dir).join(format!("{}.json", seed)))
+}
+
+/// The best earlier run on `world`'s seed, if one was saved at the same
+/// playfield size.
+fn load_ghost(cfg: &Cfg, world: &World) -> Result<Option<Ghost>> {
+    let Some(path) = ghost_path(cfg, world.seed()).filter(|path| path.exists()) else {
+        return Ok(None);
+    };
+    let replay: Replay = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    let ghost = Ghost::new(replay, cfg);
+    Ok(ghost.matches(world).then_some(ghost))
+}
+
+fn save_ghost(cfg: &Cfg, replay: &Replay) -> Result<()> {
+    let Some(path) = ghost_path(cfg, replay.seed) else {
+        return Ok(());
+    };
+    fs::create_dir_all(&cfg.ghost_dir)?;
+    fs::write(path, serde_json::to_string(replay)?)?;
+    Ok(())
 }
@@ -0,0 +1,97 @@
+use crate::{config::Cfg, rendering::GhostArgs, replay::Replay, world::World};
+
+/// The best earlier run on the same seed, raced alongside the player. Its
+/// recorded inputs drive a second world, so its dino meets the same
+/// obstacles at the same ticks.
+pub struct Ghost {
+    replay: Replay,
+    world: World,
+}
+
+impl Ghost {
+    pub fn new(replay: Replay, cfg: &Cfg) -> Self {
+        let world = replay.world(cfg);
+        Self { replay, world }
+    }
+
+    /// Whether the ghost's run started like `world`'s: same seed,
+    /// playfield size and gameplay settings, without which the obstacles
+    /// would differ.
+    pub fn matches(&self, world: &World) -> bool {
+        self.replay.seed == world.seed()
+            && self.replay.width == world.width()
+            && self.replay.height == world.height()
+            && self.replay.config_hash == world.replay().config_hash
+    }
+
+    /// Plays the ghost's inputs until it has run `ticks` ticks, the
+    /// player's count, or died.
+    pub fn follow(&mut self, ticks: u64) {
+        while !self.world.is_over() && self.world.ticks() < ticks {
+            let inputs: Vec<_> = self.replay.inputs_at(self.world.ticks()).collect();
+            self.world.tick(&inputs);
+        }
+    }
+
+    /// The score the ghost's run ended with.
+    pub fn score(&self) -> usize {
+        self.replay.score
+    }
+
+    /// How to draw the ghost next to a player who has `score` points. The
+    /// lead is measured against the ghost's score at the same tick.
+    pub fn args(&self, score: usize) -> GhostArgs {
+        let dino = &self.world.physics().dino;
+        GhostArgs {
+            state: dino.state,
+            y: dino.y,
+            visible: !self.world.is_over(),
+            lead: score as i64 - self.world.score() as i64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::world::Input};
+
+    #[test]
+    fn ghost_retraces_the_recorded_run() {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(4);
+        let inputs = |tick: u64| match tick % 30 {
+            0 => vec![Input::Jump],
+            _ => vec![Input::Stand],
+        };
+
+        let mut first = World::new(cfg.clone(), 80, 24);
+        while !first.is_over() {
+            first.tick(&inputs(first.ticks()));
+        }
+        let mut ghost = Ghost::new(first.replay().clone(), &cfg);
+
+        let mut faster = cfg.clone();
+        faster.physics.initial_speed *= 2.0;
+        assert!(!ghost.matches(&World::new(faster, 80, 24)));
+
+        let mut second = World::new(cfg, 80, 24);
+        assert!(ghost.matches(&second));
+        let mut checked_mid_run = false;
+        while !second.is_over() {
+            second.tick(&inputs(second.ticks()));
+            ghost.follow(second.ticks());
+
+            // Level with the ghost all the way, not only once it has finished.
+            let args = ghost.args(second.score());
+            assert_eq!(args.y, second.physics().dino.y);
+            assert_eq!(args.visible, !second.is_over());
+            assert_eq!(args.lead, 0);
+            checked_mid_run |= second.score() > 0 && second.score() < ghost.score();
+
+            // A few points further on would put the player ahead.
+            assert_eq!(ghost.args(second.score() + 3).lead, 3);
+        }
+        assert!(checked_mid_run);
+        assert_eq!(ghost.args(second.score()).lead, 0);
+    }
+}
@@ -1,83 +1,170 @@
 use {
-    crate::{
-        config::Cfg,
-        sound::SoundPlayer,
-        types::{Dino, DinoState},
-    },
     crossterm::event::{self, Event, KeyCode},
-    std::time::Duration,
+    dino_rs::{telnet::Key, world::Input},
+    std::{
+        collections::VecDeque,
+        sync::mpsc::{Receiver, RecvTimeoutError},
+        time::Duration,
+    },
 };
 
-pub struct InputHandler;
+/// Reads key presses from this process's terminal, or from a remote one
+/// whose keys arrive over a channel.
+pub struct InputHandler {
+    remote: Option<Remote>,
+}
+
+struct Remote {
+    keys: Receiver<Key>,
+    /// Keys [`InputHandler::wait`] took off the channel but nobody has read.
+    pending: VecDeque<KeyCode>,
+}
 
 impl InputHandler {
-    pub fn handle_input(dino: &mut Dino, player: &SoundPlayer) -> color_eyre::Result<bool> {
-        let cfg = Cfg::load()?;
-        while event::poll(Duration::from_millis(0))? {
-            if let Event::Key(key_event) = event::read()? {
-                match key_event.code {
-                    KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
-                        return Ok(false);
-                    }
-                    KeyCode::Char(' ') | KeyCode::Up => {
-                        if Self::jump(dino, cfg.clone())
-                            && let Err(e) = player.play_jump_sound()
-                        {
-                            eprintln!("Failed to play jump sound: {}", e);
-                        }
-                    }
-                    KeyCode::Down => {
-                        Self::start_crouch(dino);
-                    }
-                    _ => {}
+    pub fn terminal() -> Self {
+        Self { remote: None }
+    }
+
+    /// Reads keys sent on `keys`. Once the sender hangs up every read
+    /// returns Esc, so whatever screen is showing quits.
+    pub fn remote(keys: Receiver<Key>) -> Self {
+        Self {
+            remote: Some(Remote {
+                keys,
+                pending: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Drains pending key presses into game inputs, or returns `None` when
+    /// the player asked to quit.
+    pub fn poll(&mut self) -> color_eyre::Result<Option<Vec<Input>>> {
+        let mut inputs = Vec::new();
+        let mut crouching = false;
+
+        while let Some(key) = self.next_key(Duration::ZERO)? {
+            match key {
+                KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char(' ') | KeyCode::Up => inputs.push(Input::Jump),
+                KeyCode::Down => {
+                    inputs.push(Input::Crouch);
+                    crouching = true;
                 }
+                _ => {}
             }
         }
 
-        if matches!(dino.state, DinoState::Crouching) && !Self::is_down_pressed()? {
-            Self::stop_crouch(dino);
+        // Terminals only report key presses, so a crouch lasts as long as
+        // the key keeps repeating.
+        if !crouching {
+            inputs.push(Input::Stand);
         }
 
-        Ok(true)
+        Ok(Some(inputs))
     }
 
-    pub fn wait_for_key() -> color_eyre::Result<Option<KeyCode>> {
-        if event::poll(Duration::from_millis(100))?
-            && let Event::Key(key_event) = event::read()?
-        {
-            return Ok(Some(key_event.code));
+    /// Like [`InputHandler::poll`], with one set of inputs per player:
+    /// W and S for player one, Up and Down for player two.
+    pub fn poll_versus(&mut self) -> color_eyre::Result<Option<[Vec<Input>; 2]>> {
+        let mut inputs = [Vec::new(), Vec::new()];
+        let mut crouching = [false; 2];
+
+        while let Some(key) = self.next_key(Duration::ZERO)? {
+            let (player, input) = match key {
+                KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
+                    return Ok(None);
+                }
+                KeyCode::Char('w') | KeyCode::Char('W') => (0, Input::Jump),
+                KeyCode::Char('s') | KeyCode::Char('S') => (0, Input::Crouch),
+                KeyCode::Up => (1, Input::Jump),
+                KeyCode::Down => (1, Input::Crouch),
+                _ => continue,
+            };
+            crouching[player] |= input == Input::Crouch;
+            inputs[player].push(input);
         }
-        Ok(None)
-    }
 
-    fn jump(dino: &mut Dino, cfg: Cfg) -> bool {
-        if matches!(dino.state, DinoState::Running | DinoState::Crouching) {
-            dino.state = DinoState::Jumping;
-            dino.velocity_y = cfg.physics.jump_velocity;
-            true
-        } else {
-            false
+        for (inputs, crouching) in inputs.iter_mut().zip(crouching) {
+            if !crouching {
+                inputs.push(Input::Stand);
+            }
         }
+
+        Ok(Some(inputs))
     }
 
-    fn start_crouch(dino: &mut Dino) {
-        dino.state = DinoState::Crouching;
+    /// Blocks until an input event is pending or `timeout` passes, without
+    /// consuming the event.
+    pub fn wait(&mut self, timeout: Duration) -> color_eyre::Result<bool> {
+        let Some(remote) = &mut self.remote else {
+            return Ok(event::poll(timeout)?);
+        };
+        if !remote.pending.is_empty() {
+            return Ok(true);
+        }
+        match remote.keys.recv_timeout(timeout) {
+            Ok(key) => remote.pending.push_back(key_code(key)),
+            Err(RecvTimeoutError::Timeout) => return Ok(false),
+            Err(RecvTimeoutError::Disconnected) => remote.pending.push_back(KeyCode::Esc),
+        }
+        Ok(true)
     }
 
-    fn stop_crouch(dino: &mut Dino) {
-        if matches!(dino.state, DinoState::Crouching) {
-            dino.state = DinoState::Running;
+    /// Drains pending events and reports whether any was a key press.
+    pub fn any_key(&mut self) -> color_eyre::Result<bool> {
+        let mut pressed = false;
+        while let Some(key) = self.next_key(Duration::ZERO)? {
+            pressed = true;
+            // A closed remote keeps answering Esc.
+            if key == KeyCode::Esc {
+                break;
+            }
         }
+        Ok(pressed)
+    }
+
+    pub fn wait_for_key(&mut self) -> color_eyre::Result<Option<KeyCode>> {
+        self.next_key(Duration::from_millis(100))
     }
 
-    fn is_down_pressed() -> color_eyre::Result<bool> {
-        while event::poll(Duration::from_millis(0))? {
-            if let Event::Key(key_event) = event::read()?
-                && matches!(key_event.code, KeyCode::Down)
-            {
-                return Ok(true);
+    /// The next key press, waiting up to `timeout` for one. Other terminal
+    /// events are skipped.
+    fn next_key(&mut self, timeout: Duration) -> color_eyre::Result<Option<KeyCode>> {
+        let Some(remote) = &mut self.remote else {
+            if !event::poll(timeout)? {
+                return Ok(None);
             }
+            loop {
+                if let Event::Key(key_event) = event::read()? {
+                    return Ok(Some(key_event.code));
+                }
+                if !event::poll(Duration::ZERO)? {
+                    return Ok(None);
+                }
+            }
+        };
+
+        if let Some(key) = remote.pending.pop_front() {
+            return Ok(Some(key));
         }
-        Ok(false)
+        match remote.keys.recv_timeout(timeout) {
+            Ok(key) => Ok(Some(key_code(key))),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Ok(Some(KeyCode::Esc)),
+        }
+    }
+}
+
+fn key_code(key: Key) -> KeyCode {
+    match key {
+        Key::Char(c) => KeyCode::Char(c),
+        Key::Up => KeyCode::Up,
+        Key::Down => KeyCode::Down,
+        Key::Left => KeyCode::Left,
+        Key::Right => KeyCode::Right,
+        Key::Enter => KeyCode::Enter,
+        Key::Esc => KeyCode::Esc,
     }
 }
@@ -0,0 +1,578 @@
+//! A leaderboard anyone can host: a [`LeaderboardServer`] keeps submitted
+//! runs in a local file and a [`LeaderboardClient`] submits runs and reads
+//! the best ones back, one JSON request and reply per connection.
+//!
+//! Submissions carry their [`Replay`], which the server plays again with
+//! [`Replay::verify`] before accepting the score, so only runs recorded
+//! under the server's gameplay settings and `env` playfield size (and
+//! signed with its key, if it has one) make it onto the board.
+//! Submissions the client cannot deliver wait in a queue file until the
+//! server is back.
+
+use {
+    crate::{config::Cfg, replay::Replay},
+    serde::{Deserialize, Serialize},
+    std::{
+        cmp::Reverse,
+        fs::{self, OpenOptions},
+        io::{self, BufRead, BufReader, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    },
+};
+
+/// How long either end waits on the other.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest request the server reads, which bounds a replay's size.
+const MAX_REQUEST: u64 = 8 << 20;
+/// Longest run the server plays back, which bounds the time one
+/// submission can take.
+const MAX_RUN: Duration = Duration::from_secs(2 * 60 * 60);
+/// Most inputs a tick can record: a jump, a crouch and a stand.
+const MAX_INPUTS_PER_TICK: u64 = 3;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Submit {
+        name: String,
+        replay: Replay,
+    },
+    /// The `count` best runs.
+    Top {
+        count: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Reply {
+    /// The run is on the board at `rank`, counting from 1.
+    Accepted {
+        rank: usize,
+    },
+    Rejected {
+        reason: String,
+    },
+    Top {
+        entries: Vec<Entry>,
+    },
+}
+
+/// One run on the board.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: usize,
+    pub seed: u64,
+    /// When the server accepted it, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+/// A run as the server stores it, one per line.
+#[derive(Clone, Serialize, Deserialize)]
+struct Submission {
+    name: String,
+    date: String,
+    replay: Replay,
+}
+
+impl Submission {
+    fn entry(&self) -> Entry {
+        Entry {
+            name: self.name.clone(),
+            score: self.replay.score,
+            seed: self.replay.seed,
+            date: self.date.clone(),
+        }
+    }
+}
+
+fn write_line(stream: &mut TcpStream, value: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+fn read_line<T: for<'de> Deserialize<'de>>(stream: &TcpStream) -> io::Result<T> {
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_REQUEST)).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Every accepted run, best first, backed by an append-only file.
+struct Board {
+    path: PathBuf,
+    submissions: Vec<Submission>,
+}
+
+impl Board {
+    /// Reads the runs stored at `path`. Lines that do not parse, such as
+    /// one cut short by a crash, are skipped.
+    fn load(path: &Path) -> io::Result<Self> {
+        let mut submissions: Vec<Submission> = match fs::read_to_string(path) {
+            Ok(text) => text
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        submissions.sort_by_key(|submission| Reverse(submission.replay.score));
+        Ok(Self {
+            path: path.to_path_buf(),
+            submissions,
+        })
+    }
+
+    /// Stores `submission` and returns its rank. Ties go to the earlier
+    /// run.
+    fn add(&mut self, submission: Submission) -> io::Result<usize> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut line = serde_json::to_string(&submission)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+
+        let score = submission.replay.score;
+        let index = self
+            .submissions
+            .partition_point(|earlier| earlier.replay.score >= score);
+        self.submissions.insert(index, submission);
+        Ok(index + 1)
+    }
+
+    fn top(&self, count: usize) -> Vec<Entry> {
+        self.submissions
+            .iter()
+            .take(count)
+            .map(Submission::entry)
+            .collect()
+    }
+}
+
+/// Serves a leaderboard stored at a local path. Runs are verified under
+/// `cfg`'s gameplay settings and, with a `key`, must be signed with it.
+pub struct LeaderboardServer {
+    listener: TcpListener,
+    board: Arc<Mutex<Board>>,
+    cfg: Arc<Cfg>,
+    key: Option<Arc<[u8]>>,
+}
+
+impl LeaderboardServer {
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        store: &Path,
+        cfg: Cfg,
+        key: Option<Vec<u8>>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            board: Arc::new(Mutex::new(Board::load(store)?)),
+            cfg: Arc::new(cfg),
+            key: key.map(Into::into),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answers requests until the listener fails, each connection on its
+    /// own thread.
+    pub fn run(&self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let (board, cfg, key) = (
+                Arc::clone(&self.board),
+                Arc::clone(&self.cfg),
+                self.key.clone(),
+            );
+            thread::Builder::new()
+                .name("leaderboard".to_string())
+                .spawn(move || {
+                    // A client that hangs up early has nothing to hear.
+                    answer(stream, &board, &cfg, key.as_deref()).ok();
+                })?;
+        }
+    }
+}
+
+fn answer(
+    mut stream: TcpStream,
+    board: &Mutex<Board>,
+    cfg: &Cfg,
+    key: Option<&[u8]>,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let reply = match read_line(&stream) {
+        Ok(Request::Submit { name, replay }) => match screen(&replay, cfg).and_then(|()| {
+            replay
+                .verify(cfg, key)
+                .map_err(|rejection| rejection.to_string())
+        }) {
+            Ok(_) => {
+                let submission = Submission {
+                    name: name.trim().chars().take(20).collect(),
+                    date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+                    replay,
+                };
+                let rank = board
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .add(submission)?;
+                Reply::Accepted { rank }
+            }
+            Err(reason) => Reply::Rejected { reason },
+        },
+        Ok(Request::Top { count }) => Reply::Top {
+            entries: board.lock().unwrap_or_else(|e| e.into_inner()).top(count),
+        },
+        Err(e) => Reply::Rejected {
+            reason: format!("bad request: {}", e),
+        },
+    };
+    write_line(&mut stream, &reply)
+}
+
+/// Turns down runs not worth playing back: on another playfield than
+/// the server's, longer than [`MAX_RUN`], or with more inputs than their
+/// ticks allow.
+fn screen(replay: &Replay, cfg: &Cfg) -> Result<(), String> {
+    // A wider field shows obstacles sooner, so scores only compare on
+    // one size.
+    let (expected, found) = (
+        (cfg.env.width, cfg.env.height),
+        (replay.width, replay.height),
+    );
+    if found != expected {
+        return Err(format!(
+            "played on a {}x{} playfield, the board takes {}x{}",
+            found.0, found.1, expected.0, expected.1
+        ));
+    }
+    let max_ticks = (MAX_RUN.as_secs_f32() / cfg.physics.fixed_timestep) as u64;
+    if replay.ticks > max_ticks {
+        return Err(format!("the run is longer than {} ticks", max_ticks));
+    }
+    if replay.inputs.len() as u64 > replay.ticks.saturating_mul(MAX_INPUTS_PER_TICK) {
+        return Err("the run has more inputs than its ticks allow".to_string());
+    }
+    Ok(())
+}
+
+/// What became of a submission.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Submitted {
+    Accepted {
+        rank: usize,
+    },
+    Rejected {
+        reason: String,
+    },
+    /// The server could not be reached; the run waits in the queue.
+    Queued,
+}
+
+/// Talks to the leaderboard at `addr`, keeping undelivered runs in
+/// `queue`, one request per line.
+pub struct LeaderboardClient {
+    addr: String,
+    queue: PathBuf,
+}
+
+impl LeaderboardClient {
+    pub fn new(addr: impl Into<String>, queue: impl Into<PathBuf>) -> Self {
+        Self {
+            addr: addr.into(),
+            queue: queue.into(),
+        }
+    }
+
+    fn request(&self, request: &Request) -> io::Result<Reply> {
+        let addr = self.addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no address for {}", self.addr),
+            )
+        })?;
+        let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        write_line(&mut stream, request)?;
+        read_line(&stream)
+    }
+
+    /// The `count` best runs on the board.
+    pub fn top(&self, count: usize) -> io::Result<Vec<Entry>> {
+        match self.request(&Request::Top { count })? {
+            Reply::Top { entries } => Ok(entries),
+            reply => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected reply {:?}", reply),
+            )),
+        }
+    }
+
+    /// Sends queued runs and then this one. A run the server cannot be
+    /// reached for joins the queue; errors only come from the queue file.
+    pub fn submit(&self, name: &str, replay: Replay) -> io::Result<Submitted> {
+        let (_, reached) = self.send_queue()?;
+        let request = Request::Submit {
+            name: name.to_string(),
+            replay,
+        };
+        if !reached {
+            self.enqueue(&request)?;
+            return Ok(Submitted::Queued);
+        }
+        match self.request(&request) {
+            Ok(reply) => Ok(submitted(reply)),
+            Err(_) => {
+                self.enqueue(&request)?;
+                Ok(Submitted::Queued)
+            }
+        }
+    }
+
+    /// Sends queued runs, oldest first, and returns what became of each.
+    /// The first one that cannot be delivered stops the rest, which stay
+    /// queued, rather than waiting out the timeout for every run.
+    pub fn flush(&self) -> io::Result<Vec<Submitted>> {
+        self.send_queue().map(|(results, _)| results)
+    }
+
+    /// [`LeaderboardClient::flush`], also saying whether the server took
+    /// every queued run.
+    fn send_queue(&self) -> io::Result<(Vec<Submitted>, bool)> {
+        let text = match fs::read_to_string(&self.queue) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((Vec::new(), true)),
+            Err(e) => return Err(e),
+        };
+
+        let mut results = Vec::new();
+        let mut lines = text.lines();
+        let mut waiting = String::new();
+        for line in lines.by_ref() {
+            let Ok(request) = serde_json::from_str::<Request>(line) else {
+                continue;
+            };
+            match self.request(&request) {
+                Ok(reply) => results.push(submitted(reply)),
+                Err(_) => {
+                    waiting.push_str(line);
+                    waiting.push('\n');
+                    break;
+                }
+            }
+        }
+        for line in lines {
+            waiting.push_str(line);
+            waiting.push('\n');
+        }
+
+        let reached = waiting.is_empty();
+        if reached {
+            fs::remove_file(&self.queue)?;
+        } else {
+            fs::write(&self.queue, waiting)?;
+        }
+        Ok((results, reached))
+    }
+
+    /// Runs waiting to be sent.
+    pub fn queued(&self) -> usize {
+        fs::read_to_string(&self.queue).map_or(0, |text| text.lines().count())
+    }
+
+    fn enqueue(&self, request: &Request) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.queue)?;
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+}
+
+fn submitted(reply: Reply) -> Submitted {
+    match reply {
+        Reply::Accepted { rank } => Submitted::Accepted { rank },
+        Reply::Rejected { reason } => Submitted::Rejected { reason },
+        Reply::Top { .. } => Submitted::Rejected {
+            reason: "unexpected reply".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::world::{Input, World},
+        std::sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// A path under the system temp dir, unique to this test process.
+    fn scratch(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dino-{}-{}", std::process::id(), name));
+        fs::remove_file(&path).ok();
+        path
+    }
+
+    /// A finished run on `seed`, jumping every `every` ticks.
+    fn run(cfg: &Cfg, seed: u64, every: u64) -> Replay {
+        let mut cfg = cfg.clone();
+        cfg.game.seed = Some(seed);
+        let mut world = World::new(cfg, 80, 24);
+        while !world.is_over() {
+            let input = if world.ticks().is_multiple_of(every) {
+                Input::Jump
+            } else {
+                Input::Stand
+            };
+            world.tick(&[input]);
+        }
+        world.replay().clone()
+    }
+
+    fn serve(store: &Path, key: Option<&[u8]>) -> SocketAddr {
+        let server = LeaderboardServer::bind(
+            "127.0.0.1:0",
+            store,
+            Cfg::default(),
+            key.map(<[u8]>::to_vec),
+        )
+        .expect("bind");
+        let addr = server.local_addr().expect("address");
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    #[test]
+    fn server_ranks_verified_runs_and_keeps_them() {
+        let store = scratch("store.jsonl");
+        let key = b"team key";
+        let addr = serve(&store, Some(key));
+        let client = LeaderboardClient::new(addr.to_string(), scratch("queue-a.jsonl"));
+
+        let cfg = Cfg::default();
+        let mut runs: Vec<Replay> = [(1, 20), (2, 30), (3, 25)]
+            .into_iter()
+            .map(|(seed, every)| run(&cfg, seed, every))
+            .collect();
+        for replay in &mut runs {
+            replay.sign(key);
+            let submitted = client.submit("dino", replay.clone()).expect("submit");
+            assert!(matches!(submitted, Submitted::Accepted { .. }));
+        }
+
+        let mut forged = runs[0].clone();
+        forged.score *= 10;
+        forged.sign(key);
+        let Submitted::Rejected { reason } = client.submit("cheat", forged).expect("submit") else {
+            panic!("forged run accepted");
+        };
+        assert!(reason.contains("scores"), "{}", reason);
+        assert!(matches!(
+            client.submit("cheat", run(&cfg, 4, 20)).expect("submit"),
+            Submitted::Rejected { .. }
+        ));
+
+        // Signed or not, runs the server will not play back are turned
+        // down before it tries, and it keeps answering.
+        let tampered = |edit: fn(&mut Replay)| {
+            let mut replay = runs[1].clone();
+            edit(&mut replay);
+            replay.sign(key);
+            match client.submit("cheat", replay).expect("submit") {
+                Submitted::Rejected { reason } => reason,
+                submitted => panic!("tampered run {:?}", submitted),
+            }
+        };
+        assert!(tampered(|replay| replay.width = 2_000_000).contains("playfield"));
+        assert!(tampered(|replay| replay.height = 10).contains("playfield"));
+        assert!(tampered(|replay| replay.ticks = u64::MAX).contains("longer"));
+        assert!(
+            tampered(|replay| {
+                replay.inputs = vec![(0, Input::Jump); replay.ticks as usize * 3 + 1];
+            })
+            .contains("inputs")
+        );
+
+        let mut scores: Vec<usize> = runs.iter().map(|replay| replay.score).collect();
+        scores.sort_by(|a, b| b.cmp(a));
+        let top = client.top(2).expect("top");
+        assert_eq!(
+            top.iter().map(|entry| entry.score).collect::<Vec<_>>(),
+            scores[..2]
+        );
+
+        let reloaded = Board::load(&store).expect("reload");
+        assert_eq!(reloaded.top(10).len(), 3);
+        fs::remove_file(&store).ok();
+    }
+
+    #[test]
+    fn client_queues_runs_until_the_server_answers() {
+        let queue = scratch("queue-b.jsonl");
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port");
+        let offline = LeaderboardClient::new(closed.to_string(), &queue);
+        let replay = run(&Cfg::default(), 5, 20);
+        assert_eq!(
+            offline.submit("dino", replay.clone()).expect("queue"),
+            Submitted::Queued
+        );
+        assert_eq!(offline.queued(), 1);
+
+        // A server that hangs up on everyone is tried once per flush or
+        // submission, not once per queued run.
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let hangs_up = listener.local_addr().expect("address");
+        let tries = Arc::new(AtomicUsize::new(0));
+        thread::spawn({
+            let tries = Arc::clone(&tries);
+            move || {
+                for stream in listener.incoming() {
+                    tries.fetch_add(1, Ordering::SeqCst);
+                    drop(stream);
+                }
+            }
+        });
+        let flaky = LeaderboardClient::new(hangs_up.to_string(), &queue);
+        for _ in 0..2 {
+            assert_eq!(
+                flaky.submit("dino", replay.clone()).expect("queue"),
+                Submitted::Queued
+            );
+        }
+        assert_eq!(flaky.flush().expect("flush"), Vec::new());
+        assert_eq!(tries.load(Ordering::SeqCst), 3);
+        assert_eq!(flaky.queued(), 3);
+
+        let store = scratch("store-b.jsonl");
+        let online = LeaderboardClient::new(serve(&store, None).to_string(), &queue);
+        assert_eq!(
+            online.flush().expect("flush"),
+            vec![
+                Submitted::Accepted { rank: 1 },
+                Submitted::Accepted { rank: 2 },
+                Submitted::Accepted { rank: 3 },
+            ]
+        );
+        assert_eq!(online.queued(), 0);
+        assert_eq!(online.top(5).expect("top")[0].score, replay.score);
+        fs::remove_file(&store).ok();
+    }
+}
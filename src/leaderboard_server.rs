@@ -0,0 +1,28 @@
+//! `leaderboard-server`: keeps the leaderboard games submit their runs to,
+//! accepting only runs whose replays play back to the score they claim.
+
+use {
+    color_eyre::{Result, eyre::WrapErr},
+    dino_rs::{config::Cfg, leaderboard::LeaderboardServer, replay},
+    std::path::Path,
+};
+
+pub fn run(cfg: Cfg) -> Result<()> {
+    let port = cfg.leaderboard.port;
+    let key = if cfg.key_file.is_empty() {
+        None
+    } else {
+        Some(replay::load_key(Path::new(&cfg.key_file))?)
+    };
+    let signed = key.is_some();
+    let store = cfg.leaderboard.store_file.clone();
+    let server = LeaderboardServer::bind(("0.0.0.0", port), Path::new(&store), cfg, key)
+        .wrap_err_with(|| format!("Failed to start the leaderboard on port {}", port))?;
+    println!(
+        "Keeping the leaderboard in {} on port {}{}",
+        store,
+        port,
+        if signed { "; runs must be signed" } else { "" }
+    );
+    server.run().wrap_err("The leaderboard stopped")
+}
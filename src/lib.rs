@@ -0,0 +1,133 @@
+//! The Chrome dinosaur game as a library.
+//!
+//! A [`World`] holds one game. Hosts feed it elapsed time and player
+//! [`Input`]s through [`World::step`] and get back the [`Event`]s that
+//! happened, so they can play sounds or show a game-over screen. The world
+//! never touches the terminal:
+//!
+//! ```
+//! use {
+//!     dino_rs::{Cfg, Event, Input, World},
+//!     std::time::Duration,
+//! };
+//!
+//! let mut cfg = Cfg::default();
+//! cfg.game.seed = Some(42);
+//!
+//! let mut world = World::new(cfg, 80, 24);
+//! let events = world.step(Duration::from_millis(30), &[Input::Jump]);
+//! assert!(events.contains(&Event::Jumped));
+//!
+//! while !world.is_over() {
+//!     world.step(Duration::from_millis(30), &[]);
+//! }
+//! println!("scored {}", world.score());
+//! ```
+//!
+//! Every run is recorded as a [`Replay`], its seed plus each input and the
+//! tick it came before, which plays back identically with
+//! [`Replay::simulate`]. A [`Ghost`] plays one back beside a live run on
+//! the same seed. [`Replay::verify`] checks a claimed score by playing it
+//! back, against a hash of the gameplay settings and optionally an HMAC
+//! signature.
+//!
+//! For drawing, a [`Scene`] turns [`World::frame`] into a [`Buffer`] of
+//! styled cells. A [`Renderer`] pairs a scene with a [`Backend`] that shows
+//! the cells, and with the `widget` feature [`GameWidget`] draws into any
+//! ratatui area instead.
+//!
+//! The lower layers stay public for tools that need them:
+//! [`PhysicsEngine`] runs single ticks and [`ObstacleSpawner`] decides what
+//! comes next, configured through [`SpawnerConfig`].
+//!
+//! [`Env`] wraps those two in a gym-style `reset`/`step` interface for
+//! training agents, with rewards and frame skip set by [`EnvConfig`].
+//! [`Bot`] is a built-in rule-based player that reads the engine through
+//! [`World::physics`] and answers with an [`Action`]. A [`Network`] does
+//! the same from an [`Observation`], with weights evolved by the binary's
+//! `train` command.
+//!
+//! The daily challenge seeds each day's course from the date with
+//! [`daily::seed`] and keeps each day's best run in a [`DailyBoard`].
+//! Two players sharing a keyboard are scored round by round with a
+//! [`versus::Tally`].
+//!
+//! Because runs are deterministic, racing over a network only needs each
+//! player's inputs: a [`Rival`] rebuilds another player's run from them.
+//!
+//! # Features
+//!
+//! - `tui`: the `dino-rs` binary and the crossterm backend.
+//! - `audio`: music and sound effects in the binary.
+//! - `widget`: [`GameWidget`] for ratatui apps.
+//! - `net`: racing other players over TCP with [`RaceHost`] and
+//!   [`RaceClient`], a telnet decoder and [`session`] bookkeeping for
+//!   serving the game to remote terminals, [`SpectatorBackend`] for streaming a game to viewers, and
+//!   a self-hosted leaderboard with [`LeaderboardServer`] and
+//!   [`LeaderboardClient`]; the binary's `host`, `join`, `serve`, `watch`
+//!   and `leaderboard-server` commands. Part of `tui`.
+//!
+//! All are on by default. With none of them the crate only needs the
+//! simulation and rendering dependencies, not crossterm or rodio.
+
+pub mod backend;
+pub mod bot;
+pub mod buffer;
+mod canvas;
+pub mod config;
+pub mod daily;
+mod daynight;
+pub mod display;
+pub mod env;
+pub mod ghost;
+#[cfg(feature = "net")]
+pub mod leaderboard;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod network;
+pub mod pacing;
+pub mod physics;
+pub mod rendering;
+pub mod replay;
+#[cfg(feature = "net")]
+pub mod session;
+pub mod snapshot;
+pub mod spawner;
+#[cfg(feature = "net")]
+pub mod spectate;
+pub mod sprites;
+#[cfg(feature = "net")]
+pub mod telnet;
+pub mod theme;
+pub mod types;
+pub mod versus;
+#[cfg(feature = "widget")]
+pub mod widget;
+pub mod world;
+
+pub use {
+    backend::{Backend, LineBackend, MemoryBackend},
+    bot::Bot,
+    buffer::{Buffer, Cell},
+    config::{Cfg, EnvConfig, ServeConfig, SpawnerConfig, TrainConfig},
+    daily::{DailyBoard, DailyEntry},
+    env::{Action, Env, Observation, ObstacleView, StepInfo},
+    ghost::Ghost,
+    network::Network,
+    physics::PhysicsEngine,
+    rendering::{DrawArgs, GhostArgs, Renderer, Scene},
+    replay::{Rejection, Replay, Verified},
+    snapshot::Snapshot,
+    spawner::ObstacleSpawner,
+    types::{DinoState, Obstacle, ObstacleType},
+    world::{Event, Input, World},
+};
+
+#[cfg(feature = "widget")]
+pub use widget::GameWidget;
+#[cfg(feature = "net")]
+pub use {
+    leaderboard::{LeaderboardClient, LeaderboardServer},
+    net::{RaceClient, RaceHost, Rival, Rules},
+    spectate::{Screen, SpectatorBackend},
+};
@@ -1,25 +1,81 @@
-mod config;
-mod display;
+mod agent;
+mod analyze;
+mod cli;
 mod game;
 mod input;
-mod physics;
-mod rendering;
+mod leaderboard_server;
+mod race;
+mod render_thread;
+mod serve;
+#[cfg(feature = "audio")]
 mod sound;
-mod spawner;
-mod types;
+mod train;
+mod verify;
+mod watch;
+
+#[cfg(not(feature = "audio"))]
+mod mute;
+#[cfg(not(feature = "audio"))]
+use mute as sound;
+
+use {
+    clap::Parser,
+    cli::Command,
+    dino_rs::{backend::BackendKind, config::Cfg},
+    game::{Game, Next, Pilot, Terminal},
+};
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
-    setup_terminal()?;
+    let cli = cli::Cli::parse();
+    let mut cfg = Cfg::load()?;
+    cli.apply(&mut cfg);
+
+    match &cli.command {
+        Some(Command::Train(args)) => return train::run(cfg, args),
+        Some(Command::Analyze(args)) => return analyze::run(cfg, args),
+        Some(Command::Host(args)) => return race::host(cfg, args),
+        Some(Command::Join(args)) => return race::join(cfg, args),
+        Some(Command::Serve(_)) => return serve::run(cfg),
+        Some(Command::Watch(args)) => return watch::run(cfg, args),
+        Some(Command::Verify(args)) => return verify::run(cfg, args),
+        Some(Command::LeaderboardServer(_)) => return leaderboard_server::run(cfg),
+        None => {}
+    }
+    if cli.agent {
+        return agent::run(cfg, &cli);
+    }
 
-    let result = run_game();
+    let pilot = match &cli.watch {
+        Some(path) => Pilot::Network(train::load_genome(path)?),
+        None => Pilot::Bot,
+    };
+    let autoplay = cli.autoplay || cli.watch.is_some();
 
-    cleanup_terminal()?;
+    let backend = BackendKind::from_config(&cfg.render.backend);
+    let terminal = Terminal::local(&cfg)?;
+    warn(terminal.renderer.warnings());
+    setup_terminal(backend)?;
+
+    let result = Game::new(cfg, pilot, terminal).and_then(|game| run_game(game, autoplay));
+
+    cleanup_terminal(backend)?;
     result
 }
 
-fn setup_terminal() -> color_eyre::Result<()> {
+/// Shows config problems on the normal screen, where they stay readable
+/// once the game exits.
+pub fn warn(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+}
+
+pub fn setup_terminal(backend: BackendKind) -> color_eyre::Result<()> {
     crossterm::terminal::enable_raw_mode()?;
+    if backend == BackendKind::Line {
+        return Ok(());
+    }
     crossterm::execute!(
         std::io::stdout(),
         crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
@@ -29,30 +85,36 @@ fn setup_terminal() -> color_eyre::Result<()> {
     Ok(())
 }
 
-fn cleanup_terminal() -> color_eyre::Result<()> {
-    crossterm::execute!(
-        std::io::stdout(),
-        crossterm::terminal::LeaveAlternateScreen,
-        crossterm::cursor::Show
-    )?;
+pub fn cleanup_terminal(backend: BackendKind) -> color_eyre::Result<()> {
+    if backend != BackendKind::Line {
+        crossterm::execute!(
+            std::io::stdout(),
+            crossterm::terminal::LeaveAlternateScreen,
+            crossterm::cursor::Show
+        )?;
+    }
     crossterm::terminal::disable_raw_mode()?;
     Ok(())
 }
 
-fn run_game() -> color_eyre::Result<()> {
-    let mut game = game::Game::new()?;
+pub fn run_game(mut game: Game, autoplay: bool) -> color_eyre::Result<()> {
+    let mut next = if autoplay { Next::Watch } else { game.title()? };
 
     loop {
-        game.show_countdown()?;
-
-        if !game.run()? {
-            break;
-        }
-
-        if !game.wait_for_restart()? {
-            break;
-        }
+        next = match next {
+            Next::Play => game.play()?,
+            Next::Watch => {
+                game.demo()?;
+                game.title()?
+            }
+            Next::Daily => game.daily()?,
+            Next::Versus => {
+                game.versus()?;
+                game.title()?
+            }
+            Next::Quit => break,
+        };
     }
 
-    Ok(())
+    game.finish()
 }
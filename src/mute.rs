@@ -0,0 +1,38 @@
+//! Stands in for the rodio sound player when the `audio` feature is off.
+
+use color_eyre::eyre::Result;
+
+#[derive(Clone, Default)]
+pub struct SoundPlayer;
+
+impl SoundPlayer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn silent() -> Self {
+        Self
+    }
+
+    pub fn play_bg_music(&self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn play_death_screen_music(&self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn stop_music(&self) {}
+
+    pub fn play_jump_sound(&self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn play_death_sound(&self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn play_high_score_sound(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,836 @@
+//! Racing over TCP: the messages players exchange and the connections that
+//! carry them, one JSON object per line.
+//!
+//! Everyone runs the same deterministic [`World`] locally, on the seed and
+//! [`Rules`] the host picked, so the only traffic is each player's inputs
+//! and how many ticks their run has reached. A [`RaceHost`] accepts
+//! players and relays what each sends to the others; a [`RaceClient`]
+//! talks to it; a [`Rival`] replays another player's inputs to show their
+//! dino, and a [`RaceView`] keeps every rival in a race up to date.
+
+use {
+    crate::{
+        config::{Cfg, GameConfig, PhysicsConfig, SpawnerConfig},
+        rendering::GhostArgs,
+        replay::Replay,
+        world::{Input, World},
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        io::{self, BufRead, BufReader, Write},
+        net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+        sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender},
+        thread,
+        time::Duration,
+    },
+};
+
+/// The host's player id.
+pub const HOST: u32 = 0;
+
+/// How long a client waits for the host's welcome, and how long a write
+/// may stall before the connection counts as lost.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Lines the host may have waiting for one player before it gives up on
+/// them as too slow.
+const PEER_QUEUE: usize = 1024;
+
+/// The settings that decide how a run plays out. Everyone in a race uses
+/// the host's, whatever their own config says.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Rules {
+    pub physics: PhysicsConfig,
+    pub game: GameConfig,
+    pub spawner: SpawnerConfig,
+}
+
+impl Rules {
+    pub fn from_cfg(cfg: &Cfg) -> Self {
+        Self {
+            physics: cfg.physics.clone(),
+            game: cfg.game.clone(),
+            spawner: cfg.spawner.clone(),
+        }
+    }
+
+    /// Replaces the gameplay sections of `cfg`, keeping its look.
+    pub fn apply(&self, cfg: &mut Cfg) {
+        cfg.physics = self.physics.clone();
+        cfg.game = self.game.clone();
+        cfg.spawner = self.spawner.clone();
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// The host's greeting to a new connection, with the playfield size
+    /// every world in the race uses.
+    Welcome {
+        id: u32,
+        width: usize,
+        height: usize,
+        rules: Box<Rules>,
+    },
+    /// Everyone connected, sent whenever that changes between races.
+    Lobby { players: Vec<u32> },
+    /// A race begins on `seed`. Connections not in `players` joined too
+    /// late and spectate until the next one.
+    Start { seed: u64, players: Vec<u32> },
+    /// Player `id` has run `tick` ticks, with `inputs` new since their last
+    /// update, each tagged with the tick it came before.
+    Progress {
+        id: u32,
+        tick: u64,
+        inputs: Vec<(u64, Input)>,
+    },
+    /// Player `id` disconnected; [`HOST`] leaving ends the session.
+    Left { id: u32 },
+}
+
+/// `message` as one line.
+fn line(message: &Message) -> io::Result<String> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// Sends `message` as one line.
+fn send(stream: &mut TcpStream, message: &Message) -> io::Result<()> {
+    stream.write_all(line(message)?.as_bytes())
+}
+
+/// Reads lines from `stream` on a separate thread, tagging each message
+/// with `id`. `None` follows the last one, once the stream closes. Lines
+/// that do not parse are skipped.
+fn spawn_reader(
+    id: u32,
+    stream: TcpStream,
+    sender: Sender<(u32, Option<Message>)>,
+) -> io::Result<()> {
+    thread::Builder::new()
+        .name(format!("race-reader-{}", id))
+        .spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                if let Ok(message) = serde_json::from_str(&line)
+                    && sender.send((id, Some(message))).is_err()
+                {
+                    return;
+                }
+            }
+            sender.send((id, None)).ok();
+        })?;
+    Ok(())
+}
+
+/// Writes the lines queued for `stream` on a separate thread. A failed
+/// write hangs up, which the stream's reader then reports.
+fn spawn_writer(id: u32, mut stream: TcpStream) -> io::Result<SyncSender<String>> {
+    let (sender, lines) = mpsc::sync_channel::<String>(PEER_QUEUE);
+    thread::Builder::new()
+        .name(format!("race-writer-{}", id))
+        .spawn(move || {
+            for line in lines {
+                if stream.write_all(line.as_bytes()).is_err() {
+                    stream.shutdown(Shutdown::Both).ok();
+                    return;
+                }
+            }
+        })?;
+    Ok(sender)
+}
+
+fn connection(stream: &TcpStream) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(TIMEOUT))
+}
+
+/// A player connected to the host. What the host sends them goes out on
+/// their own writer thread, so a slow connection never holds up the
+/// host's game.
+struct Peer {
+    stream: TcpStream,
+    lines: SyncSender<String>,
+}
+
+impl Peer {
+    fn new(id: u32, stream: TcpStream, sender: Sender<(u32, Option<Message>)>) -> io::Result<Self> {
+        // Accepted streams inherit the listener's nonblocking mode on
+        // macOS and the BSDs, and the reader would take `WouldBlock` for
+        // a hang-up.
+        stream.set_nonblocking(false)?;
+        connection(&stream)?;
+        let lines = spawn_writer(id, stream.try_clone()?)?;
+        spawn_reader(id, stream.try_clone()?, sender)?;
+        Ok(Self { stream, lines })
+    }
+
+    /// Queues `line`. False once the peer has hung up or fallen too far
+    /// behind to catch up.
+    fn send(&self, line: &str) -> bool {
+        self.lines.try_send(line.to_string()).is_ok()
+    }
+}
+
+/// One race in progress, as far as the host has heard.
+struct Race {
+    seed: u64,
+    players: Vec<u32>,
+    /// Each player's latest tick and every input so far, for late joiners.
+    progress: BTreeMap<u32, (u64, Vec<(u64, Input)>)>,
+}
+
+/// Accepts players and relays their messages. The host plays too, as
+/// [`HOST`].
+pub struct RaceHost {
+    listener: TcpListener,
+    width: usize,
+    height: usize,
+    rules: Rules,
+    peers: BTreeMap<u32, Peer>,
+    sender: Sender<(u32, Option<Message>)>,
+    incoming: Receiver<(u32, Option<Message>)>,
+    next_id: u32,
+    race: Option<Race>,
+}
+
+impl RaceHost {
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        width: usize,
+        height: usize,
+        rules: Rules,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let (sender, incoming) = mpsc::channel();
+
+        Ok(Self {
+            listener,
+            width,
+            height,
+            rules,
+            peers: BTreeMap::new(),
+            sender,
+            incoming,
+            next_id: HOST + 1,
+            race: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// The host and everyone connected.
+    pub fn players(&self) -> Vec<u32> {
+        std::iter::once(HOST)
+            .chain(self.peers.keys().copied())
+            .collect()
+    }
+
+    /// Starts a race on `seed` for everyone connected and returns who is
+    /// in it.
+    pub fn start(&mut self, seed: u64) -> Vec<u32> {
+        let players = self.players();
+        self.broadcast(
+            &Message::Start {
+                seed,
+                players: players.clone(),
+            },
+            None,
+        );
+        self.race = Some(Race {
+            seed,
+            players: players.clone(),
+            progress: BTreeMap::new(),
+        });
+        players
+    }
+
+    /// Sends the host's own progress to everyone.
+    pub fn send(&mut self, message: Message) {
+        let message = self.record(HOST, message);
+        self.broadcast(&message, None);
+    }
+
+    /// Accepts new connections and handles everything that arrived since
+    /// the last call. Returns what the host's game needs to know: other
+    /// players' progress, departures and lobby changes.
+    pub fn poll(&mut self) -> Vec<Message> {
+        let mut messages = Vec::new();
+
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Some(lobby) = self.admit(stream) {
+                messages.push(lobby);
+            }
+        }
+
+        while let Ok((id, message)) = self.incoming.try_recv() {
+            match message {
+                Some(message @ Message::Progress { .. }) => {
+                    let message = self.record(id, message);
+                    self.broadcast(&message, Some(id));
+                    messages.push(message);
+                }
+                Some(_) => {}
+                None => messages.extend(self.drop_peer(id)),
+            }
+        }
+
+        messages
+    }
+
+    /// Greets a new connection. Between races it joins the lobby, which
+    /// is returned; during one it gets the race so far and spectates.
+    fn admit(&mut self, stream: TcpStream) -> Option<Message> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let welcome = Message::Welcome {
+            id,
+            width: self.width,
+            height: self.height,
+            rules: Box::new(self.rules.clone()),
+        };
+        let mut greeting = vec![welcome];
+        if let Some(race) = &self.race {
+            greeting.push(Message::Start {
+                seed: race.seed,
+                players: race.players.clone(),
+            });
+            for (&player, (tick, inputs)) in &race.progress {
+                greeting.push(Message::Progress {
+                    id: player,
+                    tick: *tick,
+                    inputs: inputs.clone(),
+                });
+            }
+        }
+
+        let Ok(peer) = Peer::new(id, stream, self.sender.clone()) else {
+            return None;
+        };
+        let greeted = greeting
+            .iter()
+            .all(|message| line(message).is_ok_and(|line| peer.send(&line)));
+        if !greeted {
+            peer.stream.shutdown(Shutdown::Both).ok();
+            return None;
+        }
+        self.peers.insert(id, peer);
+
+        if self.race.is_some() {
+            return None;
+        }
+        let lobby = Message::Lobby {
+            players: self.players(),
+        };
+        self.broadcast(&lobby, None);
+        Some(lobby)
+    }
+
+    /// Stamps progress with the sender's id, so nobody can speak for
+    /// another player, and keeps it for late joiners.
+    fn record(&mut self, id: u32, message: Message) -> Message {
+        let Message::Progress { tick, inputs, .. } = message else {
+            return message;
+        };
+        if let Some(race) = &mut self.race {
+            let (reached, all) = race.progress.entry(id).or_default();
+            *reached = tick;
+            all.extend(&inputs);
+        }
+        Message::Progress { id, tick, inputs }
+    }
+
+    fn broadcast(&mut self, message: &Message, except: Option<u32>) {
+        let Ok(line) = line(message) else { return };
+        let failed: Vec<u32> = self
+            .peers
+            .iter()
+            .filter(|(id, _)| Some(**id) != except)
+            .filter_map(|(&id, peer)| (!peer.send(&line)).then_some(id))
+            .collect();
+        for id in failed {
+            self.drop_peer(id);
+        }
+    }
+
+    /// Forgets a lost connection and tells everyone else.
+    fn drop_peer(&mut self, id: u32) -> Vec<Message> {
+        let Some(peer) = self.peers.remove(&id) else {
+            return Vec::new();
+        };
+        peer.stream.shutdown(Shutdown::Both).ok();
+        let mut messages = vec![Message::Left { id }];
+        if self.race.is_none() {
+            messages.push(Message::Lobby {
+                players: self.players(),
+            });
+        }
+        for message in &messages {
+            self.broadcast(message, None);
+        }
+        messages
+    }
+
+    /// Marks the race as over, so new connections join the lobby.
+    pub fn finish(&mut self) {
+        self.race = None;
+    }
+}
+
+/// Reader threads hold their own handle on each stream, so dropping ours
+/// alone would not hang up.
+impl Drop for RaceHost {
+    fn drop(&mut self) {
+        for peer in self.peers.values() {
+            peer.stream.shutdown(Shutdown::Both).ok();
+        }
+    }
+}
+
+/// A connection to a [`RaceHost`].
+pub struct RaceClient {
+    pub id: u32,
+    pub width: usize,
+    pub height: usize,
+    pub rules: Rules,
+    stream: TcpStream,
+    incoming: Receiver<(u32, Option<Message>)>,
+    connected: bool,
+}
+
+impl RaceClient {
+    /// Connects and waits for the host's welcome.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        connection(&stream)?;
+        let (sender, incoming) = mpsc::channel();
+        spawn_reader(HOST, stream.try_clone()?, sender)?;
+
+        match incoming.recv_timeout(TIMEOUT) {
+            Ok((
+                _,
+                Some(Message::Welcome {
+                    id,
+                    width,
+                    height,
+                    rules,
+                }),
+            )) => Ok(Self {
+                id,
+                width,
+                height,
+                rules: *rules,
+                stream,
+                incoming,
+                connected: true,
+            }),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the host did not answer",
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the host did not send a welcome",
+            )),
+        }
+    }
+
+    /// Sends to the host. Failures are left for [`RaceClient::poll`] to
+    /// report as the host leaving.
+    pub fn send(&mut self, message: &Message) {
+        if self.connected && send(&mut self.stream, message).is_err() {
+            self.stream.shutdown(Shutdown::Both).ok();
+        }
+    }
+
+    /// Everything the host sent since the last call. Losing the host shows
+    /// up as [`Message::Left`] for [`HOST`], once.
+    pub fn poll(&mut self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        while let Ok((_, message)) = self.incoming.try_recv() {
+            match message {
+                Some(message) => messages.push(message),
+                None => {
+                    self.connected = false;
+                    messages.push(Message::Left { id: HOST });
+                }
+            }
+        }
+        messages
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+}
+
+impl Drop for RaceClient {
+    fn drop(&mut self) {
+        self.stream.shutdown(Shutdown::Both).ok();
+    }
+}
+
+/// Another player's run, rebuilt here from the inputs they send. Runs are
+/// deterministic, so it matches theirs tick for tick.
+pub struct Rival {
+    pub id: u32,
+    /// Set once the player disconnects; their run stops where it was.
+    pub left: bool,
+    inputs: Replay,
+    world: World,
+}
+
+impl Rival {
+    /// `cfg` must carry the race's rules and seed.
+    pub fn new(id: u32, cfg: &Cfg, width: usize, height: usize) -> Self {
+        let world = World::new(cfg.clone(), width, height);
+        Self {
+            id,
+            left: false,
+            inputs: Replay::new(world.seed(), width, height),
+            world,
+        }
+    }
+
+    /// Takes in a [`Message::Progress`] and plays the run up to `tick`.
+    pub fn update(&mut self, tick: u64, inputs: &[(u64, Input)]) {
+        self.inputs.inputs.extend(inputs);
+        while !self.world.is_over() && self.world.ticks() < tick {
+            let next: Vec<Input> = self.inputs.inputs_at(self.world.ticks()).collect();
+            self.world.tick(&next);
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Whether the run may still go on.
+    pub fn racing(&self) -> bool {
+        !self.left && !self.world.is_over()
+    }
+
+    /// How to draw this rival beside a player who has `score` points.
+    pub fn args(&self, score: usize) -> GhostArgs {
+        let dino = &self.world.physics().dino;
+        GhostArgs {
+            state: dino.state,
+            y: dino.y,
+            visible: self.racing(),
+            lead: score as i64 - self.world.score() as i64,
+        }
+    }
+}
+
+/// One race as seen from one end: everyone in it and their runs, kept up
+/// to date from the messages about them.
+pub struct RaceView {
+    pub seed: u64,
+    /// Everyone in the race, this end included, until they leave.
+    pub players: Vec<u32>,
+    pub rivals: Vec<Rival>,
+    /// Whether this end races, rather than spectating having joined late.
+    pub racing: bool,
+}
+
+impl RaceView {
+    /// The race [`Message::Start`] announced, seen by `me`. `cfg` must
+    /// carry the race's rules.
+    pub fn new(
+        cfg: &Cfg,
+        me: u32,
+        seed: u64,
+        players: &[u32],
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let mut cfg = cfg.clone();
+        cfg.game.seed = Some(seed);
+        let rivals = players
+            .iter()
+            .filter(|&&id| id != me)
+            .map(|&id| Rival::new(id, &cfg, width, height))
+            .collect();
+        let racing = players.contains(&me);
+        let mut players = players.to_vec();
+        if !racing {
+            players.push(me);
+        }
+        Self {
+            seed,
+            players,
+            rivals,
+            racing,
+        }
+    }
+
+    /// Takes in a message sent during the race. A late joiner gets the
+    /// race so far right behind the start, often in the same batch, and
+    /// must hand those messages here too.
+    pub fn handle(&mut self, message: Message) {
+        match message {
+            Message::Progress { id, tick, inputs } => {
+                if let Some(rival) = self.rivals.iter_mut().find(|rival| rival.id == id) {
+                    rival.update(tick, &inputs);
+                }
+            }
+            // Everyone else's inputs came through the host.
+            Message::Left { id: HOST } => {
+                self.rivals.iter_mut().for_each(|rival| rival.left = true);
+            }
+            Message::Left { id } => {
+                self.players.retain(|&player| player != id);
+                if let Some(rival) = self.rivals.iter_mut().find(|rival| rival.id == id) {
+                    rival.left = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::time::Instant};
+
+    /// Calls `poll` until it finds something, for up to a few seconds.
+    fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        let start = Instant::now();
+        loop {
+            if let Some(found) = poll() {
+                return found;
+            }
+            assert!(start.elapsed() < TIMEOUT, "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn host() -> (RaceHost, SocketAddr) {
+        let host =
+            RaceHost::bind("127.0.0.1:0", 80, 24, Rules::from_cfg(&Cfg::default())).expect("bind");
+        let addr = host.local_addr().expect("address");
+        (host, addr)
+    }
+
+    /// Connects a client while the host keeps polling, as its game loop
+    /// would, and returns what the host saw meanwhile.
+    fn join(host: &mut RaceHost, addr: SocketAddr) -> (RaceClient, Vec<Message>) {
+        let client = thread::spawn(move || RaceClient::connect(addr));
+        let mut seen = Vec::new();
+        while !client.is_finished() {
+            seen.extend(host.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        seen.extend(host.poll());
+        let client = client.join().expect("client thread").expect("connect");
+        (client, seen)
+    }
+
+    fn progress(tick: u64, inputs: Vec<(u64, Input)>) -> Message {
+        Message::Progress {
+            id: 99,
+            tick,
+            inputs,
+        }
+    }
+
+    #[test]
+    fn host_relays_progress_and_departures() {
+        let (mut host, addr) = host();
+        let (mut first, _) = join(&mut host, addr);
+        let (mut second, seen) = join(&mut host, addr);
+        assert_eq!(host.players(), vec![HOST, first.id, second.id]);
+        assert!(
+            seen.iter()
+                .any(|m| matches!(m, Message::Lobby { players } if players.len() == 3))
+        );
+
+        let players = host.start(7);
+        assert_eq!(players.len(), 3);
+        wait_for(|| {
+            first
+                .poll()
+                .into_iter()
+                .find(|m| matches!(m, Message::Start { seed: 7, .. }))
+        });
+
+        first.send(&progress(30, vec![(0, Input::Jump)]));
+        let relayed = wait_for(|| {
+            host.poll();
+            second.poll().into_iter().find_map(|m| match m {
+                Message::Progress { id, tick, .. } => Some((id, tick)),
+                _ => None,
+            })
+        });
+        assert_eq!(relayed, (first.id, 30), "ids are stamped by the host");
+
+        drop(first);
+        let left = wait_for(|| {
+            host.poll().into_iter().find_map(|m| match m {
+                Message::Left { id } => Some(id),
+                _ => None,
+            })
+        });
+        assert_eq!(host.players(), vec![HOST, second.id]);
+        wait_for(|| {
+            second
+                .poll()
+                .into_iter()
+                .find(|m| matches!(m, Message::Left { id } if *id == left))
+        });
+
+        drop(host);
+        wait_for(|| {
+            second
+                .poll()
+                .into_iter()
+                .find(|m| matches!(m, Message::Left { id: HOST }))
+        });
+        assert!(!second.connected());
+    }
+
+    #[test]
+    fn a_player_who_stops_reading_does_not_stall_the_host() {
+        let (mut host, addr) = host();
+        let stalled = TcpStream::connect(addr).expect("connect");
+        wait_for(|| {
+            host.poll()
+                .into_iter()
+                .find(|m| matches!(m, Message::Lobby { .. }))
+        });
+        let (mut reading, _) = join(&mut host, addr);
+        host.start(3);
+
+        // Far more than the socket buffers hold, so writing it straight to
+        // the stalled connection would block.
+        let inputs: Vec<_> = (0..2000).map(|tick| (tick, Input::Jump)).collect();
+        let start = Instant::now();
+        for tick in 0..600 {
+            host.send(progress(tick, inputs.clone()));
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "{:?}",
+            start.elapsed()
+        );
+
+        wait_for(|| {
+            reading
+                .poll()
+                .into_iter()
+                .find(|m| matches!(m, Message::Progress { tick: 599, .. }))
+        });
+        drop(stalled);
+    }
+
+    #[test]
+    fn late_joiner_catches_up_as_a_spectator() {
+        let mut cfg = Cfg::default();
+        let (mut host, addr) = host();
+        host.start(3);
+        cfg.game.seed = Some(3);
+
+        // The host plays a while before anyone else arrives.
+        let mut world = World::new(cfg.clone(), 80, 24);
+        for tick in 0..200 {
+            let input = if tick % 25 == 0 {
+                Input::Jump
+            } else {
+                Input::Stand
+            };
+            world.tick(&[input]);
+        }
+        host.send(progress(world.ticks(), world.replay().inputs.clone()));
+
+        let (mut late, seen) = join(&mut host, addr);
+        assert!(seen.is_empty(), "late joiners do not change the lobby");
+        let (players, tick, inputs) = wait_for(|| {
+            let messages = late.poll();
+            let players = messages.iter().find_map(|m| match m {
+                Message::Start { players, .. } => Some(players.clone()),
+                _ => None,
+            })?;
+            messages.into_iter().find_map(|m| match m {
+                Message::Progress {
+                    id: HOST,
+                    tick,
+                    inputs,
+                } => Some((players.clone(), tick, inputs)),
+                _ => None,
+            })
+        });
+        assert!(!players.contains(&late.id));
+
+        let mut rival = Rival::new(HOST, &cfg, 80, 24);
+        rival.update(tick, &inputs);
+        assert_eq!(rival.world().ticks(), world.ticks());
+        assert_eq!(rival.world().physics().dino.y, world.physics().dino.y);
+        assert_eq!(rival.world().is_over(), world.is_over());
+    }
+
+    #[test]
+    fn late_joiners_race_view_catches_up_from_the_start_batch() {
+        let cfg = Cfg::default();
+        let (mut host, addr) = host();
+        let (mut early, _) = join(&mut host, addr);
+        let players = host.start(11);
+
+        // Both racers play a while before anyone else arrives.
+        let mut worlds = [HOST, early.id].map(|_| {
+            let mut cfg = cfg.clone();
+            cfg.game.seed = Some(11);
+            World::new(cfg, 80, 24)
+        });
+        for tick in 0..150 {
+            for (world, every) in worlds.iter_mut().zip([20, 35]) {
+                let input = if tick % every == 0 {
+                    Input::Jump
+                } else {
+                    Input::Stand
+                };
+                world.tick(&[input]);
+            }
+        }
+        host.send(progress(
+            worlds[0].ticks(),
+            worlds[0].replay().inputs.clone(),
+        ));
+        early.send(&progress(
+            worlds[1].ticks(),
+            worlds[1].replay().inputs.clone(),
+        ));
+        wait_for(|| {
+            host.poll()
+                .into_iter()
+                .find(|m| matches!(m, Message::Progress { .. }))
+        });
+
+        let (mut late, _) = join(&mut host, addr);
+        // Let the whole greeting arrive, so it comes out as one batch.
+        thread::sleep(Duration::from_millis(200));
+        let mut batch = late.poll().into_iter();
+        let (seed, started) = batch
+            .find_map(|m| match m {
+                Message::Start { seed, players } => Some((seed, players)),
+                _ => None,
+            })
+            .expect("start");
+        assert_eq!(started, players);
+
+        let mut race = RaceView::new(&cfg, late.id, seed, &started, 80, 24);
+        batch.for_each(|message| race.handle(message));
+        assert_eq!(race.players, [HOST, early.id, late.id]);
+        assert!(!race.racing);
+        for (rival, world) in race.rivals.iter().zip(&worlds) {
+            assert_eq!(rival.world().ticks(), world.ticks());
+            assert_eq!(rival.world().score(), world.score());
+            assert_eq!(rival.world().physics().dino.y, world.physics().dino.y);
+        }
+    }
+}
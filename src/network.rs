@@ -0,0 +1,194 @@
+//! A small feed-forward network that plays from an [`Observation`], and
+//! the genetic operators the `train` command evolves it with.
+
+use {
+    crate::{
+        env::{Action, Observation},
+        types::DinoState,
+    },
+    rand::Rng,
+    serde::{Deserialize, Serialize},
+};
+
+/// Obstacles ahead the network can see.
+const VISIBLE_OBSTACLES: usize = 2;
+
+/// Features per obstacle: present, distance, width, height, elevation.
+const OBSTACLE_FEATURES: usize = 5;
+
+/// Numbers fed to the network each decision.
+pub const INPUTS: usize = VISIBLE_OBSTACLES * OBSTACLE_FEATURES + 5;
+
+/// One score per [`Action`]: noop, jump, crouch.
+const OUTPUTS: usize = 3;
+
+/// One hidden tanh layer between the observation features and the three
+/// action scores; the highest score wins. `weights` holds each layer's
+/// rows, bias last, so a genome is just this list.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Network {
+    hidden: usize,
+    weights: Vec<f32>,
+}
+
+impl Network {
+    /// Weights for a network with `hidden` hidden units.
+    pub fn weight_count(hidden: usize) -> usize {
+        hidden * (INPUTS + 1) + OUTPUTS * (hidden + 1)
+    }
+
+    /// A network with weights drawn uniformly from `-1.0..1.0`.
+    pub fn random(hidden: usize, rng: &mut impl Rng) -> Self {
+        Self {
+            hidden,
+            weights: (0..Self::weight_count(hidden))
+                .map(|_| rng.random_range(-1.0..1.0))
+                .collect(),
+        }
+    }
+
+    /// Fails if the weight list does not fit the layer sizes, as in a
+    /// hand-edited or truncated genome file.
+    pub fn validate(&self) -> color_eyre::Result<()> {
+        let expected = Self::weight_count(self.hidden);
+        if self.weights.len() != expected {
+            color_eyre::eyre::bail!(
+                "expected {} weights for {} hidden units, found {}",
+                expected,
+                self.hidden,
+                self.weights.len()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn decide(&self, observation: &Observation) -> Action {
+        let inputs = features(observation);
+        let (layer1, layer2) = self.weights.split_at(self.hidden * (INPUTS + 1));
+
+        let hidden: Vec<f32> = layer1
+            .chunks_exact(INPUTS + 1)
+            .map(|row| dot(row, &inputs).tanh())
+            .collect();
+
+        let scores: Vec<f32> = layer2
+            .chunks_exact(self.hidden + 1)
+            .map(|row| dot(row, &hidden))
+            .collect();
+
+        let best = scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(i, _)| i);
+        [Action::Noop, Action::Jump, Action::Crouch][best]
+    }
+
+    /// A child taking each weight from either parent at random.
+    pub fn crossover(&self, other: &Self, rng: &mut impl Rng) -> Self {
+        Self {
+            hidden: self.hidden,
+            weights: self
+                .weights
+                .iter()
+                .zip(&other.weights)
+                .map(|(&a, &b)| if rng.random_bool(0.5) { a } else { b })
+                .collect(),
+        }
+    }
+
+    /// Nudges each weight, with probability `rate`, by Gaussian noise of
+    /// standard deviation `strength`.
+    pub fn mutate(&mut self, rate: f64, strength: f32, rng: &mut impl Rng) {
+        for weight in &mut self.weights {
+            if rng.random_bool(rate.clamp(0.0, 1.0)) {
+                *weight += gaussian(rng) * strength;
+            }
+        }
+    }
+}
+
+/// `row` holds one weight per input followed by a bias.
+fn dot(row: &[f32], inputs: &[f32]) -> f32 {
+    let (weights, bias) = row.split_at(inputs.len());
+    weights.iter().zip(inputs).map(|(w, x)| w * x).sum::<f32>() + bias[0]
+}
+
+/// Scales the observation to roughly `-1.0..1.0`. Missing obstacles are
+/// all zeros.
+fn features(observation: &Observation) -> [f32; INPUTS] {
+    let mut features = [0.0; INPUTS];
+
+    for (i, obstacle) in observation
+        .obstacles
+        .iter()
+        .take(VISIBLE_OBSTACLES)
+        .enumerate()
+    {
+        let slot = &mut features[i * OBSTACLE_FEATURES..(i + 1) * OBSTACLE_FEATURES];
+        slot[0] = 1.0;
+        slot[1] = obstacle.distance / 100.0;
+        slot[2] = obstacle.width as f32 / 10.0;
+        slot[3] = obstacle.height as f32 / 10.0;
+        slot[4] = obstacle.elevation as f32 / 10.0;
+    }
+
+    let dino = &mut features[VISIBLE_OBSTACLES * OBSTACLE_FEATURES..];
+    dino[0] = observation.speed / 10.0;
+    dino[1] = observation.dino_y / 20.0;
+    dino[2] = observation.dino_velocity / 10.0;
+    dino[3] = f32::from(observation.dino_state == DinoState::Jumping);
+    dino[4] = f32::from(observation.dino_state == DinoState::Crouching);
+
+    features
+}
+
+/// A standard normal sample, by the Box-Muller transform.
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = rng.random_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::physics::seeded_rng};
+
+    fn observation() -> Observation {
+        Observation {
+            obstacles: Vec::new(),
+            speed: 4.0,
+            dino_y: 0.0,
+            dino_velocity: 0.0,
+            dino_state: DinoState::Running,
+            score: 0,
+            frame: None,
+        }
+    }
+
+    #[test]
+    fn output_bias_picks_the_action() {
+        let hidden = 4;
+        let mut network = Network {
+            hidden,
+            weights: vec![0.0; Network::weight_count(hidden)],
+        };
+        network.validate().expect("valid");
+
+        let jump_bias = hidden * (INPUTS + 1) + (hidden + 1) + hidden;
+        network.weights[jump_bias] = 1.0;
+        assert_eq!(network.decide(&observation()), Action::Jump);
+    }
+
+    #[test]
+    fn genetic_operators_keep_the_shape() {
+        let mut rng = seeded_rng(Some(5), 0);
+        let a = Network::random(6, &mut rng);
+        let b = Network::random(6, &mut rng);
+
+        let mut child = a.crossover(&b, &mut rng);
+        child.mutate(0.5, 0.3, &mut rng);
+        child.validate().expect("valid");
+        assert_ne!(child.weights, a.weights);
+    }
+}
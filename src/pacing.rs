@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+/// Decides when the next frame should be drawn.
+///
+/// Frames are spaced at the target rate. When writing a frame to the
+/// terminal takes a large share of that budget (slow terminals, big windows,
+/// SSH), the interval is stretched so input and physics still get time, and
+/// it eases back once writes get faster again.
+pub struct FrameScheduler {
+    target: Duration,
+    interval: Duration,
+    next_frame: Instant,
+    write_time: f32,
+    adaptive: bool,
+}
+
+impl FrameScheduler {
+    /// Share of the frame interval that terminal writes may use before the
+    /// scheduler backs off.
+    const WRITE_BUDGET: f32 = 0.6;
+    const SMOOTHING: f32 = 0.2;
+    const RECOVERY: f32 = 0.05;
+
+    pub fn new(target_fps: f32, adaptive: bool) -> Self {
+        let target = Duration::from_secs_f32(1.0 / target_fps.max(1.0));
+        Self {
+            target,
+            interval: target,
+            next_frame: Instant::now(),
+            write_time: 0.0,
+            adaptive,
+        }
+    }
+
+    pub fn target(&self) -> Duration {
+        self.target
+    }
+
+    /// The current spacing between frames: the target, or longer while
+    /// writes are slow.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// How long the caller may sleep or wait for input before drawing.
+    pub fn time_until_frame(&self) -> Duration {
+        self.next_frame.saturating_duration_since(Instant::now())
+    }
+
+    pub fn frame_due(&self) -> bool {
+        Instant::now() >= self.next_frame
+    }
+
+    /// Records how long the last frame took to write and schedules the next.
+    pub fn record_frame(&mut self, write_time: Duration) {
+        self.write_time += (write_time.as_secs_f32() - self.write_time) * Self::SMOOTHING;
+
+        if self.adaptive {
+            let needed = Duration::from_secs_f32(self.write_time / Self::WRITE_BUDGET);
+            let desired = needed.max(self.target);
+
+            self.interval = if desired > self.interval {
+                desired
+            } else {
+                self.interval.mul_f32(1.0 - Self::RECOVERY) + desired.mul_f32(Self::RECOVERY)
+            };
+        }
+
+        let now = Instant::now();
+        self.next_frame += self.interval;
+        if self.next_frame < now {
+            self.next_frame = now + self.interval;
+        }
+    }
+
+    /// Resets the schedule so the next frame is drawn immediately, e.g. after
+    /// the game was paused on another screen.
+    pub fn restart(&mut self) {
+        self.next_frame = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_while_writes_lag_and_recovers_after() {
+        let mut scheduler = FrameScheduler::new(50.0, true);
+        let target = scheduler.target();
+
+        // Writes well inside the budget leave the rate alone.
+        for _ in 0..20 {
+            scheduler.record_frame(Duration::from_millis(5));
+        }
+        assert_eq!(scheduler.interval(), target);
+
+        // 30 ms writes need 50 ms frames to stay within 60% of the interval.
+        for _ in 0..60 {
+            scheduler.record_frame(Duration::from_millis(30));
+        }
+        let slowed = scheduler.interval();
+        assert!(
+            slowed > Duration::from_millis(49) && slowed <= Duration::from_millis(50),
+            "{:?}",
+            slowed
+        );
+
+        // Once writes are fast again the interval eases back, never jumping.
+        let mut last = slowed;
+        for _ in 0..200 {
+            scheduler.record_frame(Duration::from_millis(2));
+            let interval = scheduler.interval();
+            assert!(interval <= last && last - interval < Duration::from_millis(2));
+            last = interval;
+        }
+        assert!(
+            last.saturating_sub(target) < Duration::from_micros(100),
+            "{:?}",
+            last
+        );
+    }
+
+    #[test]
+    fn fixed_rate_ignores_write_times() {
+        let mut scheduler = FrameScheduler::new(50.0, false);
+        for _ in 0..60 {
+            scheduler.record_frame(Duration::from_millis(100));
+        }
+        assert_eq!(scheduler.interval(), scheduler.target());
+    }
+}
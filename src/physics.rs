@@ -1,28 +1,56 @@
 use crate::config::Cfg;
 use crate::spawner::ObstacleSpawner;
-use crate::types::{Cloud, Dino, DinoState, Obstacle, ObstacleType};
-use rand::Rng;
+use crate::sprites::AnimClock;
+use crate::types::{Cloud, Dino, DinoState, Obstacle, ObstacleType, Star};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
+/// Moves the dino, obstacles and scenery one fixed tick at a time. Most
+/// hosts want [`World`](crate::world::World), which adds timing, input and
+/// scoring on top.
 pub struct PhysicsEngine {
     pub cfg: Cfg,
     pub dino: Dino,
     pub obstacles: Vec<Obstacle>,
     pub clouds: Vec<Cloud>,
+    pub stars: Vec<Star>,
     pub speed: f32,
     pub score_accumulator: f32,
+    pub clock: AnimClock,
+    /// What the dino ran into, once it is dead.
+    pub hit: Option<ObstacleType>,
+    rng: StdRng,
+}
+
+/// Screen column of the dino's left edge.
+pub const DINO_X: usize = 10;
+
+/// Random number generator for one part of the game. With `game.seed` set
+/// every run replays identically; `stream` keeps the parts from sharing a
+/// sequence.
+pub fn seeded_rng(seed: Option<u64>, stream: u64) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(stream)),
+        None => StdRng::from_os_rng(),
+    }
 }
 
 impl PhysicsEngine {
     pub fn new(cfg: Cfg, width: usize, height: usize) -> Self {
+        let mut rng = seeded_rng(cfg.game.seed, 0);
+
         let mut clouds = Vec::new();
         for _ in 0..cfg.game.initial_cloud_count {
             clouds.push(Cloud::new(
-                rand::rng().random_range(0.0..width as f32),
-                rand::rng()
-                    .random_range(5..height.saturating_sub(cfg.clone().physics.ground_height + 10)),
+                rng.random_range(0.0..width as f32),
+                rng.random_range(5..height.saturating_sub(cfg.clone().physics.ground_height + 10)),
             ));
         }
 
+        let mut stars = Vec::new();
+        for _ in 0..cfg.day_night.star_count {
+            stars.push(Self::random_star(&cfg, &mut rng, width as f32, height));
+        }
+
         Self {
             cfg: cfg.clone(),
             dino: Dino::new(),
@@ -31,8 +59,12 @@ impl PhysicsEngine {
                 ObstacleType::SmallCactus,
             )],
             clouds,
+            stars,
             speed: cfg.physics.initial_speed,
             score_accumulator: 0.0,
+            clock: AnimClock::default(),
+            hit: None,
+            rng,
         }
     }
 
@@ -47,19 +79,62 @@ impl PhysicsEngine {
         self.clouds.clear();
         for _ in 0..self.cfg.game.initial_cloud_count {
             self.clouds.push(Cloud::new(
-                rand::rng().random_range(0.0..width as f32),
-                rand::rng()
+                self.rng.random_range(0.0..width as f32),
+                self.rng
                     .random_range(5..height.saturating_sub(self.cfg.physics.ground_height + 10)),
             ));
         }
 
+        self.stars.clear();
+        for _ in 0..self.cfg.day_night.star_count {
+            let star = Self::random_star(&self.cfg, &mut self.rng, width as f32, height);
+            self.stars.push(star);
+        }
+
         self.speed = self.cfg.physics.initial_speed;
         self.score_accumulator = 0.0;
+        self.clock = AnimClock::default();
+        self.hit = None;
+    }
+
+    fn random_star(cfg: &Cfg, rng: &mut StdRng, width: f32, height: usize) -> Star {
+        let sky_bottom = height.saturating_sub(cfg.physics.ground_height + 8).max(2);
+        Star::new(
+            rng.random_range(0.0..width.max(1.0)),
+            rng.random_range(1..sky_bottom),
+            rng.random(),
+        )
+    }
+
+    /// Starts a jump from the ground. Returns whether one started.
+    pub fn jump(&mut self) -> bool {
+        if matches!(self.dino.state, DinoState::Running | DinoState::Crouching) {
+            self.dino.state = DinoState::Jumping;
+            self.dino.velocity_y = self.cfg.physics.jump_velocity;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Crouches, or drops straight to the ground mid-jump.
+    pub fn crouch(&mut self) {
+        if self.dino.state != DinoState::Dead {
+            self.dino.state = DinoState::Crouching;
+        }
+    }
+
+    pub fn stand(&mut self) {
+        if self.dino.state == DinoState::Crouching {
+            self.dino.state = DinoState::Running;
+        }
     }
 
+    /// Runs one tick, adding any points earned to `score`. Returns `false`
+    /// once the dino has hit an obstacle.
     pub fn update(
         &mut self,
-        spawner: &ObstacleSpawner,
+        spawner: &mut ObstacleSpawner,
         screen_width: usize,
         screen_height: usize,
         score: &mut usize,
@@ -67,10 +142,18 @@ impl PhysicsEngine {
         self.update_dino();
         self.update_obstacles(spawner, screen_width);
         self.update_clouds(screen_width, screen_height);
+        self.update_stars(screen_width);
         self.update_score(score);
+        self.advance_clock();
         self.increase_speed();
 
-        !self.check_collision(screen_height)
+        if let Some(hit) = self.check_collision(screen_height) {
+            self.dino.state = DinoState::Dead;
+            self.hit = Some(hit);
+            return false;
+        }
+
+        true
     }
 
     fn update_dino(&mut self) {
@@ -89,10 +172,11 @@ impl PhysicsEngine {
                 self.dino.y = 0.0;
                 self.dino.velocity_y = 0.0;
             }
+            DinoState::Dead => {}
         }
     }
 
-    fn update_obstacles(&mut self, spawner: &ObstacleSpawner, screen_width: usize) {
+    fn update_obstacles(&mut self, spawner: &mut ObstacleSpawner, screen_width: usize) {
         for obs in &mut self.obstacles {
             obs.x -= self.speed;
         }
@@ -100,7 +184,9 @@ impl PhysicsEngine {
         if let Some(last_obs) = self.obstacles.last()
             && spawner.should_spawn(last_obs.x, screen_width as f32)
         {
-            self.obstacles.push(spawner.spawn_next(last_obs.x));
+            let mut next = spawner.spawn_next(last_obs.x);
+            next.id = last_obs.id + 1;
+            self.obstacles.push(next);
         }
 
         self.obstacles.retain(|o| o.x > -20.0);
@@ -111,12 +197,23 @@ impl PhysicsEngine {
             cloud.x -= self.speed / self.cfg.game.cloud_speed_divisor;
             if cloud.x < -10.0 {
                 cloud.x = width as f32 + 10.0;
-                cloud.y = rand::rng()
+                cloud.y = self
+                    .rng
                     .random_range(5..height.saturating_sub(self.cfg.physics.ground_height + 10));
             }
         }
     }
 
+    fn update_stars(&mut self, width: usize) {
+        for star in &mut self.stars {
+            star.x -= self.speed / self.cfg.day_night.star_speed_divisor;
+            star.twinkle = star.twinkle.wrapping_add(1);
+            if star.x < 0.0 {
+                star.x += width as f32;
+            }
+        }
+    }
+
     fn update_score(&mut self, score: &mut usize) {
         self.score_accumulator += 1.0;
         if self.score_accumulator >= self.cfg.game.score_update_threshold {
@@ -125,84 +222,111 @@ impl PhysicsEngine {
         }
     }
 
+    fn advance_clock(&mut self) {
+        self.clock.distance += self.speed;
+        self.clock.elapsed += self.cfg.physics.fixed_timestep;
+    }
+
     fn increase_speed(&mut self) {
         self.speed += self.cfg.physics.speed_increment;
     }
 
-    fn check_collision(&self, screen_height: usize) -> bool {
-        let dino_x = 10;
+    fn check_collision(&self, screen_height: usize) -> Option<ObstacleType> {
         let ground_y = screen_height.saturating_sub(self.cfg.physics.ground_height);
-        let dino_ground_y = ground_y.saturating_sub(6);
-        let dino_y = dino_ground_y.saturating_sub(self.dino.y as usize);
-
-        let (dino_width, dino_height, hitbox_y_offset) =
-            if matches!(self.dino.state, DinoState::Crouching) {
-                (6, 4, 2)
-            } else {
-                (8, 6, 0)
-            };
-
-        let dino_hitbox_y = dino_y + hitbox_y_offset;
-
-        for obs in &self.obstacles {
-            let obs_x = obs.x as usize;
-
-            let collision = match obs.obstacle_type {
-                ObstacleType::SmallCactus => {
-                    let obs_y = ground_y.saturating_sub(2);
-                    let dino_box = Box::new(dino_x, dino_hitbox_y, dino_width, dino_height);
-                    dino_box.check_collision(Box::new(obs_x, obs_y, 3, 2))
-                }
-                ObstacleType::MediumCactus => {
-                    let obs_y = ground_y.saturating_sub(3);
-                    let dino_box = Box::new(dino_x, dino_hitbox_y, dino_width, dino_height);
-                    let obs_box = Box::new(obs_x, obs_y, 3, 3);
-
-                    dino_box.check_collision(obs_box)
-                }
-                ObstacleType::TallCactus => {
-                    let obs_y = ground_y.saturating_sub(4);
-                    let dino_box = Box::new(dino_x, dino_hitbox_y, dino_width, dino_height);
-                    let obs_box = Box::new(obs_x, obs_y, 5, 4);
 
-                    dino_box.check_collision(obs_box)
-                }
-                ObstacleType::WideCactus => {
-                    let obs_y = ground_y.saturating_sub(3);
-                    let dino_box = Box::new(dino_x, dino_hitbox_y, dino_width, dino_height);
-                    let obs_box = Box::new(obs_x, obs_y, 7, 3);
+        self.obstacles
+            .iter()
+            .find(|obs| {
+                collides(
+                    self.dino.state,
+                    self.dino.y,
+                    obs.obstacle_type,
+                    obs.x,
+                    ground_y,
+                )
+            })
+            .map(|obs| obs.obstacle_type)
+    }
+}
 
-                    dino_box.check_collision(obs_box)
-                }
-                ObstacleType::PterodactylLow => {
-                    let ptero_y = ground_y.saturating_sub(8);
-                    let dino_box = Box::new(dino_x, dino_hitbox_y, dino_width, dino_height);
-                    let obs_box = Box::new(obs_x, ptero_y, 7, 3);
+/// Whether a dino in `state`, `dino_y` cells above the ground, overlaps an
+/// obstacle whose left edge is at column `obstacle_x`.
+pub fn collides(
+    state: DinoState,
+    dino_y: f32,
+    obstacle_type: ObstacleType,
+    obstacle_x: f32,
+    ground_y: usize,
+) -> bool {
+    let dino_ground_y = ground_y.saturating_sub(6);
+    let dino_top = dino_ground_y.saturating_sub(dino_y as usize);
+
+    let dino_hitbox = Hitbox::dino(state);
+    let dino_box = Box::new(
+        DINO_X,
+        dino_top + dino_hitbox.y_offset,
+        dino_hitbox.width,
+        dino_hitbox.height,
+    );
+
+    let hitbox = Hitbox::obstacle(obstacle_type);
+    let obs_y = obstacle_top(obstacle_type, ground_y) + hitbox.y_offset;
+
+    dino_box.check_collision(&Box::new(
+        obstacle_x as usize,
+        obs_y,
+        hitbox.width,
+        hitbox.height,
+    ))
+}
 
-                    dino_box.check_collision(obs_box)
-                }
-                ObstacleType::PterodactylMid => {
-                    let ptero_y = ground_y.saturating_sub(12);
-                    let dino_box = Box::new(dino_x, dino_hitbox_y, dino_width, dino_height);
-                    let obs_box = Box::new(obs_x, ptero_y, 7, 3);
+/// Collision box size in cells. `y_offset` is how far below the top of the
+/// entity's sprite box the hitbox starts.
+#[derive(Clone, Copy, Debug)]
+pub struct Hitbox {
+    pub width: usize,
+    pub height: usize,
+    pub y_offset: usize,
+}
 
-                    dino_box.check_collision(obs_box)
-                }
-                ObstacleType::PterodactylHigh => {
-                    let ptero_y = ground_y.saturating_sub(16);
-                    let dino_box = Box::new(dino_x, dino_hitbox_y, dino_width, dino_height);
-                    let obs_box = Box::new(obs_x, ptero_y, 7, 3);
+impl Hitbox {
+    const fn new(width: usize, height: usize, y_offset: usize) -> Self {
+        Self {
+            width,
+            height,
+            y_offset,
+        }
+    }
 
-                    dino_box.check_collision(obs_box)
-                }
-            };
+    pub fn dino(state: DinoState) -> Self {
+        if matches!(state, DinoState::Crouching) {
+            Self::new(6, 4, 2)
+        } else {
+            Self::new(8, 6, 0)
+        }
+    }
 
-            if collision {
-                return true;
-            }
+    pub fn obstacle(obstacle_type: ObstacleType) -> Self {
+        match obstacle_type {
+            ObstacleType::SmallCactus => Self::new(3, 2, 0),
+            ObstacleType::MediumCactus => Self::new(3, 3, 0),
+            ObstacleType::TallCactus => Self::new(5, 4, 0),
+            ObstacleType::WideCactus => Self::new(7, 3, 0),
+            ObstacleType::PterodactylLow
+            | ObstacleType::PterodactylMid
+            | ObstacleType::PterodactylHigh => Self::new(7, 3, 0),
         }
+    }
+}
 
-        false
+/// Screen row of the top of an obstacle's sprite box. Cacti stand on the
+/// ground, pterodactyls fly at fixed altitudes above it.
+pub fn obstacle_top(obstacle_type: ObstacleType, ground_y: usize) -> usize {
+    match obstacle_type {
+        ObstacleType::PterodactylLow => ground_y.saturating_sub(8),
+        ObstacleType::PterodactylMid => ground_y.saturating_sub(12),
+        ObstacleType::PterodactylHigh => ground_y.saturating_sub(16),
+        _ => ground_y.saturating_sub(Hitbox::obstacle(obstacle_type).height),
     }
 }
 
@@ -218,7 +342,7 @@ impl Box {
         Self { x, y, w, h }
     }
 
-    pub fn check_collision(&self, other: Self) -> bool {
+    pub fn check_collision(&self, other: &Self) -> bool {
         self.x < other.x + other.w
             && self.x + self.w > other.x
             && self.y < other.y + other.h
@@ -0,0 +1,402 @@
+//! `host` and `join`: race other players over TCP.
+//!
+//! The host picks the seed and gameplay rules. Every player runs the same
+//! world locally and sends the host its inputs, which the host passes on,
+//! so other players show as ghost dinos rebuilt from those inputs. Players
+//! whose run has ended watch the leader until everyone is out, and anyone
+//! who connects mid-race spectates until the next one starts.
+
+use {
+    crate::{
+        cli::{HostArgs, JoinArgs},
+        game::render_frame,
+        input::InputHandler,
+        render_thread::{RenderCommand, RenderFrame, RenderThread},
+        sound::SoundPlayer,
+    },
+    color_eyre::{
+        Result,
+        eyre::{WrapErr, bail},
+    },
+    crossterm::event::KeyCode,
+    dino_rs::{
+        backend::BackendKind,
+        config::Cfg,
+        display::DisplaySettings,
+        net::{HOST, Message, RaceClient, RaceHost, RaceView, Rival, Rules},
+        pacing::FrameScheduler,
+        rendering::Renderer,
+        world::{Event, World},
+    },
+    std::{
+        cmp::Reverse,
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+pub fn host(cfg: Cfg, args: &HostArgs) -> Result<()> {
+    let (width, height) = (cfg.env.width, cfg.env.height);
+    let host = RaceHost::bind(("0.0.0.0", args.port), width, height, Rules::from_cfg(&cfg))
+        .wrap_err_with(|| format!("Failed to listen on port {}", args.port))?;
+    let lobby = format!("Hosting a race on port {}", args.port);
+    run(cfg, Link::Host(host), lobby)
+}
+
+pub fn join(mut cfg: Cfg, args: &JoinArgs) -> Result<()> {
+    let client = RaceClient::connect(&args.addr)
+        .wrap_err_with(|| format!("Failed to join {}", args.addr))?;
+    client.rules.apply(&mut cfg);
+    let lobby = format!("Joined {} as {}", args.addr, name(client.id));
+    run(cfg, Link::Client(client), lobby)
+}
+
+fn run(cfg: Cfg, link: Link, lobby: String) -> Result<()> {
+    // Every world in the race is the host's size, whatever the terminal's.
+    let (width, height) = link.size(&cfg);
+    let display = DisplaySettings::detect()?;
+    if display.width < width || display.height < height {
+        bail!(
+            "The race is played at {}x{}, but this terminal is {}x{}; make the window larger \
+             and try again",
+            width,
+            height,
+            display.width,
+            display.height
+        );
+    }
+
+    let backend = BackendKind::from_config(&cfg.render.backend);
+    let mut session = Session::new(cfg, link, lobby)?;
+    crate::setup_terminal(backend)?;
+    let result = session.play().and_then(|()| session.render.finish());
+    crate::cleanup_terminal(backend)?;
+    result
+}
+
+/// Which end of the connection this game is.
+enum Link {
+    Host(RaceHost),
+    Client(RaceClient),
+}
+
+impl Link {
+    fn id(&self) -> u32 {
+        match self {
+            Link::Host(_) => HOST,
+            Link::Client(client) => client.id,
+        }
+    }
+
+    fn size(&self, cfg: &Cfg) -> (usize, usize) {
+        match self {
+            Link::Host(_) => (cfg.env.width, cfg.env.height),
+            Link::Client(client) => (client.width, client.height),
+        }
+    }
+
+    /// False once a client has lost the host.
+    fn connected(&self) -> bool {
+        match self {
+            Link::Host(_) => true,
+            Link::Client(client) => client.connected(),
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        match self {
+            Link::Host(host) => host.send(message),
+            Link::Client(client) => client.send(&message),
+        }
+    }
+
+    fn poll(&mut self) -> Vec<Message> {
+        match self {
+            Link::Host(host) => host.poll(),
+            Link::Client(client) => client.poll(),
+        }
+    }
+}
+
+struct Session {
+    cfg: Cfg,
+    link: Link,
+    lobby: String,
+    /// Everyone connected, as far as this end has heard.
+    players: Vec<u32>,
+    width: usize,
+    height: usize,
+    render: RenderThread,
+    input: InputHandler,
+    scheduler: FrameScheduler,
+    player: SoundPlayer,
+}
+
+impl Session {
+    fn new(cfg: Cfg, link: Link, lobby: String) -> Result<Self> {
+        let (width, height) = link.size(&cfg);
+        let renderer = Renderer::new(cfg.clone(), width, height)?;
+        crate::warn(renderer.warnings());
+        let render = RenderThread::spawn(renderer)?;
+        let scheduler = FrameScheduler::new(cfg.render.target_fps, cfg.render.adaptive);
+
+        Ok(Self {
+            cfg,
+            link,
+            lobby,
+            players: Vec::new(),
+            width,
+            height,
+            render,
+            input: InputHandler::terminal(),
+            scheduler,
+            player: SoundPlayer::new(),
+        })
+    }
+
+    /// Races until the player quits or the host goes away.
+    fn play(&mut self) -> Result<()> {
+        let mut results = Vec::new();
+        while let Some(race) = self.wait_for_start(&results)? {
+            match self.race(race)? {
+                Some(standings) => results = standings,
+                None => return Ok(()),
+            }
+            if let Link::Host(host) = &mut self.link {
+                host.finish();
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows the lobby under the last race's results until a race starts.
+    /// The host starts one; everyone else waits for it. Returns `None` if
+    /// the player quit.
+    fn wait_for_start(&mut self, results: &[String]) -> Result<Option<RaceView>> {
+        if let Link::Host(host) = &self.link {
+            self.players = host.players();
+        }
+        self.show_lobby(results)?;
+
+        loop {
+            if let Some(key) = self.input.wait_for_key()? {
+                match key {
+                    KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => return Ok(None),
+                    KeyCode::Char(' ') | KeyCode::Char('r') | KeyCode::Char('R') => {
+                        if let Link::Host(host) = &mut self.link {
+                            let seed = self.cfg.game.seed.unwrap_or_else(rand::random);
+                            let players = host.start(seed);
+                            return Ok(Some(self.view(seed, &players)));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            let mut messages = self.link.poll().into_iter();
+            while let Some(message) = messages.next() {
+                match message {
+                    Message::Lobby { players } => {
+                        self.players = players;
+                        self.show_lobby(results)?;
+                    }
+                    Message::Start { seed, players } => {
+                        // A late joiner's catch-up follows the start in the
+                        // same batch.
+                        let mut race = self.view(seed, &players);
+                        messages.for_each(|message| race.handle(message));
+                        return Ok(Some(race));
+                    }
+                    Message::Left { id: HOST } => self.show_lobby(results)?,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn view(&self, seed: u64, players: &[u32]) -> RaceView {
+        let me = self.link.id();
+        RaceView::new(&self.cfg, me, seed, players, self.width, self.height)
+    }
+
+    fn show_lobby(&mut self, results: &[String]) -> Result<()> {
+        let me = self.link.id();
+        let names: Vec<String> = self
+            .players
+            .iter()
+            .map(|&id| {
+                if id == me {
+                    "You".to_string()
+                } else {
+                    name(id)
+                }
+            })
+            .collect();
+        let prompt = match self.link {
+            Link::Host(_) => "Press SPACE to start a race or 'Q' to quit",
+            Link::Client(_) if !self.link.connected() => {
+                "The host closed the race; press 'Q' to quit"
+            }
+            Link::Client(_) => "Waiting for the host to start; press 'Q' to quit",
+        };
+
+        let mut lines = results.to_vec();
+        lines.extend([
+            self.lobby.clone(),
+            String::new(),
+            format!("Players: {}", names.join(", ")),
+            String::new(),
+            prompt.to_string(),
+        ]);
+        self.render.send(RenderCommand::Message(lines))
+    }
+
+    /// Plays one race and returns the standings, or `None` if the player
+    /// quit. Connections the race started without spectate.
+    fn race(&mut self, mut race: RaceView) -> Result<Option<Vec<String>>> {
+        let mut cfg = self.cfg.clone();
+        cfg.game.seed = Some(race.seed);
+        let me = self.link.id();
+        let (width, height) = (self.width, self.height);
+        let mut world = race.racing.then(|| World::new(cfg.clone(), width, height));
+
+        if world.is_some() {
+            for remaining in (1..=3).rev() {
+                self.render.send(RenderCommand::Countdown {
+                    remaining,
+                    frame_duration: self.scheduler.target(),
+                })?;
+                thread::sleep(Duration::from_secs(1));
+            }
+            if let Err(e) = self.player.play_bg_music() {
+                eprintln!("Failed to play background music: {}", e);
+            }
+        }
+        self.render.send(RenderCommand::Reset { width, height })?;
+        self.render.send(RenderCommand::Clear)?;
+
+        let mut sent = 0;
+        let mut last_step = Instant::now();
+        self.scheduler.restart();
+
+        loop {
+            let until_tick = world
+                .as_ref()
+                .filter(|world| !world.is_over())
+                .map_or(Duration::MAX, |world| {
+                    world.time_until_tick().saturating_sub(last_step.elapsed())
+                });
+            self.input
+                .wait(self.scheduler.time_until_frame().min(until_tick))?;
+
+            let Some(inputs) = self.input.poll()? else {
+                self.player.stop_music();
+                return Ok(None);
+            };
+
+            let now = Instant::now();
+            if let Some(world) = world.as_mut().filter(|world| !world.is_over()) {
+                let ticks = world.ticks();
+                for event in world.step(now.duration_since(last_step), &inputs) {
+                    self.play_sound(event);
+                }
+
+                let recorded = &world.replay().inputs;
+                if world.ticks() > ticks || recorded.len() > sent {
+                    self.link.send(Message::Progress {
+                        id: me,
+                        tick: world.ticks(),
+                        inputs: recorded[sent..].to_vec(),
+                    });
+                    sent = recorded.len();
+                }
+            }
+            last_step = now;
+
+            for message in self.link.poll() {
+                race.handle(message);
+            }
+
+            let racing = world.as_ref().is_some_and(|world| !world.is_over())
+                || race.rivals.iter().any(Rival::racing);
+            if !racing {
+                self.player.stop_music();
+                self.players = race.players;
+                return Ok(Some(standings(world.as_ref(), &race.rivals)));
+            }
+
+            if self.scheduler.frame_due()
+                && let Some(frame) = frame(world.as_ref(), &race.rivals)
+            {
+                self.render.frame(frame)?;
+                self.scheduler.record_frame(self.render.last_write_time());
+            }
+        }
+    }
+
+    fn play_sound(&mut self, event: Event) {
+        let played = match event {
+            Event::Jumped => self.player.play_jump_sound(),
+            Event::Died => self.player.play_death_sound(),
+            Event::NewHighscore => Ok(()),
+        };
+        if let Err(e) = played {
+            eprintln!("Failed to play sound: {}", e);
+        }
+    }
+}
+
+fn name(id: u32) -> String {
+    format!("Player {}", id + 1)
+}
+
+/// Shows the player's own run while it lasts, then the leader's, or
+/// nothing once everyone is out. Everyone else is a ghost, the highest
+/// scorer first so the status line measures against them.
+fn frame(world: Option<&World>, rivals: &[Rival]) -> Option<RenderFrame> {
+    let leader = rivals
+        .iter()
+        .filter(|rival| rival.racing())
+        .max_by_key(|rival| rival.world().ticks());
+    let (view, watched) = match (world.filter(|world| !world.is_over()), leader) {
+        (Some(world), _) => (world, None),
+        (None, Some(leader)) => (leader.world(), Some(leader.id)),
+        (None, None) => return None,
+    };
+
+    let mut frame = render_frame(view, watched.map(|_| "SPECTATING"));
+    let mut ghosts: Vec<&Rival> = rivals
+        .iter()
+        .filter(|rival| Some(rival.id) != watched)
+        .collect();
+    ghosts.sort_by_key(|rival| Reverse(rival.world().score()));
+    frame.ghosts = ghosts
+        .iter()
+        .map(|rival| rival.args(view.score()))
+        .collect();
+    Some(frame)
+}
+
+/// The race's results, best score first.
+fn standings(world: Option<&World>, rivals: &[Rival]) -> Vec<String> {
+    let mut rows: Vec<(String, usize)> = rivals
+        .iter()
+        .map(|rival| {
+            let note = if rival.left { " (left)" } else { "" };
+            (format!("{}{}", name(rival.id), note), rival.world().score())
+        })
+        .collect();
+    if let Some(world) = world {
+        rows.push(("You".to_string(), world.score()));
+    }
+    rows.sort_by_key(|(_, score)| Reverse(*score));
+
+    let mut lines = vec!["RACE RESULTS".to_string(), String::new()];
+    lines.extend(
+        rows.iter()
+            .enumerate()
+            .map(|(i, (who, score))| format!("  {}. {:<20} {:>6}", i + 1, who, score)),
+    );
+    lines.extend([String::new(), String::new()]);
+    lines
+}
@@ -0,0 +1,260 @@
+use {
+    color_eyre::{Result, eyre::eyre},
+    dino_rs::{
+        daily::DailyEntry,
+        rendering::{DrawArgs, GhostArgs, Renderer},
+        snapshot::Snapshot,
+    },
+    std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+            mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError},
+        },
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    },
+};
+
+/// Frames that may wait for the render thread before new ones are dropped.
+const QUEUE_DEPTH: usize = 2;
+
+/// Everything needed to draw one frame, owned so it can cross threads.
+pub struct RenderFrame {
+    pub snapshot: Snapshot,
+    pub score: usize,
+    pub highscore: usize,
+    pub banner: Option<&'static str>,
+    pub ghosts: Vec<GhostArgs>,
+}
+
+pub enum RenderCommand {
+    Frame(RenderFrame),
+    /// One labelled frame per lane of a two-player round, each composed
+    /// `height` rows tall.
+    Lanes {
+        height: usize,
+        lanes: Vec<(RenderFrame, &'static str)>,
+    },
+    Title {
+        highscore: usize,
+        next_daily: Duration,
+    },
+    Countdown {
+        remaining: u32,
+        frame_duration: Duration,
+    },
+    GameOver {
+        score: usize,
+        highscore: usize,
+        /// Lines about the shared leaderboard, if there is one.
+        board: Vec<String>,
+    },
+    DailyOver {
+        score: usize,
+        entry: DailyEntry,
+        streak: u32,
+        next_daily: Duration,
+    },
+    /// A screen of plain text.
+    Message(Vec<String>),
+    RoundCountdown {
+        round: usize,
+        remaining: u32,
+        wins: [usize; 2],
+    },
+    RoundOver {
+        winner: Option<usize>,
+        scores: [usize; 2],
+        wins: [usize; 2],
+    },
+    Clear,
+    Reset {
+        width: usize,
+        height: usize,
+    },
+}
+
+/// Writes to the terminal on its own thread so slow writes never hold up
+/// input or physics.
+///
+/// Frames are offered without blocking and dropped when the queue is full;
+/// the thread also skips ahead to the newest queued frame. Other commands
+/// are never dropped and are handled in order.
+pub struct RenderThread {
+    sender: Option<SyncSender<RenderCommand>>,
+    handle: Option<JoinHandle<Result<()>>>,
+    write_nanos: Arc<AtomicU64>,
+}
+
+impl RenderThread {
+    pub fn spawn(renderer: Renderer) -> Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_DEPTH);
+        let write_nanos = Arc::new(AtomicU64::new(0));
+        let handle = thread::Builder::new().name("render".into()).spawn({
+            let write_nanos = Arc::clone(&write_nanos);
+            move || render_loop(renderer, receiver, &write_nanos)
+        })?;
+
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+            write_nanos,
+        })
+    }
+
+    /// Offers a frame, dropping it if the render thread is still busy.
+    pub fn frame(&mut self, frame: RenderFrame) -> Result<()> {
+        self.offer(RenderCommand::Frame(frame))
+    }
+
+    /// Offers a two-player frame, dropping it if the render thread is
+    /// still busy.
+    pub fn lanes(&mut self, height: usize, lanes: Vec<(RenderFrame, &'static str)>) -> Result<()> {
+        self.offer(RenderCommand::Lanes { height, lanes })
+    }
+
+    fn offer(&mut self, command: RenderCommand) -> Result<()> {
+        match self.sender()?.try_send(command) {
+            Ok(()) | Err(TrySendError::Full(_)) => Ok(()),
+            Err(TrySendError::Disconnected(_)) => Err(self.shutdown()),
+        }
+    }
+
+    /// Queues a command, waiting for room if necessary.
+    pub fn send(&mut self, command: RenderCommand) -> Result<()> {
+        match self.sender()?.send(command) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(self.shutdown()),
+        }
+    }
+
+    /// How long the most recent frame took to write to the terminal.
+    pub fn last_write_time(&self) -> Duration {
+        Duration::from_nanos(self.write_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Waits for everything queued so far to be written, then stops the
+    /// thread.
+    pub fn finish(mut self) -> Result<()> {
+        self.sender.take();
+        self.join()
+    }
+
+    fn sender(&self) -> Result<&SyncSender<RenderCommand>> {
+        self.sender
+            .as_ref()
+            .ok_or_else(|| eyre!("render thread has already stopped"))
+    }
+
+    /// Called once the channel is found closed, which only happens when the
+    /// render thread exited early; returns why it did.
+    fn shutdown(&mut self) -> color_eyre::Report {
+        self.sender.take();
+        match self.join() {
+            Err(e) => e,
+            Ok(()) => eyre!("render thread stopped unexpectedly"),
+        }
+    }
+
+    fn join(&mut self) -> Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle.join().map_err(|_| eyre!("render thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Err(e) = self.join() {
+            eprintln!("Render thread failed: {}", e);
+        }
+    }
+}
+
+fn render_loop(
+    mut renderer: Renderer,
+    receiver: Receiver<RenderCommand>,
+    write_nanos: &AtomicU64,
+) -> Result<()> {
+    while let Ok(mut command) = receiver.recv() {
+        while matches!(
+            command,
+            RenderCommand::Frame(_) | RenderCommand::Lanes { .. }
+        ) {
+            match receiver.try_recv() {
+                Ok(next) => command = next,
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+            }
+        }
+
+        match command {
+            RenderCommand::Frame(frame) => {
+                let start = Instant::now();
+                renderer.draw(draw_args(&frame))?;
+                let nanos = start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+                write_nanos.store(nanos, Ordering::Relaxed);
+            }
+            RenderCommand::Lanes { height, lanes } => {
+                let start = Instant::now();
+                let args = lanes
+                    .iter()
+                    .map(|(frame, label)| (draw_args(frame), *label))
+                    .collect();
+                renderer.draw_lanes(height, args)?;
+                let nanos = start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+                write_nanos.store(nanos, Ordering::Relaxed);
+            }
+            RenderCommand::Countdown {
+                remaining,
+                frame_duration,
+            } => renderer.show_countdown(remaining, frame_duration)?,
+            RenderCommand::Title {
+                highscore,
+                next_daily,
+            } => renderer.show_title(highscore, next_daily)?,
+            RenderCommand::GameOver {
+                score,
+                highscore,
+                board,
+            } => renderer.show_game_over(score, highscore, &board)?,
+            RenderCommand::DailyOver {
+                score,
+                entry,
+                streak,
+                next_daily,
+            } => renderer.show_daily_over(score, &entry, streak, next_daily)?,
+            RenderCommand::Message(lines) => renderer.show_message(&lines)?,
+            RenderCommand::RoundCountdown {
+                round,
+                remaining,
+                wins,
+            } => renderer.show_round_countdown(round, remaining, wins)?,
+            RenderCommand::RoundOver {
+                winner,
+                scores,
+                wins,
+            } => renderer.show_round_over(winner, scores, wins)?,
+            RenderCommand::Clear => renderer.clear()?,
+            RenderCommand::Reset { width, height } => {
+                renderer.update_dimensions(width, height);
+                renderer.reset_day_night();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn draw_args(frame: &RenderFrame) -> DrawArgs<'_> {
+    let mut args = DrawArgs::from_snapshot(&frame.snapshot, frame.score, frame.highscore);
+    if let Some(banner) = frame.banner {
+        args = args.with_banner(banner);
+    }
+    for &ghost in &frame.ghosts {
+        args = args.with_ghost(ghost);
+    }
+    args
+}
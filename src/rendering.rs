@@ -1,16 +1,49 @@
 use {
     crate::{
+        backend::Backend,
+        buffer::Buffer,
+        canvas::{self, Canvas, SubcellMode},
         config::Cfg,
-        types::{Cloud, DinoState, Obstacle, ObstacleType},
+        daily::{self, DailyEntry},
+        daynight::DayNightCycle,
+        display,
+        physics::{DINO_X, obstacle_top},
+        snapshot::Snapshot,
+        sprites::{AnimClock, Animation, SpriteSet},
+        theme::{self, ColorSupport, Style, Theme},
+        types::{Cloud, DinoState, Obstacle, Star},
     },
-    crossterm::{cursor, execute},
-    std::io::{Write, stdout},
+    std::time::Duration,
+    unicode_width::UnicodeWidthChar,
 };
 
-pub struct Renderer {
+/// Inner width of the boxed panels on text screens, in cells.
+const PANEL_WIDTH: usize = 31;
+
+/// Turns world state into a grid of styled cells: sprites, palette and the
+/// day/night cycle, with no idea where the cells end up.
+pub struct Scene {
     cfg: Cfg,
     width: usize,
     height: usize,
+    theme: Theme,
+    day_theme: Theme,
+    night_theme: Theme,
+    day_night: DayNightCycle,
+    color_support: ColorSupport,
+    sprites: SpriteSet,
+    ascii: bool,
+    subcell: Option<SubcellMode>,
+    /// What the theme config asked for that could not be used.
+    warnings: Vec<String>,
+}
+
+/// A [`Scene`] attached to a [`Backend`] that shows what it draws.
+pub struct Renderer {
+    scene: Scene,
+    /// One scene per lane for [`Renderer::draw_lanes`], made on first use.
+    lanes: Vec<Scene>,
+    backend: Box<dyn Backend>,
 }
 
 pub struct DrawArgs<'a> {
@@ -18,36 +51,399 @@ pub struct DrawArgs<'a> {
     dino_y: f32,
     obstacles: &'a [Obstacle],
     clouds: &'a [Cloud],
+    stars: &'a [Star],
     score: usize,
     highscore: usize,
     speed: f32,
+    clock: AnimClock,
+    banner: Option<&'a str>,
+    ghosts: Vec<GhostArgs>,
+}
+
+/// An earlier run's dino, or another player's, drawn dim behind the
+/// player's.
+#[derive(Clone, Copy, Debug)]
+pub struct GhostArgs {
+    pub state: DinoState,
+    pub y: f32,
+    /// False once the ghost's run has ended.
+    pub visible: bool,
+    /// Points the player is past the ghost's score at the same moment;
+    /// negative while behind it.
+    pub lead: i64,
 }
 
 impl<'a> DrawArgs<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         dino_state: DinoState,
         dino_y: f32,
         obstacles: &'a [Obstacle],
         clouds: &'a [Cloud],
+        stars: &'a [Star],
         score: usize,
         highscore: usize,
         speed: f32,
+        clock: AnimClock,
     ) -> Self {
         Self {
             dino_state,
             dino_y,
             obstacles,
             clouds,
+            stars,
             score,
             highscore,
             speed,
+            clock,
+            banner: None,
+            ghosts: Vec::new(),
         }
     }
+
+    /// Adds a label across the top of the playfield, such as `DEMO` while
+    /// the bot plays.
+    pub fn with_banner(mut self, banner: &'a str) -> Self {
+        self.banner = Some(banner);
+        self
+    }
+
+    /// Adds a ghost dino. The status line says how the player stands
+    /// against the first one added.
+    pub fn with_ghost(mut self, ghost: GhostArgs) -> Self {
+        self.ghosts.push(ghost);
+        self
+    }
+
+    pub fn from_snapshot(snapshot: &'a Snapshot, score: usize, highscore: usize) -> Self {
+        Self::new(
+            snapshot.dino_state,
+            snapshot.dino_y,
+            &snapshot.obstacles,
+            &snapshot.clouds,
+            &snapshot.stars,
+            score,
+            highscore,
+            snapshot.speed,
+            snapshot.clock,
+        )
+    }
 }
 
 impl Renderer {
-    pub fn new(cfg: Cfg, width: usize, height: usize) -> Self {
-        Self { cfg, width, height }
+    /// Creates a renderer for the terminal, with the backend chosen by
+    /// `render.backend`.
+    #[cfg(feature = "tui")]
+    pub fn new(cfg: Cfg, width: usize, height: usize) -> color_eyre::Result<Self> {
+        let color_support = ColorSupport::detect(&cfg.theme.color_mode);
+        let backend =
+            crate::backend::BackendKind::from_config(&cfg.render.backend).create(color_support);
+        Self::with_backend(cfg, width, height, backend)
+    }
+
+    pub fn with_backend(
+        cfg: Cfg,
+        width: usize,
+        height: usize,
+        backend: Box<dyn Backend>,
+    ) -> color_eyre::Result<Self> {
+        Ok(Self {
+            scene: Scene::new(cfg, width, height)?,
+            lanes: Vec::new(),
+            backend,
+        })
+    }
+
+    /// See [`Scene::warnings`].
+    pub fn warnings(&self) -> &[String] {
+        self.scene.warnings()
+    }
+
+    pub fn reset_day_night(&mut self) {
+        self.scene.reset_day_night();
+        for lane in &mut self.lanes {
+            lane.reset_day_night();
+        }
+    }
+
+    pub fn update_dimensions(&mut self, width: usize, height: usize) {
+        self.scene.update_dimensions(width, height);
+    }
+
+    pub fn draw(&mut self, draw_args: DrawArgs) -> color_eyre::Result<()> {
+        let screen = self.scene.compose(draw_args);
+        self.backend.draw(&screen)
+    }
+
+    /// Draws one game per lane, stacked top to bottom in equal shares of
+    /// the screen, each labelled across its top row. Games are composed
+    /// `scene_height` rows tall; when that is more than a lane holds, the
+    /// lane shows the bottom of the scene so the ground and status line
+    /// stay in view.
+    pub fn draw_lanes(
+        &mut self,
+        scene_height: usize,
+        lanes: Vec<(DrawArgs, &str)>,
+    ) -> color_eyre::Result<()> {
+        let (width, height) = (self.scene.width, self.scene.height);
+        while self.lanes.len() < lanes.len() {
+            let scene = Scene::new(self.scene.cfg.clone(), width, scene_height)?;
+            self.lanes.push(scene);
+        }
+
+        let count = lanes.len().max(1);
+        let mut screen = Buffer::new(width, height, self.scene.theme.background);
+        for (i, (args, label)) in lanes.into_iter().enumerate() {
+            let top = height * i / count;
+            let rows = height * (i + 1) / count - top;
+
+            let scene = &mut self.lanes[i];
+            scene.update_dimensions(width, scene_height);
+            let lane = scene.compose(args);
+            screen.copy_rows(&lane, scene_height.saturating_sub(rows), top, rows);
+            scene.draw_banner(&mut screen, label, top);
+        }
+
+        self.backend.draw(&screen)
+    }
+
+    /// `board` is shown under the scores, such as the shared leaderboard.
+    pub fn show_game_over(
+        &mut self,
+        score: usize,
+        highscore: usize,
+        board: &[String],
+    ) -> color_eyre::Result<()> {
+        let mut lines = vec![String::new()];
+        lines.extend(self.panel(&[
+            vec!["     GAME OVER!".to_string()],
+            vec![
+                format!("Final Score: {}", score),
+                format!("High Score:  {}", highscore),
+            ],
+        ]));
+        lines.push(String::new());
+        if !board.is_empty() {
+            lines.extend(board.iter().map(|line| format!("  {}", line)));
+            lines.push(String::new());
+        }
+        lines.push("  Press 'R' to restart or 'Q' to quit".to_string());
+        self.backend.show_text(&lines)
+    }
+
+    /// `next_daily` is how long until the next daily challenge starts.
+    pub fn show_title(&mut self, highscore: usize, next_daily: Duration) -> color_eyre::Result<()> {
+        let mut lines = vec![String::new()];
+        lines.extend(self.panel(&[
+            vec!["        DINO-RS".to_string()],
+            vec![
+                "SPACE / P   Play".to_string(),
+                "W           Watch the bot".to_string(),
+                "2           Two players".to_string(),
+                format!("D           Daily ({})", daily::countdown(next_daily)),
+                "Q           Quit".to_string(),
+            ],
+        ]));
+        lines.extend([String::new(), format!("  High Score: {}", highscore)]);
+        self.backend.show_text(&lines)
+    }
+
+    /// The game-over screen of a daily challenge run, with the day's
+    /// entry on the board after it.
+    pub fn show_daily_over(
+        &mut self,
+        score: usize,
+        entry: &DailyEntry,
+        streak: u32,
+        next_daily: Duration,
+    ) -> color_eyre::Result<()> {
+        let days = if streak == 1 { "day" } else { "days" };
+        let mut lines = vec![String::new()];
+        lines.extend(self.panel(&[
+            vec![
+                "     GAME OVER!".to_string(),
+                format!("Daily challenge {}", entry.date),
+            ],
+            vec![
+                format!("Final Score: {}", score),
+                format!("Best Today:  {}", entry.best),
+                format!("Attempts:    {}", entry.attempts),
+                format!("Streak:      {} {}", streak, days),
+            ],
+        ]));
+        lines.extend([
+            String::new(),
+            format!("  Next challenge in {}", daily::countdown(next_daily)),
+            "  Press 'R' to try again or 'Q' to quit".to_string(),
+        ]);
+        self.backend.show_text(&lines)
+    }
+
+    /// Shows one step of the countdown before a two-player round, with the
+    /// rounds won so far.
+    pub fn show_round_countdown(
+        &mut self,
+        round: usize,
+        remaining: u32,
+        wins: [usize; 2],
+    ) -> color_eyre::Result<()> {
+        self.backend.show_text(&[
+            format!("Round {} starting in {}...", round, remaining),
+            String::new(),
+            format!("Player 1: {}  |  Player 2: {}", wins[0], wins[1]),
+            String::new(),
+            "Controls:".to_string(),
+            "  Player 1 (top)    - W jump, S crouch".to_string(),
+            "  Player 2 (bottom) - UP jump, DOWN crouch".to_string(),
+            "  Q - Quit".to_string(),
+        ])
+    }
+
+    /// Shows the end of a two-player round. `winner` is the player who
+    /// survived longer, counting from zero, or `None` for a draw.
+    pub fn show_round_over(
+        &mut self,
+        winner: Option<usize>,
+        scores: [usize; 2],
+        wins: [usize; 2],
+    ) -> color_eyre::Result<()> {
+        let headline = match winner {
+            Some(player) => format!("PLAYER {} WINS!", player + 1),
+            None => "DRAW!".to_string(),
+        };
+        let mut lines = vec![String::new()];
+        lines.extend(self.panel(&[
+            vec![headline],
+            vec![
+                format!("Player 1 score: {}", scores[0]),
+                format!("Player 2 score: {}", scores[1]),
+            ],
+            vec![format!("Rounds won:  {:<3} - {}", wins[0], wins[1])],
+        ]));
+        lines.extend([
+            String::new(),
+            "  Press 'R' for the next round or 'Q' to quit".to_string(),
+        ]);
+        self.backend.show_text(&lines)
+    }
+
+    /// `sections` in a box, with a rule between each, as lines indented
+    /// like the rest of a text screen.
+    fn panel(&self, sections: &[Vec<String>]) -> Vec<String> {
+        let [tl, tr, ml, mr, bl, br, h, v] = if self.scene.ascii {
+            ['+', '+', '+', '+', '+', '+', '=', '|']
+        } else {
+            ['╔', '╗', '╠', '╣', '╚', '╝', '═', '║']
+        };
+        let rule = h.to_string().repeat(PANEL_WIDTH);
+        let mut lines = vec![format!("  {}{}{}", tl, rule, tr)];
+        for (i, section) in sections.iter().enumerate() {
+            if i > 0 {
+                lines.push(format!("  {}{}{}", ml, rule, mr));
+            }
+            lines.extend(
+                section
+                    .iter()
+                    .map(|row| format!("  {}  {:<width$}{}", v, row, v, width = PANEL_WIDTH - 2)),
+            );
+        }
+        lines.push(format!("  {}{}{}", bl, rule, br));
+        lines
+    }
+
+    /// Shows a screen of plain text, such as a lobby or a results list.
+    pub fn show_message(&mut self, lines: &[String]) -> color_eyre::Result<()> {
+        self.backend.show_text(lines)
+    }
+
+    /// Shows one step of the pre-game countdown.
+    pub fn show_countdown(
+        &mut self,
+        remaining: u32,
+        frame_duration: Duration,
+    ) -> color_eyre::Result<()> {
+        let rates = format!(
+            "Physics: {} FPS | Rendering: {} FPS | Theme: {}",
+            self.scene.cfg.physics.physics_fps,
+            (1.0 / frame_duration.as_secs_f32()).round(),
+            self.scene.theme.name
+        );
+        self.backend.show_text(&[
+            format!("Starting in {}...", remaining),
+            String::new(),
+            "Controls:".to_string(),
+            "  SPACE / UP - Jump".to_string(),
+            "  DOWN - Crouch (hold)".to_string(),
+            "  Q - Quit".to_string(),
+            String::new(),
+            rates,
+        ])
+    }
+
+    pub fn clear(&mut self) -> color_eyre::Result<()> {
+        self.backend.clear()
+    }
+}
+
+impl Scene {
+    pub fn new(cfg: Cfg, width: usize, height: usize) -> color_eyre::Result<Self> {
+        let ascii = display::wants_ascii(&cfg.sprites.charset);
+        let sprites = SpriteSet::load(&cfg.sprites, ascii)?;
+        let subcell = if ascii {
+            None
+        } else {
+            SubcellMode::from_config(&cfg.render.mode)
+        };
+        let (day_theme, warnings) = Theme::from_config(&cfg.theme);
+        let color_support = ColorSupport::detect(&cfg.theme.color_mode);
+        let night_theme = if color_support == ColorSupport::None || day_theme.is_colorless() {
+            day_theme.reversed()
+        } else {
+            day_theme.night()
+        };
+        let day_night = DayNightCycle::new(cfg.day_night.clone());
+
+        Ok(Self {
+            cfg,
+            width,
+            height,
+            theme: day_theme.clone(),
+            day_theme,
+            night_theme,
+            day_night,
+            color_support,
+            sprites,
+            ascii,
+            subcell,
+            warnings,
+        })
+    }
+
+    /// Problems with the theme config, worth showing before the game takes
+    /// over the terminal.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn reset_day_night(&mut self) {
+        self.day_night.reset();
+    }
+
+    /// Picks this frame's palette. Inverted night themes cannot be faded, so
+    /// they flip over at the midpoint instead.
+    fn update_theme(&mut self, score: usize) -> f32 {
+        let blend = self.day_night.advance(score);
+        let fades = self.color_support != ColorSupport::None && !self.day_theme.is_colorless();
+
+        self.theme = if fades {
+            self.day_theme.lerp(&self.night_theme, blend)
+        } else if blend >= 0.5 {
+            self.night_theme.clone()
+        } else {
+            self.day_theme.clone()
+        };
+
+        blend
     }
 
     pub fn update_dimensions(&mut self, width: usize, height: usize) {
@@ -55,94 +451,194 @@ impl Renderer {
         self.height = height;
     }
 
-    pub fn draw(&self, draw_args: DrawArgs) -> color_eyre::Result<()> {
-        execute!(stdout(), cursor::MoveTo(0, 0))?;
+    /// Draws one frame at the scene's current size.
+    pub fn compose(&mut self, draw_args: DrawArgs) -> Buffer {
+        let night = self.update_theme(draw_args.score);
+        let mut screen = Buffer::new(self.width, self.height, self.theme.background);
 
-        let mut screen = vec![vec![' '; self.width]; self.height];
-
-        self.draw_clouds(&mut screen, draw_args.clouds);
-        self.draw_ground(&mut screen);
-        self.draw_dino(&mut screen, draw_args.dino_state, draw_args.dino_y);
-        self.draw_obstacles(&mut screen, draw_args.obstacles);
+        match self.subcell {
+            None => {
+                self.draw_clouds(
+                    &mut screen,
+                    draw_args.clouds,
+                    draw_args.stars,
+                    night,
+                    draw_args.clock,
+                );
+                self.draw_ground(&mut screen);
+                for ghost in draw_args.ghosts.iter().filter(|g| g.visible) {
+                    self.draw_dino(
+                        &mut screen,
+                        ghost.state,
+                        ghost.y,
+                        draw_args.clock,
+                        self.ghost_style(),
+                    );
+                }
+                self.draw_dino(
+                    &mut screen,
+                    draw_args.dino_state,
+                    draw_args.dino_y,
+                    draw_args.clock,
+                    self.theme.dino,
+                );
+                self.draw_obstacles(&mut screen, draw_args.obstacles, draw_args.clock);
+            }
+            Some(mode) => {
+                self.draw_night_sky(&mut screen, draw_args.stars, night, draw_args.clock);
+                self.draw_ground(&mut screen);
 
-        self.render_screen(&screen)?;
-        self.render_status(draw_args.score, draw_args.highscore, draw_args.speed)?;
+                let mut canvas = Canvas::new(self.width, self.height);
+                self.raster_clouds(&mut canvas, draw_args.clouds, draw_args.clock);
+                for ghost in draw_args.ghosts.iter().filter(|g| g.visible) {
+                    self.raster_dino(
+                        &mut canvas,
+                        ghost.state,
+                        ghost.y,
+                        draw_args.clock,
+                        self.ghost_style(),
+                    );
+                }
+                self.raster_dino(
+                    &mut canvas,
+                    draw_args.dino_state,
+                    draw_args.dino_y,
+                    draw_args.clock,
+                    self.theme.dino,
+                );
+                self.raster_obstacles(&mut canvas, draw_args.obstacles, draw_args.clock);
+                canvas.pack_into(&mut screen, mode);
+            }
+        }
+        self.draw_status(
+            &mut screen,
+            draw_args.score,
+            draw_args.highscore,
+            draw_args.speed,
+            draw_args.ghosts.first(),
+        );
+        if let Some(banner) = draw_args.banner {
+            self.draw_banner(&mut screen, banner, 1);
+        }
 
-        Ok(())
+        screen
     }
 
-    fn draw_clouds(&self, screen: &mut [Vec<char>], clouds: &[Cloud]) {
-        let cloud_art = ["  .--.  ", " (    ) ", "(_.____)"];
+    fn draw_clouds(
+        &self,
+        screen: &mut Buffer,
+        clouds: &[Cloud],
+        stars: &[Star],
+        night: f32,
+        clock: AnimClock,
+    ) {
+        self.draw_night_sky(screen, stars, night, clock);
 
         for cloud in clouds {
             let x = cloud.x as usize;
             if x < self.width.saturating_sub(6) && cloud.y < self.height.saturating_sub(2) {
-                for (dy, line) in cloud_art.iter().enumerate() {
-                    if cloud.y + dy < self.height {
-                        for (dx, ch) in line.chars().enumerate() {
-                            if x + dx < self.width {
-                                screen[cloud.y + dy][x + dx] = ch;
-                            }
-                        }
-                    }
-                }
+                self.draw_sprite(
+                    screen,
+                    &self.sprites.cloud,
+                    clock,
+                    (x, cloud.y),
+                    self.height,
+                    self.theme.cloud,
+                );
+            }
+        }
+    }
+
+    /// Draws the moon and stars once the fade is far enough into night that
+    /// they would be visible, dimmed while still fading in.
+    fn draw_night_sky(&self, screen: &mut Buffer, stars: &[Star], night: f32, clock: AnimClock) {
+        if night < 0.35 {
+            return;
+        }
+
+        let dim = |style: Style| {
+            if night < 0.75 {
+                style.with_attrs(theme::Attrs::DIM)
+            } else {
+                style
+            }
+        };
+        let star_glyphs = ['.', '+', '*', '+'];
+        let ground_y = self.height.saturating_sub(self.cfg.physics.ground_height);
+
+        for star in stars {
+            let x = star.x as usize;
+            if x < self.width && star.y < ground_y {
+                let ch = star_glyphs[(star.twinkle / 16) as usize % star_glyphs.len()];
+                screen.set(x, star.y, ch, dim(self.theme.star));
             }
         }
+
+        let moon_x = self.width.saturating_sub(16);
+        self.draw_sprite(
+            screen,
+            &self.sprites.moon,
+            clock,
+            (moon_x, 2),
+            self.height,
+            dim(self.theme.moon),
+        );
     }
 
-    fn draw_ground(&self, screen: &mut [Vec<char>]) {
+    fn draw_ground(&self, screen: &mut Buffer) {
         let ground_y = self.height.saturating_sub(self.cfg.physics.ground_height);
+        let line = if self.ascii { '-' } else { '─' };
         (ground_y..self.height).for_each(|y| {
             (0..self.width).for_each(|x| {
                 if y == ground_y {
-                    screen[y][x] = '─';
+                    screen.set(x, y, line, self.theme.ground);
                 } else if y == ground_y + 1 {
-                    screen[y][x] = if x % 3 == 0 { '.' } else { ' ' };
+                    let ch = if x % 3 == 0 { '.' } else { ' ' };
+                    screen.set(x, y, ch, self.theme.ground);
                 }
             });
         });
     }
 
-    fn draw_dino(&self, screen: &mut [Vec<char>], state: DinoState, dino_y: f32) {
-        let dino_x = 10;
+    fn draw_dino(
+        &self,
+        screen: &mut Buffer,
+        state: DinoState,
+        dino_y: f32,
+        clock: AnimClock,
+        style: Style,
+    ) {
+        let dino_x = DINO_X;
         let ground_y = self.height.saturating_sub(self.cfg.physics.ground_height);
         let dino_ground_y = ground_y.saturating_sub(6);
         let dino_screen_y = dino_ground_y.saturating_sub(dino_y as usize);
 
-        let dino_art = if matches!(state, DinoState::Crouching) {
-            vec![
-                "               ",
-                "               ",
-                "⢠⣀⢀⠀⣀⣀⣀⣀⡀⣀⡠⣄⣤⣠⡀",
-                "⠀⠹⢿⣿⣿⣿⣿⣿⣿⣿⣿⣿⠿⡿⡇",
-                "⠀⠀⠈⢹⠻⣿⠟⠛⡏⠉⠙⠋⠒⠂⠀",
-                "⠀⠀⠀⠈⠐⠇⠀⠀⠁⠁⠀⠀⠀⠀ ",
-            ]
-        } else {
-            vec![
-                "          ",
-                "     ⣾⣽⣿⣿⡇",
-                "⢀   ⢀⣿⣿⠯  ",
-                "⢸⣦⣤⣾⣿⣿⣿    ",
-                " ⠙⢿⣿⣿⡿⠃   ",
-                "  ⠸ ⠈⠇    ",
-            ]
-        };
+        self.draw_sprite(
+            screen,
+            self.sprites.dino(state),
+            clock,
+            (dino_x, dino_screen_y),
+            ground_y,
+            style,
+        );
 
-        for (dy, line) in dino_art.iter().enumerate() {
-            let y = dino_screen_y + dy;
-            if y < ground_y && y < self.height {
-                for (dx, ch) in line.chars().enumerate() {
-                    let x = dino_x + dx;
-                    if x < self.width && ch != ' ' {
-                        screen[y][x] = ch;
-                    }
-                }
-            }
+        if let Some(overlay) = self.sprites.dino_overlay(state) {
+            self.draw_sprite(
+                screen,
+                overlay,
+                clock,
+                (dino_x, dino_screen_y),
+                ground_y,
+                style,
+            );
         }
     }
 
-    fn draw_obstacles(&self, screen: &mut [Vec<char>], obstacles: &[Obstacle]) {
+    fn ghost_style(&self) -> Style {
+        self.theme.dino.with_attrs(theme::Attrs::DIM)
+    }
+
+    fn draw_obstacles(&self, screen: &mut Buffer, obstacles: &[Obstacle], clock: AnimClock) {
         let ground_y = self.height.saturating_sub(self.cfg.physics.ground_height);
 
         for obs in obstacles {
@@ -151,136 +647,289 @@ impl Renderer {
                 continue;
             }
 
-            match obs.obstacle_type {
-                ObstacleType::SmallCactus => {
-                    let art = vec!["|||", "|||"];
-                    self.draw_obstacle_art(screen, &art, obs_x, ground_y);
-                }
-                ObstacleType::MediumCactus => {
-                    let art = vec![" | ", "/|\\", " | "];
-                    self.draw_obstacle_art(screen, &art, obs_x, ground_y);
-                }
-                ObstacleType::TallCactus => {
-                    let art = vec!["  |  ", " \\|/ ", "  |  ", " /|\\ "];
-                    self.draw_obstacle_art(screen, &art, obs_x, ground_y);
-                }
-                ObstacleType::WideCactus => {
-                    let art = vec![" | | ", "/|\\|/\\", " | | "];
-                    self.draw_obstacle_art(screen, &art, obs_x, ground_y);
-                }
-                ObstacleType::PterodactylLow => {
-                    let art = vec![" ^   ^ ", "<(o.o)>", "  \\_/  "];
-                    let fly_y = ground_y.saturating_sub(8);
-                    self.draw_ptero_art(screen, &art, obs_x, fly_y);
-                }
-                ObstacleType::PterodactylMid => {
-                    let art = vec![" ^   ^ ", "<(o.o)>", "  \\_/  "];
-                    let fly_y = ground_y.saturating_sub(12);
-                    self.draw_ptero_art(screen, &art, obs_x, fly_y);
-                }
-                ObstacleType::PterodactylHigh => {
-                    let art = vec![" ^   ^ ", "<(o.o)>", "  \\_/  "];
-                    let fly_y = ground_y.saturating_sub(16);
-                    self.draw_ptero_art(screen, &art, obs_x, fly_y);
-                }
-            }
+            let top = obstacle_top(obs.obstacle_type, ground_y);
+            self.draw_sprite(
+                screen,
+                self.sprites.obstacle(obs.obstacle_type),
+                clock,
+                (obs_x, top),
+                ground_y,
+                self.theme.obstacle(obs.obstacle_type),
+            );
         }
     }
 
-    fn draw_obstacle_art(&self, screen: &mut [Vec<char>], art: &[&str], x: usize, ground_y: usize) {
-        let start_y = ground_y.saturating_sub(art.len());
-        for (dy, line) in art.iter().enumerate() {
-            let y = start_y + dy;
-            if y < ground_y && y < screen.len() {
-                for (dx, ch) in line.chars().enumerate() {
-                    let px = x + dx;
-                    if px < screen[0].len() && ch != ' ' {
-                        screen[y][px] = ch;
-                    }
+    /// Draws the current frame of `sprite` with its anchor cell on `at`,
+    /// skipping transparent glyphs and clipping rows at or below `clip_y`.
+    /// Glyphs advance by their display width so wide ones take two columns.
+    fn draw_sprite(
+        &self,
+        screen: &mut Buffer,
+        sprite: &Animation,
+        clock: AnimClock,
+        at: (usize, usize),
+        clip_y: usize,
+        style: Style,
+    ) {
+        let (anchor_x, anchor_y) = sprite.anchor;
+        let origin_x = at.0 as isize - anchor_x as isize;
+        let origin_y = at.1 as isize - anchor_y as isize;
+
+        for (dy, line) in sprite.frame(clock).lines.iter().enumerate() {
+            let y = origin_y + dy as isize;
+            if y < 0 || y as usize >= clip_y {
+                continue;
+            }
+
+            let mut x = origin_x;
+            for ch in line.chars() {
+                let column = x;
+                x += ch.width().unwrap_or(0) as isize;
+                if column < 0 || sprite.is_transparent(ch) {
+                    continue;
                 }
+
+                let glyph_style = match sprite.colors.get(&ch) {
+                    Some(&color) => Style {
+                        fg: Some(color),
+                        ..style
+                    },
+                    None => style,
+                };
+                screen.set(column as usize, y as usize, ch, glyph_style);
             }
         }
     }
 
-    fn draw_ptero_art(&self, screen: &mut [Vec<char>], art: &[&str], x: usize, y: usize) {
-        for (dy, line) in art.iter().enumerate() {
-            let py = y + dy;
-            if py < screen.len() {
-                for (dx, ch) in line.chars().enumerate() {
-                    let px = x + dx;
-                    if px < screen[0].len() && ch != ' ' {
-                        screen[py][px] = ch;
-                    }
-                }
+    fn raster_clouds(&self, canvas: &mut Canvas, clouds: &[Cloud], clock: AnimClock) {
+        for cloud in clouds {
+            self.raster_sprite(
+                canvas,
+                &self.sprites.cloud,
+                clock,
+                (cloud.x, cloud.y as f32),
+                self.height,
+                self.theme.cloud,
+            );
+        }
+    }
+
+    fn raster_dino(
+        &self,
+        canvas: &mut Canvas,
+        state: DinoState,
+        dino_y: f32,
+        clock: AnimClock,
+        style: Style,
+    ) {
+        let ground_y = self.height.saturating_sub(self.cfg.physics.ground_height);
+        let at = (DINO_X as f32, ground_y.saturating_sub(6) as f32 - dino_y);
+
+        self.raster_sprite(canvas, self.sprites.dino(state), clock, at, ground_y, style);
+
+        if let Some(overlay) = self.sprites.dino_overlay(state) {
+            self.raster_sprite(canvas, overlay, clock, at, ground_y, style);
+        }
+    }
+
+    fn raster_obstacles(&self, canvas: &mut Canvas, obstacles: &[Obstacle], clock: AnimClock) {
+        let ground_y = self.height.saturating_sub(self.cfg.physics.ground_height);
+
+        for obs in obstacles {
+            if obs.x >= self.width as f32 {
+                continue;
             }
+
+            let top = obstacle_top(obs.obstacle_type, ground_y);
+            self.raster_sprite(
+                canvas,
+                self.sprites.obstacle(obs.obstacle_type),
+                clock,
+                (obs.x, top as f32),
+                ground_y,
+                self.theme.obstacle(obs.obstacle_type),
+            );
         }
     }
 
-    fn render_screen(&self, screen: &[Vec<char>]) -> color_eyre::Result<()> {
-        for row in screen {
-            for &ch in row {
-                print!("{}", ch);
+    /// Sub-cell counterpart of [`Self::draw_sprite`]: `at` is a fractional
+    /// cell position, rounded to the nearest dot rather than the nearest cell.
+    fn raster_sprite(
+        &self,
+        canvas: &mut Canvas,
+        sprite: &Animation,
+        clock: AnimClock,
+        at: (f32, f32),
+        clip_y: usize,
+        style: Style,
+    ) {
+        let (anchor_x, anchor_y) = sprite.anchor;
+        let origin_x = ((at.0 - anchor_x as f32) * canvas::DOTS_X as f32).round() as isize;
+        let origin_y = ((at.1 - anchor_y as f32) * canvas::DOTS_Y as f32).round() as isize;
+        let clip = (clip_y * canvas::DOTS_Y) as isize;
+
+        for (dy, line) in sprite.frame(clock).lines.iter().enumerate() {
+            let y = origin_y + (dy * canvas::DOTS_Y) as isize;
+            let mut column = 0;
+
+            for ch in line.chars() {
+                let x = origin_x + (column * canvas::DOTS_X) as isize;
+                column += ch.width().unwrap_or(0);
+                if sprite.is_transparent(ch) {
+                    continue;
+                }
+
+                let glyph_style = match sprite.colors.get(&ch) {
+                    Some(&color) => Style {
+                        fg: Some(color),
+                        ..style
+                    },
+                    None => style,
+                };
+                canvas.stamp(x, y, ch, glyph_style, clip);
             }
         }
-        Ok(())
     }
 
-    fn render_status(&self, score: usize, highscore: usize, speed: f32) -> color_eyre::Result<()> {
-        print!(
-            "\rScore: {} | High: {} | Speed: {:.1}x",
+    fn draw_status(
+        &self,
+        screen: &mut Buffer,
+        score: usize,
+        highscore: usize,
+        speed: f32,
+        ghost: Option<&GhostArgs>,
+    ) {
+        let mut status = format!(
+            "Score: {} | High: {} | Speed: {:.1}x",
             score,
             highscore,
             speed / self.cfg.physics.initial_speed
         );
-        stdout().flush()?;
-        Ok(())
-    }
-
-    pub fn show_game_over(&self, score: usize, highscore: usize) -> color_eyre::Result<()> {
-        use crossterm::terminal::ClearType;
-        execute!(
-            stdout(),
-            crossterm::terminal::Clear(ClearType::All),
-            cursor::MoveTo(0, 0)
-        )?;
-        println!("\n  ╔═══════════════════════════════╗");
-        println!("  ║       GAME OVER!              ║");
-        println!("  ╠═══════════════════════════════╣");
-        println!("  ║  Final Score: {:<14}  ║", score);
-        println!("  ║  High Score:  {:<14}  ║", highscore);
-        println!("  ╚═══════════════════════════════╝");
-        println!("\n  Press 'R' to restart or 'Q' to quit");
-        stdout().flush()?;
-        Ok(())
-    }
-
-    pub fn show_countdown(&self, frame_duration: std::time::Duration) -> color_eyre::Result<()> {
-        use crossterm::terminal::ClearType;
-
-        for i in (1..=3).rev() {
-            execute!(
-                stdout(),
-                crossterm::terminal::Clear(ClearType::All),
-                cursor::MoveTo(0, 0)
-            )?;
-            println!("Starting in {}...", i);
-            println!("\nControls:");
-            println!("  SPACE / UP - Jump");
-            println!("  DOWN - Crouch (hold)");
-            println!("  Q - Quit");
-            println!(
-                "\nPhysics: {} FPS | Rendering: {} FPS",
-                self.cfg.physics.physics_fps,
-                (1.0 / frame_duration.as_secs_f32()).round()
-            );
-            stdout().flush()?;
-            std::thread::sleep(std::time::Duration::from_secs(1));
+        if let Some(ghost) = ghost {
+            let lead = match ghost.lead.cmp(&0) {
+                std::cmp::Ordering::Greater => format!("ahead by {}", ghost.lead),
+                std::cmp::Ordering::Less => format!("behind by {}", -ghost.lead),
+                std::cmp::Ordering::Equal => "level".to_string(),
+            };
+            status.push_str(&format!(" | Ghost: {}", lead));
+        }
+        screen.set_str(0, self.height.saturating_sub(1), &status, self.theme.hud);
+    }
+
+    fn draw_banner(&self, screen: &mut Buffer, banner: &str, y: usize) {
+        let text = format!(" {} ", banner);
+        let x = self.width.saturating_sub(text.chars().count()) / 2;
+        screen.set_str(x, y, &text, self.theme.hud.reversed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{backend::MemoryBackend, physics::PhysicsEngine, spawner::ObstacleSpawner},
+        std::{env, fs, path::Path},
+    };
+
+    const WIDTH: usize = 80;
+    const HEIGHT: usize = 24;
+    const TICKS: usize = 400;
+    const EVERY: usize = 25;
+
+    fn seeded_cfg(mode: &str) -> Cfg {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(7);
+        cfg.sprites.charset = "unicode".to_string();
+        cfg.theme.color_mode = "none".to_string();
+        cfg.render.mode = mode.to_string();
+        cfg
+    }
+
+    /// Plays a seeded run with no input, drawing every `EVERY`th tick and the
+    /// moment the dino dies, then the game-over screen.
+    fn seeded_run(mode: &str) -> Vec<Vec<String>> {
+        let cfg = seeded_cfg(mode);
+        let backend = MemoryBackend::new();
+        let mut renderer =
+            Renderer::with_backend(cfg.clone(), WIDTH, HEIGHT, Box::new(backend.clone()))
+                .expect("renderer");
+        let mut physics = PhysicsEngine::new(cfg.clone(), WIDTH, HEIGHT);
+        let mut spawner = ObstacleSpawner::new(cfg);
+        let mut score = 0;
+
+        for tick in 0..TICKS {
+            spawner.update_difficulty(score);
+            let alive = physics.update(&mut spawner, WIDTH, HEIGHT, &mut score);
+
+            if tick % EVERY == 0 || !alive {
+                renderer
+                    .draw(DrawArgs::new(
+                        physics.dino.state,
+                        physics.dino.y,
+                        &physics.obstacles,
+                        &physics.clouds,
+                        &physics.stars,
+                        score,
+                        score,
+                        physics.speed,
+                        physics.clock,
+                    ))
+                    .expect("draw");
+            }
+            if !alive {
+                break;
+            }
         }
-        execute!(
-            stdout(),
-            crossterm::terminal::Clear(ClearType::All),
-            cursor::MoveTo(0, 0)
-        )?;
-        Ok(())
+
+        renderer
+            .show_game_over(score, score, &[])
+            .expect("game over");
+        backend.frames()
+    }
+
+    /// Compares against `tests/golden/<name>.txt`. Run with
+    /// `UPDATE_GOLDEN=1` to rewrite the file after an intended change.
+    fn assert_golden(name: &str, frames: &[Vec<String>]) {
+        let actual: String = frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                let rows: Vec<&str> = frame.iter().map(|row| row.trim_end()).collect();
+                format!("--- frame {} ---\n{}\n", i, rows.join("\n"))
+            })
+            .collect();
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.txt", name));
+
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("{} is missing, run with UPDATE_GOLDEN=1", path.display()));
+        assert!(
+            actual == expected,
+            "frames differ from {}, run with UPDATE_GOLDEN=1 to accept",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn seeded_run_is_repeatable() {
+        assert_eq!(seeded_run("cell"), seeded_run("cell"));
+    }
+
+    #[test]
+    fn cell_frames_match_golden() {
+        assert_golden("cell", &seeded_run("cell"));
+    }
+
+    #[test]
+    fn braille_frames_match_golden() {
+        assert_golden("braille", &seeded_run("braille"));
     }
 }
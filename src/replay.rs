@@ -0,0 +1,436 @@
+use {
+    crate::{
+        config::{Cfg, GameConfig, PhysicsConfig, SpawnerConfig},
+        world::{self, Input, World},
+    },
+    color_eyre::eyre::{Context, Result, bail},
+    hmac::{Hmac, Mac},
+    serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha256},
+    std::{
+        fmt::{self, Write},
+        fs,
+        path::Path,
+    },
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Everything needed to play a run again: the seed and playfield size it
+/// started with and each input, tagged with the physics tick it came
+/// before. [`World`] records one for every run.
+///
+/// Replays only reproduce the run under the physics, game and spawner
+/// settings they were recorded with, which `config_hash` identifies.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Ticks the run lasted, once it is over.
+    pub ticks: u64,
+    pub score: usize,
+    pub inputs: Vec<(u64, Input)>,
+    /// [`config_hash`] of the settings the run was played under.
+    #[serde(default)]
+    pub config_hash: String,
+    /// HMAC-SHA256 of everything above with a shared key, in hex; see
+    /// [`Replay::sign`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// The gameplay settings a run's outcome depends on.
+#[derive(Serialize)]
+struct Gameplay<'a> {
+    physics: &'a PhysicsConfig,
+    game: GameConfig,
+    spawner: &'a SpawnerConfig,
+}
+
+/// SHA-256, in hex, of the settings that decide how a run plays out. Two
+/// configs with the same hash replay every run the same way.
+pub fn config_hash(cfg: &Cfg) -> String {
+    let mut game = cfg.game.clone();
+    // The seed travels with the replay, and attract mode never touches a
+    // run in progress.
+    game.seed = None;
+    game.attract_after = 0.0;
+    let gameplay = Gameplay {
+        physics: &cfg.physics,
+        game,
+        spawner: &cfg.spawner,
+    };
+    let text = toml::to_string(&gameplay).unwrap_or_default();
+    hex(&Sha256::digest(text.as_bytes()))
+}
+
+/// Reads a signing key: the contents of the file at `path`, without
+/// surrounding whitespace.
+pub fn load_key(path: &Path) -> Result<Vec<u8>> {
+    let key = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let key = key.trim_ascii();
+    if key.is_empty() {
+        bail!("{} holds no key", path.display());
+    }
+    Ok(key.to_vec())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut text, byte| {
+        write!(text, "{:02x}", byte).ok();
+        text
+    })
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Why [`Replay::verify`] turned a replay down.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// Played under other gameplay settings, or recorded before replays
+    /// carried a config hash.
+    Config {
+        expected: String,
+        found: String,
+    },
+    /// Played on a playfield smaller than any world can be.
+    Size {
+        minimum: (usize, usize),
+        found: (usize, usize),
+    },
+    Unsigned,
+    BadSignature,
+    /// Inputs out of order, or after the run ended.
+    Inputs,
+    /// The dino is still alive where the run claims to end.
+    Unfinished {
+        ticks: u64,
+    },
+    /// The dino dies at another tick than claimed.
+    Ticks {
+        claimed: u64,
+        replayed: u64,
+    },
+    Score {
+        claimed: usize,
+        replayed: usize,
+    },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Config { found, .. } if found.is_empty() => {
+                write!(f, "the replay has no config hash")
+            }
+            Self::Config { expected, found } => write!(
+                f,
+                "played under different settings (config hash {:.12}, expected {:.12})",
+                found, expected
+            ),
+            Self::Size { minimum, found } => write!(
+                f,
+                "played on a {}x{} playfield, smaller than the {}x{} a game needs",
+                found.0, found.1, minimum.0, minimum.1
+            ),
+            Self::Unsigned => write!(f, "the replay is not signed"),
+            Self::BadSignature => write!(
+                f,
+                "the signature does not match; the replay was changed or signed with another key"
+            ),
+            Self::Inputs => write!(f, "inputs are out of order or come after the run ends"),
+            Self::Unfinished { ticks } => {
+                write!(
+                    f,
+                    "the dino is still alive at tick {}, where the run claims to end",
+                    ticks
+                )
+            }
+            Self::Ticks { claimed, replayed } => {
+                write!(f, "the dino dies at tick {}, not {}", replayed, claimed)
+            }
+            Self::Score { claimed, replayed } => {
+                write!(
+                    f,
+                    "the run scores {}, not the claimed {}",
+                    replayed, claimed
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// A replay [`Replay::verify`] accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Verified {
+    pub score: usize,
+    pub ticks: u64,
+    /// Whether the signature was checked.
+    pub signed: bool,
+}
+
+impl Replay {
+    pub fn new(seed: u64, width: usize, height: usize) -> Self {
+        Self {
+            seed,
+            width,
+            height,
+            ..Self::default()
+        }
+    }
+
+    /// Adds an input applied before `tick`. A repeated crouch or stand
+    /// changes nothing, so only the first of a run of them is kept.
+    pub fn record(&mut self, tick: u64, input: Input) {
+        let repeat = matches!(input, Input::Crouch | Input::Stand)
+            && self.inputs.last().is_some_and(|&(_, last)| last == input);
+        if !repeat {
+            self.inputs.push((tick, input));
+        }
+    }
+
+    pub fn finish(&mut self, ticks: u64, score: usize) {
+        self.ticks = ticks;
+        self.score = score;
+    }
+
+    /// Inputs to apply before `tick`, in the order they were recorded.
+    pub fn inputs_at(&self, tick: u64) -> impl Iterator<Item = Input> + '_ {
+        let start = self.inputs.partition_point(|&(t, _)| t < tick);
+        let end = self.inputs.partition_point(|&(t, _)| t <= tick);
+        self.inputs[start..end].iter().map(|&(_, input)| input)
+    }
+
+    /// Signs the replay with `key`, replacing any earlier signature.
+    pub fn sign(&mut self, key: &[u8]) {
+        self.signature = Some(hex(&self.mac(key).finalize().into_bytes()));
+    }
+
+    fn mac(&self, key: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+        mac.update(self.signed_text().as_bytes());
+        mac
+    }
+
+    /// Everything the signature covers, which is everything but itself.
+    fn signed_text(&self) -> String {
+        let mut text = format!(
+            "{}:{}:{}:{}:{}:{}:",
+            self.seed, self.width, self.height, self.ticks, self.score, self.config_hash
+        );
+        for &(tick, input) in &self.inputs {
+            let code = match input {
+                Input::Jump => 'J',
+                Input::Crouch => 'C',
+                Input::Stand => 'S',
+            };
+            write!(text, "{}{};", tick, code).ok();
+        }
+        text
+    }
+
+    /// Plays the run again under `cfg` and checks that it ends at the tick
+    /// and score it claims, on the playfield size it was recorded at. With
+    /// a `key` it must also be signed with it.
+    pub fn verify(&self, cfg: &Cfg, key: Option<&[u8]>) -> Result<Verified, Rejection> {
+        let expected = config_hash(cfg);
+        if self.config_hash != expected {
+            return Err(Rejection::Config {
+                expected,
+                found: self.config_hash.clone(),
+            });
+        }
+        let (minimum, found) = (world::min_size(cfg), (self.width, self.height));
+        if found.0 < minimum.0 || found.1 < minimum.1 {
+            return Err(Rejection::Size { minimum, found });
+        }
+        if let Some(key) = key {
+            let signature = self.signature.as_deref().ok_or(Rejection::Unsigned)?;
+            let signature = unhex(signature).ok_or(Rejection::BadSignature)?;
+            self.mac(key)
+                .verify_slice(&signature)
+                .map_err(|_| Rejection::BadSignature)?;
+        }
+
+        let ordered = self.inputs.windows(2).all(|pair| pair[0].0 <= pair[1].0);
+        if !ordered
+            || self
+                .inputs
+                .last()
+                .is_some_and(|&(tick, _)| tick >= self.ticks)
+        {
+            return Err(Rejection::Inputs);
+        }
+
+        let world = self.simulate(cfg);
+        if !world.is_over() {
+            return Err(Rejection::Unfinished {
+                ticks: world.ticks(),
+            });
+        }
+        if world.ticks() != self.ticks {
+            return Err(Rejection::Ticks {
+                claimed: self.ticks,
+                replayed: world.ticks(),
+            });
+        }
+        if world.score() != self.score {
+            return Err(Rejection::Score {
+                claimed: self.score,
+                replayed: world.score(),
+            });
+        }
+        Ok(Verified {
+            score: self.score,
+            ticks: self.ticks,
+            signed: key.is_some(),
+        })
+    }
+
+    /// A fresh world set up like the one the run started in.
+    pub fn world(&self, cfg: &Cfg) -> World {
+        let mut cfg = cfg.clone();
+        cfg.game.seed = Some(self.seed);
+        World::new(cfg, self.width, self.height)
+    }
+
+    /// Plays the run again under `cfg` and returns the world as it ended.
+    pub fn simulate(&self, cfg: &Cfg) -> World {
+        let mut world = self.world(cfg);
+        while !world.is_over() && world.ticks() < self.ticks {
+            let inputs: Vec<Input> = self.inputs_at(world.ticks()).collect();
+            world.tick(&inputs);
+        }
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::time::Duration};
+
+    #[test]
+    fn recorded_run_replays_to_the_same_score() {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(9);
+        let mut world = World::new(cfg.clone(), 80, 24);
+
+        let mut frame = 0;
+        while !world.is_over() {
+            let inputs = match frame % 40 {
+                0 => vec![Input::Jump],
+                20..=25 => vec![Input::Crouch],
+                _ => vec![Input::Stand],
+            };
+            world.step(Duration::from_millis(17), &inputs);
+            frame += 1;
+        }
+
+        let replay = world.replay().clone();
+        assert_eq!(replay.score, world.score());
+
+        let replayed = replay.simulate(&cfg);
+        assert!(replayed.is_over());
+        assert_eq!(replayed.score(), replay.score);
+        assert_eq!(replayed.ticks(), replay.ticks);
+    }
+
+    #[test]
+    fn verify_rejects_edited_unsigned_and_foreign_replays() {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(3);
+        let mut world = World::new(cfg.clone(), 80, 24);
+        while !world.is_over() {
+            let inputs = match world.ticks() % 25 {
+                0 => vec![Input::Jump],
+                _ => vec![Input::Stand],
+            };
+            world.tick(&inputs);
+        }
+        let mut replay = world.replay().clone();
+        let verified = replay.verify(&cfg, None).expect("untouched replay");
+        assert_eq!(verified.score, world.score());
+        assert!(!verified.signed);
+
+        let key = b"team secret";
+        assert_eq!(replay.verify(&cfg, Some(key)), Err(Rejection::Unsigned));
+        replay.sign(key);
+        assert!(replay.verify(&cfg, Some(key)).expect("signed").signed);
+        assert_eq!(
+            replay.verify(&cfg, Some(b"other key")),
+            Err(Rejection::BadSignature)
+        );
+
+        let mut edited = replay.clone();
+        edited.score += 100;
+        assert_eq!(edited.verify(&cfg, Some(key)), Err(Rejection::BadSignature));
+        assert_eq!(
+            edited.verify(&cfg, None),
+            Err(Rejection::Score {
+                claimed: replay.score + 100,
+                replayed: replay.score,
+            })
+        );
+
+        let mut longer = replay.clone();
+        longer.ticks += 50;
+        assert!(matches!(
+            longer.verify(&cfg, None),
+            Err(Rejection::Ticks { replayed, .. }) if replayed == replay.ticks
+        ));
+
+        let mut easier = cfg.clone();
+        easier.physics.gravity *= 0.5;
+        assert!(matches!(
+            replay.verify(&easier, None),
+            Err(Rejection::Config { .. })
+        ));
+        // Neither the seed nor attract mode changes how a run plays.
+        easier = cfg.clone();
+        easier.game.seed = None;
+        easier.game.attract_after = 0.0;
+        assert_eq!(config_hash(&easier), replay.config_hash);
+    }
+
+    #[test]
+    fn verify_plays_back_at_the_recorded_size() {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(5);
+        // Free play records at whatever size the terminal was.
+        for (width, height) in [(cfg.env.width, cfg.env.height), (120, 30), (20, 19)] {
+            let mut world = World::new(cfg.clone(), width, height);
+            while !world.is_over() {
+                world.tick(&[]);
+            }
+            let replay = world.replay().clone();
+            assert_eq!((replay.width, replay.height), (width, height));
+            assert!(replay.verify(&cfg, None).is_ok(), "{}x{}", width, height);
+        }
+
+        // Too short for a world.
+        let mut world = World::new(cfg.clone(), 80, 24);
+        while !world.is_over() {
+            world.tick(&[]);
+        }
+        let mut short = world.replay().clone();
+        short.height = 10;
+        assert_eq!(
+            short.verify(&cfg, None),
+            Err(Rejection::Size {
+                minimum: world::min_size(&cfg),
+                found: (80, 10),
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! `serve`: lets remote terminals play over telnet. Each connection gets
+//! its own game, drawn as ANSI escapes down the socket and steered by the
+//! keys that come back up it. Nothing is drawn on the server's terminal,
+//! and nothing a session plays is saved on the server.
+
+use {
+    crate::{
+        game::{Game, Pilot, Terminal},
+        input::InputHandler,
+        sound::SoundPlayer,
+    },
+    color_eyre::{Result, eyre::WrapErr},
+    crossterm::{
+        cursor::{Hide, Show},
+        execute,
+        terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    dino_rs::{
+        backend::CrosstermBackend,
+        config::Cfg,
+        display::DisplaySettings,
+        rendering::Renderer,
+        session::{self, DEFAULT_SIZE, Seats, session_cfg},
+        telnet::{self, Key},
+        theme::{ColorSupport, Theme},
+        world,
+    },
+    std::{
+        io::{BufWriter, Write},
+        net::{Shutdown, TcpListener, TcpStream},
+        sync::{Arc, Mutex, mpsc},
+        thread,
+        time::Duration,
+    },
+};
+
+/// How long to wait for the client's first window size report.
+const NAWS_WAIT: Duration = Duration::from_secs(1);
+/// A client that stops reading for this long is dropped rather than
+/// stalling its game.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the reader wakes to check the session's timeouts.
+const READ_TICK: Duration = Duration::from_secs(1);
+
+pub fn run(cfg: Cfg) -> Result<()> {
+    let cfg = session_cfg(cfg);
+    // Every session builds the same theme, so its problems are told once
+    // here rather than per connection.
+    crate::warn(&Theme::from_config(&cfg.theme).1);
+    let port = cfg.serve.port;
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .wrap_err_with(|| format!("Failed to listen on port {}", port))?;
+    println!(
+        "Serving on port {} for up to {} players; connect with `telnet <host> {}`",
+        port, cfg.serve.max_sessions, port
+    );
+
+    let seats = Seats::new(cfg.serve.max_sessions);
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+
+        let Some(seat) = seats.take() else {
+            stream
+                .write_all(b"All seats are taken; try again later.\r\n")
+                .ok();
+            continue;
+        };
+        let cfg = cfg.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("session {}", peer))
+            .spawn(move || {
+                let _seat = seat;
+                println!("{} connected", peer);
+                match session(cfg, stream) {
+                    Ok(()) => println!("{} left", peer),
+                    Err(e) => eprintln!("{} dropped: {}", peer, e),
+                }
+            });
+        if let Err(e) = spawned {
+            eprintln!("Failed to start a session: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Plays one connection's game until the player quits, goes idle or runs
+/// out of time.
+fn session(cfg: Cfg, stream: TcpStream) -> Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut out = BufWriter::new(stream.try_clone()?);
+    out.write_all(&telnet::HANDSHAKE)?;
+    out.flush()?;
+
+    let min = world::min_size(&cfg);
+    let size = Arc::new(Mutex::new(DEFAULT_SIZE));
+    let (keys, received) = mpsc::channel();
+    let (sized, first_size) = mpsc::channel();
+    let reader = stream.try_clone()?;
+    let (shared, serve) = (Arc::clone(&size), cfg.serve.clone());
+    reader.set_read_timeout(Some(READ_TICK))?;
+    thread::spawn(move || session::read_client(reader, &serve, min, &shared, &keys, &sized));
+    // Clients that support NAWS answer the handshake straight away.
+    let (width, height) = first_size.recv_timeout(NAWS_WAIT).unwrap_or(DEFAULT_SIZE);
+    if width < min.0 || height < min.1 {
+        write!(
+            out,
+            "Your terminal is {}x{}; the game needs at least {}x{}.\r\n",
+            width, height, min.0, min.1
+        )?;
+        out.flush()?;
+        stream.shutdown(Shutdown::Both).ok();
+        return Ok(());
+    }
+
+    execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+    let result = remote_terminal(&cfg, &stream, size, received)
+        .and_then(|terminal| Game::new(cfg, Pilot::Bot, terminal))
+        .and_then(|game| crate::run_game(game, false));
+    execute!(out, LeaveAlternateScreen, Show).ok();
+    stream.shutdown(Shutdown::Both).ok();
+    result
+}
+
+fn remote_terminal(
+    cfg: &Cfg,
+    stream: &TcpStream,
+    size: Arc<Mutex<(usize, usize)>>,
+    keys: mpsc::Receiver<Key>,
+) -> Result<Terminal> {
+    // The server's own terminal says nothing about the client's, and
+    // nearly every terminal that speaks telnet today has 256 colors.
+    let color_support = match cfg.theme.color_mode.as_str() {
+        "auto" => ColorSupport::Ansi256,
+        mode => ColorSupport::detect(mode),
+    };
+    let display = DisplaySettings::remote(size);
+    let backend = CrosstermBackend::with_writer(color_support, Box::new(stream.try_clone()?));
+    Ok(Terminal {
+        input: InputHandler::remote(keys),
+        renderer: Renderer::with_backend(
+            cfg.clone(),
+            display.width,
+            display.height,
+            Box::new(backend),
+        )?,
+        display,
+        player: SoundPlayer::silent(),
+    })
+}
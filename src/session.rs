@@ -0,0 +1,274 @@
+//! What the binary's `serve` command needs to run many games side by side:
+//! a cap on how many play at once, a config that keeps remote runs out of
+//! the host's files, and a reader that turns a telnet client's bytes into
+//! keys and window sizes until the session should end.
+
+use {
+    crate::{
+        config::{Cfg, ServeConfig},
+        telnet::{Decoder, Key, TelnetEvent},
+    },
+    std::{
+        io::{ErrorKind, Read},
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+            mpsc::Sender,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// The window size assumed until the client reports one.
+pub const DEFAULT_SIZE: (usize, usize) = (80, 24);
+/// Larger reported windows are played at this size, which is plenty for
+/// the game and keeps a client from making the server allocate screens of
+/// any size it likes.
+pub const MAX_SIZE: (usize, usize) = (400, 200);
+
+/// The host's config with every file a game keeps turned off. Sessions
+/// run side by side in one process, and a remote player's runs have no
+/// place in the host's high score, daily board, ghosts or replays.
+pub fn session_cfg(mut cfg: Cfg) -> Cfg {
+    cfg.highscore_file.clear();
+    cfg.daily_file.clear();
+    cfg.ghost_dir.clear();
+    cfg.replay_dir.clear();
+    cfg.leaderboard.server.clear();
+    cfg
+}
+
+/// The sessions allowed to run at once.
+pub struct Seats {
+    active: Arc<AtomicUsize>,
+    max: usize,
+}
+
+impl Seats {
+    pub fn new(max: usize) -> Self {
+        Self {
+            active: Arc::new(AtomicUsize::new(0)),
+            max,
+        }
+    }
+
+    /// A seat for one more session, or `None` while all are taken.
+    pub fn take(&self) -> Option<Seat> {
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                (active < self.max).then_some(active + 1)
+            })
+            .ok()
+            .map(|_| Seat(Arc::clone(&self.active)))
+    }
+}
+
+/// Counts a running session for as long as it is held.
+pub struct Seat(Arc<AtomicUsize>);
+
+impl Drop for Seat {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Why [`read_client`] stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ended {
+    /// The client hung up, or the game stopped taking keys.
+    HungUp,
+    /// No key for `serve.idle_timeout` seconds.
+    Idle,
+    /// The session reached `serve.session_timeout` seconds.
+    TimedOut,
+}
+
+/// Decodes what the client sends into keys and window sizes until it
+/// hangs up or a timeout passes. `client` must time its reads out, as
+/// with [`std::net::TcpStream::set_read_timeout`], for the timeouts to be
+/// checked while it is quiet.
+///
+/// Sizes are passed to `sized` as reported, but stored in `size` kept
+/// between `min` and [`MAX_SIZE`], so a later resize cannot take the game
+/// outside what it can run.
+pub fn read_client(
+    mut client: impl Read,
+    serve: &ServeConfig,
+    min: (usize, usize),
+    size: &Mutex<(usize, usize)>,
+    keys: &Sender<Key>,
+    sized: &Sender<(usize, usize)>,
+) -> Ended {
+    let idle = (serve.idle_timeout > 0.0).then(|| Duration::from_secs_f32(serve.idle_timeout));
+    let deadline = (serve.session_timeout > 0.0)
+        .then(|| Instant::now() + Duration::from_secs_f32(serve.session_timeout));
+
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 256];
+    let mut last_key = Instant::now();
+    loop {
+        match client.read(&mut buf) {
+            Ok(0) => return Ended::HungUp,
+            Ok(read) => {
+                for event in decoder.feed(&buf[..read]) {
+                    match event {
+                        TelnetEvent::Key(key) => {
+                            last_key = Instant::now();
+                            if keys.send(key).is_err() {
+                                return Ended::HungUp;
+                            }
+                        }
+                        TelnetEvent::Resize { width, height } => {
+                            *size.lock().unwrap_or_else(|e| e.into_inner()) = (
+                                width.clamp(min.0, MAX_SIZE.0),
+                                height.clamp(min.1, MAX_SIZE.1),
+                            );
+                            sized.send((width, height)).ok();
+                        }
+                    }
+                }
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => return Ended::HungUp,
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ended::TimedOut;
+        }
+        if idle.is_some_and(|idle| last_key.elapsed() >= idle) {
+            return Ended::Idle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{io, sync::mpsc, thread},
+    };
+
+    /// A client that types `keys`, one per read, then goes quiet but stays
+    /// connected. Reads time out after a short wait, like a socket's.
+    struct Client {
+        keys: Vec<u8>,
+    }
+
+    impl Read for Client {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(10));
+            match self.keys.pop() {
+                Some(key) => {
+                    buf[0] = key;
+                    Ok(1)
+                }
+                None => Err(ErrorKind::WouldBlock.into()),
+            }
+        }
+    }
+
+    fn serve(idle_timeout: f32, session_timeout: f32) -> ServeConfig {
+        ServeConfig {
+            idle_timeout,
+            session_timeout,
+            ..ServeConfig::default()
+        }
+    }
+
+    /// What came of reading a client to the end.
+    struct Outcome {
+        ended: Ended,
+        keys: Vec<Key>,
+        /// Window sizes as the client reported them.
+        reported: Vec<(usize, usize)>,
+        /// The size the game was left with.
+        size: (usize, usize),
+    }
+
+    fn read(client: impl Read, serve: &ServeConfig) -> Outcome {
+        let size = Mutex::new(DEFAULT_SIZE);
+        let (keys, typed) = mpsc::channel();
+        let (sized, reported) = mpsc::channel();
+        let ended = read_client(client, serve, (20, 19), &size, &keys, &sized);
+        let size = *size.lock().unwrap();
+        Outcome {
+            ended,
+            keys: typed.try_iter().collect(),
+            reported: reported.try_iter().collect(),
+            size,
+        }
+    }
+
+    #[test]
+    fn seats_cap_the_sessions_running_at_once() {
+        let seats = Seats::new(2);
+        let first = seats.take().expect("first seat");
+        let second = seats.take().expect("second seat");
+        assert!(seats.take().is_none());
+
+        drop(first);
+        let third = seats.take().expect("a freed seat");
+        assert!(seats.take().is_none());
+        drop((second, third));
+        assert!(seats.take().is_some());
+
+        assert!(Seats::new(0).take().is_none());
+    }
+
+    #[test]
+    fn sessions_keep_nothing_in_the_hosts_files() {
+        let mut cfg = Cfg::default();
+        cfg.leaderboard.server = "scores.example.org:7880".to_string();
+        let cfg = session_cfg(cfg);
+        assert!(cfg.highscore_file.is_empty());
+        assert!(cfg.daily_file.is_empty());
+        assert!(cfg.ghost_dir.is_empty());
+        assert!(cfg.replay_dir.is_empty());
+        assert!(cfg.leaderboard.server.is_empty());
+    }
+
+    #[test]
+    fn window_sizes_are_kept_to_what_the_game_can_run() {
+        // NAWS reports of 1000x5, then 500x300, around a key press.
+        let bytes: &[u8] = &[
+            255, 250, 31, 0x03, 0xe8, 0, 5, 255, 240, b'a', 255, 250, 31, 0x01, 0xf4, 0x01, 0x2c,
+            255, 240,
+        ];
+        let outcome = read(bytes, &serve(0.0, 0.0));
+        assert_eq!(outcome.ended, Ended::HungUp);
+        assert_eq!(outcome.keys, [Key::Char('a')]);
+        assert_eq!(outcome.reported, [(1000, 5), (500, 300)]);
+        assert_eq!(outcome.size, MAX_SIZE);
+
+        let naws = |w: u16, h: u16| {
+            let ([w1, w0], [h1, h0]) = (w.to_be_bytes(), h.to_be_bytes());
+            [255, 250, 31, w1, w0, h1, h0, 255, 240]
+        };
+        assert_eq!(read(&naws(1000, 5)[..], &serve(0.0, 0.0)).size, (400, 19));
+        assert_eq!(read(&naws(10, 40)[..], &serve(0.0, 0.0)).size, (20, 40));
+        assert_eq!(read(&naws(120, 30)[..], &serve(0.0, 0.0)).size, (120, 30));
+    }
+
+    #[test]
+    fn quiet_clients_are_dropped_and_sessions_end_on_time() {
+        // Three keys, then silence past the idle timeout.
+        let start = Instant::now();
+        let client = Client {
+            keys: b"abc".to_vec(),
+        };
+        let outcome = read(client, &serve(0.2, 0.0));
+        assert_eq!(outcome.ended, Ended::Idle);
+        assert_eq!(outcome.keys.len(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // Typing all along, past the session's length.
+        let start = Instant::now();
+        let client = Client {
+            keys: vec![b' '; 1000],
+        };
+        let outcome = read(client, &serve(0.2, 0.5));
+        assert_eq!(outcome.ended, Ended::TimedOut);
+        assert!(outcome.keys.len() > 20);
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+}
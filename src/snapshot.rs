@@ -0,0 +1,99 @@
+use crate::{
+    physics::PhysicsEngine,
+    sprites::AnimClock,
+    types::{Cloud, DinoState, Obstacle, Star},
+};
+
+/// Positions of everything that moves, copied out of the physics engine
+/// after a tick.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub dino_state: DinoState,
+    pub dino_y: f32,
+    pub obstacles: Vec<Obstacle>,
+    pub clouds: Vec<Cloud>,
+    pub stars: Vec<Star>,
+    pub speed: f32,
+    pub clock: AnimClock,
+}
+
+impl Snapshot {
+    pub fn capture(physics: &PhysicsEngine) -> Self {
+        Self {
+            dino_state: physics.dino.state,
+            dino_y: physics.dino.y,
+            obstacles: physics.obstacles.clone(),
+            clouds: physics.clouds.clone(),
+            stars: physics.stars.clone(),
+            speed: physics.speed,
+            clock: physics.clock,
+        }
+    }
+
+    /// Blends from `self` (the previous tick) towards `next` by `alpha` in
+    /// `0.0..=1.0`. Objects that only exist in `next`, or that wrapped around
+    /// the screen in between, are shown where `next` has them.
+    pub fn interpolate(&self, next: &Self, alpha: f32, width: usize) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * alpha;
+        let wrap_limit = width as f32 / 2.0;
+        let blend_x = |a: f32, b: f32| {
+            if (b - a).abs() > wrap_limit {
+                b
+            } else {
+                lerp(a, b)
+            }
+        };
+
+        let obstacles = next
+            .obstacles
+            .iter()
+            .map(|obs| {
+                let x = self
+                    .obstacles
+                    .iter()
+                    .find(|prev| prev.id == obs.id)
+                    .map_or(obs.x, |prev| lerp(prev.x, obs.x));
+                Obstacle { x, ..obs.clone() }
+            })
+            .collect();
+
+        let clouds = next
+            .clouds
+            .iter()
+            .enumerate()
+            .map(|(i, cloud)| match self.clouds.get(i) {
+                Some(prev) if prev.y == cloud.y => Cloud {
+                    x: blend_x(prev.x, cloud.x),
+                    ..cloud.clone()
+                },
+                _ => cloud.clone(),
+            })
+            .collect();
+
+        let stars = next
+            .stars
+            .iter()
+            .enumerate()
+            .map(|(i, star)| match self.stars.get(i) {
+                Some(prev) => Star {
+                    x: blend_x(prev.x, star.x),
+                    ..star.clone()
+                },
+                None => star.clone(),
+            })
+            .collect();
+
+        Self {
+            dino_state: next.dino_state,
+            dino_y: lerp(self.dino_y, next.dino_y),
+            obstacles,
+            clouds,
+            stars,
+            speed: lerp(self.speed, next.speed),
+            clock: AnimClock {
+                distance: lerp(self.clock.distance, next.clock.distance),
+                elapsed: lerp(self.clock.elapsed, next.clock.elapsed),
+            },
+        }
+    }
+}
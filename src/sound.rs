@@ -17,7 +17,8 @@ pub struct Asset;
 
 #[derive(Clone)]
 pub struct SoundPlayer {
-    stream: Arc<OutputStream>,
+    /// `None` for a silent player.
+    stream: Option<Arc<OutputStream>>,
     music_sink: Arc<Mutex<Option<Sink>>>,
 }
 
@@ -27,7 +28,7 @@ impl Default for SoundPlayer {
             .expect("Failed to create audio output stream");
 
         Self {
-            stream: Arc::new(stream_handle),
+            stream: Some(Arc::new(stream_handle)),
             music_sink: Arc::new(Mutex::new(None)),
         }
     }
@@ -38,8 +39,20 @@ impl SoundPlayer {
         Self::default()
     }
 
+    /// A player that makes no sound, for games shown on someone else's
+    /// terminal.
+    pub fn silent() -> Self {
+        Self {
+            stream: None,
+            music_sink: Arc::new(Mutex::new(None)),
+        }
+    }
+
     pub fn play_bg_music(&self) -> Result<()> {
         self.stop_music();
+        let Some(stream) = &self.stream else {
+            return Ok(());
+        };
 
         let file_data = Asset::get("bgmusic.mp3")
             .context("Failed to load background music from embedded assets")?;
@@ -50,7 +63,7 @@ impl SoundPlayer {
             .repeat_infinite()
             .amplify(0.3);
 
-        let sink = rodio::Sink::connect_new(self.stream.mixer());
+        let sink = rodio::Sink::connect_new(stream.mixer());
         sink.append(source);
         sink.play();
 
@@ -63,6 +76,9 @@ impl SoundPlayer {
 
     pub fn play_death_screen_music(&self) -> Result<()> {
         self.stop_music();
+        let Some(stream) = &self.stream else {
+            return Ok(());
+        };
 
         let file_data = Asset::get("death_screen.mp3")
             .context("Failed to load death screen music from embedded assets")?;
@@ -73,7 +89,7 @@ impl SoundPlayer {
             .repeat_infinite()
             .amplify(0.3);
 
-        let sink = rodio::Sink::connect_new(self.stream.mixer());
+        let sink = rodio::Sink::connect_new(stream.mixer());
         sink.append(source);
         sink.play();
 
@@ -121,7 +137,9 @@ impl SoundPlayer {
     }
 
     fn play_sound_effect_concurrent(&self, filename: &str, volume: f32) -> Result<()> {
-        let stream = Arc::clone(&self.stream);
+        let Some(stream) = self.stream.clone() else {
+            return Ok(());
+        };
         let filename = filename.to_string();
 
         thread::spawn(move || {
@@ -1,49 +1,55 @@
 use crate::config::Cfg;
+use crate::physics::seeded_rng;
 use crate::types::{Obstacle, ObstacleType};
-use rand::Rng;
+use rand::{Rng, rngs::StdRng};
 
+/// Decides when the next obstacle appears, how far away and what kind,
+/// getting harder as the score rises.
 pub struct ObstacleSpawner {
     cfg: Cfg,
     min_gap: f32,
     max_gap: f32,
     difficulty_score: usize,
+    rng: StdRng,
 }
 
 impl ObstacleSpawner {
     pub fn new(cfg: Cfg) -> Self {
+        let rng = seeded_rng(cfg.game.seed, 1);
         Self {
+            min_gap: cfg.spawner.min_gap,
+            max_gap: cfg.spawner.max_gap,
             cfg,
-            min_gap: 50.0,
-            max_gap: 120.0,
             difficulty_score: 0,
+            rng,
         }
     }
 
     pub fn update_difficulty(&mut self, score: usize) {
         self.difficulty_score = score;
-        let difficulty_factor = (score as f32 / 1000.0).min(1.0);
-        self.min_gap = 50.0 - (difficulty_factor * 15.0);
-        self.max_gap = 120.0 - (difficulty_factor * 30.0);
+        let spawner = &self.cfg.spawner;
+        let difficulty_factor = (score as f32 / spawner.ramp_score.max(1) as f32).min(1.0);
+        self.min_gap = spawner.min_gap - (difficulty_factor * spawner.min_gap_shrink);
+        self.max_gap = spawner.max_gap - (difficulty_factor * spawner.max_gap_shrink);
     }
 
     pub fn should_spawn(&self, rightmost_obstacle_x: f32, screen_width: f32) -> bool {
         rightmost_obstacle_x < screen_width - self.min_gap
     }
 
-    pub fn spawn_next(&self, last_x: f32) -> Obstacle {
+    pub fn spawn_next(&mut self, last_x: f32) -> Obstacle {
         let gap = self.get_spawn_distance();
         let new_x = last_x + gap;
         let obstacle_type = self.choose_obstacle_type();
         Obstacle::new(new_x, obstacle_type)
     }
 
-    fn get_spawn_distance(&self) -> f32 {
-        let mut rng = rand::rng();
-        rng.random_range(self.min_gap..=self.max_gap)
+    fn get_spawn_distance(&mut self) -> f32 {
+        self.rng.random_range(self.min_gap..=self.max_gap)
     }
 
-    fn choose_obstacle_type(&self) -> ObstacleType {
-        let mut rng = rand::rng();
+    fn choose_obstacle_type(&mut self) -> ObstacleType {
+        let rng = &mut self.rng;
         let can_spawn_ptero = self.difficulty_score > self.cfg.game.ptero_spawn_score;
         let use_hard_obstacles = self.difficulty_score > self.cfg.game.hard_obstacles_score
             && rng.random_range(0..100) < 30;
@@ -0,0 +1,528 @@
+//! Streaming a game to read-only viewers over a local TCP or Unix socket,
+//! one JSON [`Update`] per line.
+//!
+//! A [`SpectatorBackend`] wraps the game's own backend and hands each
+//! screen it shows to a broadcaster thread, which sends viewers the whole
+//! screen when they connect or fall behind and only the rows that changed
+//! otherwise. [`subscribe`] connects to a broadcast and [`Screen`] puts
+//! the updates back together.
+
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+};
+use {
+    crate::{backend::Backend, buffer::Buffer, theme::Style},
+    serde::{Deserialize, Serialize},
+    std::{
+        io::{self, BufRead, BufReader, Read, Write},
+        mem,
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::{
+            Arc, Condvar, Mutex,
+            mpsc::{self, Receiver, SyncSender, TrySendError},
+        },
+        thread,
+        time::Duration,
+    },
+};
+
+/// Lines queued for one viewer before it counts as fallen behind.
+const QUEUE: usize = 8;
+/// How long a write to a viewer may stall before it is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Cells in a row that share a style. Continuation cells of double-width
+/// glyphs stay in `text`, so every char is one column.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+}
+
+pub type Row = Vec<Span>;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Update {
+    /// A whole frame, status line included.
+    Frame {
+        width: usize,
+        height: usize,
+        rows: Vec<Row>,
+    },
+    /// The rows of the last frame that changed, by index.
+    Diff {
+        rows: Vec<(usize, Row)>,
+    },
+    /// A text screen, such as the title or game over.
+    Text {
+        lines: Vec<String>,
+    },
+    Clear,
+}
+
+/// Splits `screen` into rows of styled spans.
+pub fn rows(screen: &Buffer) -> Vec<Row> {
+    screen
+        .rows()
+        .map(|cells| {
+            let mut row = Row::new();
+            for cell in cells {
+                match row.last_mut() {
+                    Some(span) if span.style == cell.style => span.text.push(cell.ch),
+                    _ => row.push(Span {
+                        style: cell.style,
+                        text: cell.ch.to_string(),
+                    }),
+                }
+            }
+            row
+        })
+        .collect()
+}
+
+/// What to send viewers who saw `last` so they see `next`: a diff between
+/// two frames of one size, or else `next` itself.
+fn change(last: Option<&Update>, next: &Update) -> Update {
+    match (last, next) {
+        (
+            Some(Update::Frame {
+                width,
+                height,
+                rows: old,
+            }),
+            Update::Frame {
+                width: next_width,
+                height: next_height,
+                rows,
+            },
+        ) if width == next_width && height == next_height => Update::Diff {
+            rows: rows
+                .iter()
+                .zip(old)
+                .enumerate()
+                .filter(|(_, (row, old))| row != old)
+                .map(|(y, (row, _))| (y, row.clone()))
+                .collect(),
+        },
+        _ => next.clone(),
+    }
+}
+
+fn line(update: &Update) -> Arc<str> {
+    let mut line = serde_json::to_string(update).unwrap_or_default();
+    line.push('\n');
+    line.into()
+}
+
+/// Handed from the game and the accept thread to the broadcaster.
+#[derive(Default)]
+struct Slot {
+    /// The newest screen not yet broadcast. A newer one replaces it, so
+    /// the game never waits on the broadcaster.
+    shown: Option<Update>,
+    joined: Vec<Viewer>,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    slot: Mutex<Slot>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn update(&self, change: impl FnOnce(&mut Slot)) {
+        change(&mut self.slot.lock().unwrap_or_else(|e| e.into_inner()));
+        self.wake.notify_one();
+    }
+}
+
+/// A connected viewer, written to by its own thread.
+struct Viewer {
+    lines: SyncSender<Arc<str>>,
+    /// Whether everything broadcast so far is queued for the viewer, so a
+    /// diff will make sense to it.
+    synced: bool,
+}
+
+impl Viewer {
+    fn spawn(mut out: Box<dyn Write + Send>) -> io::Result<Self> {
+        let (lines, queued) = mpsc::sync_channel::<Arc<str>>(QUEUE);
+        thread::Builder::new()
+            .name("spectator".to_string())
+            .spawn(move || {
+                for line in queued {
+                    if out.write_all(line.as_bytes()).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        Ok(Self {
+            lines,
+            synced: false,
+        })
+    }
+
+    /// Queues `line` without waiting. Returns false once the viewer has
+    /// disconnected; a full queue only marks it out of sync.
+    fn offer(&mut self, line: &Arc<str>) -> bool {
+        match self.lines.try_send(Arc::clone(line)) {
+            Ok(()) => self.synced = true,
+            Err(TrySendError::Full(_)) => self.synced = false,
+            Err(TrySendError::Disconnected(_)) => return false,
+        }
+        true
+    }
+}
+
+fn broadcast(shared: &Shared) {
+    let mut viewers: Vec<Viewer> = Vec::new();
+    let mut current: Option<Update> = None;
+
+    loop {
+        let (shown, joined) = {
+            let mut slot = shared.slot.lock().unwrap_or_else(|e| e.into_inner());
+            while slot.shown.is_none() && slot.joined.is_empty() && !slot.closed {
+                slot = shared.wake.wait(slot).unwrap_or_else(|e| e.into_inner());
+            }
+            if slot.closed {
+                return;
+            }
+            (slot.shown.take(), mem::take(&mut slot.joined))
+        };
+        viewers.extend(joined);
+
+        let change = shown.map(|shown| {
+            let change = change(current.as_ref(), &shown);
+            current = Some(shown);
+            change
+        });
+        let change = change
+            .filter(|change| !matches!(change, Update::Diff { rows } if rows.is_empty()))
+            .map(|change| line(&change));
+        let full = viewers
+            .iter()
+            .any(|viewer| !viewer.synced)
+            .then(|| current.as_ref().map(line))
+            .flatten();
+
+        viewers.retain_mut(|viewer| {
+            let line = if viewer.synced { &change } else { &full };
+            line.as_ref().is_none_or(|line| viewer.offer(line))
+        });
+    }
+}
+
+/// Where viewers connect: a path for a Unix socket, anything else for TCP.
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+fn is_path(addr: &str) -> bool {
+    addr.contains('/')
+}
+
+impl Listener {
+    fn bind(addr: &str) -> io::Result<Self> {
+        if !is_path(addr) {
+            return TcpListener::bind(addr).map(Self::Tcp);
+        }
+        #[cfg(unix)]
+        {
+            // A socket left behind by an earlier run would block the bind.
+            if fs::symlink_metadata(addr).is_ok_and(|meta| meta.file_type().is_socket()) {
+                fs::remove_file(addr)?;
+            }
+            UnixListener::bind(addr).map(|listener| Self::Unix(listener, addr.into()))
+        }
+        #[cfg(not(unix))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix sockets are not supported here",
+        ))
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn Write + Send>> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Self::Unix(listener, _) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+}
+
+/// Shows frames with another backend and broadcasts them to whoever
+/// connects to `addr`. Neither viewers nor the broadcast itself ever hold
+/// up drawing: a viewer that cannot keep up misses frames and gets a whole
+/// one once it catches up.
+pub struct SpectatorBackend {
+    inner: Box<dyn Backend>,
+    shared: Arc<Shared>,
+    local_addr: Option<SocketAddr>,
+    #[cfg(unix)]
+    path: Option<PathBuf>,
+}
+
+impl SpectatorBackend {
+    pub fn bind(inner: Box<dyn Backend>, addr: &str) -> io::Result<Self> {
+        let listener = Listener::bind(addr)?;
+        let shared = Arc::new(Shared::default());
+        let local_addr = match &listener {
+            Listener::Tcp(listener) => Some(listener.local_addr()?),
+            #[cfg(unix)]
+            Listener::Unix(..) => None,
+        };
+        #[cfg(unix)]
+        let path = match &listener {
+            Listener::Unix(_, path) => Some(path.clone()),
+            Listener::Tcp(_) => None,
+        };
+
+        let broadcaster = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("broadcast".to_string())
+            .spawn(move || broadcast(&broadcaster))?;
+
+        // Blocks in accept until the process exits; connections after the
+        // backend is dropped are closed straight away.
+        let acceptor = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("broadcast-accept".to_string())
+            .spawn(move || {
+                while let Ok(out) = listener.accept() {
+                    if acceptor.slot.lock().is_ok_and(|slot| slot.closed) {
+                        return;
+                    }
+                    if let Ok(viewer) = Viewer::spawn(out) {
+                        acceptor.update(|slot| slot.joined.push(viewer));
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            inner,
+            shared,
+            local_addr,
+            #[cfg(unix)]
+            path,
+        })
+    }
+
+    /// The TCP address viewers connect to, or `None` for a Unix socket.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    fn show(&self, update: Update) {
+        self.shared.update(|slot| slot.shown = Some(update));
+    }
+}
+
+impl Backend for SpectatorBackend {
+    fn draw(&mut self, screen: &Buffer) -> color_eyre::Result<()> {
+        self.inner.draw(screen)?;
+        self.show(Update::Frame {
+            width: screen.width,
+            height: screen.height,
+            rows: rows(screen),
+        });
+        Ok(())
+    }
+
+    fn show_text(&mut self, lines: &[String]) -> color_eyre::Result<()> {
+        self.inner.show_text(lines)?;
+        self.show(Update::Text {
+            lines: lines.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn clear(&mut self) -> color_eyre::Result<()> {
+        self.inner.clear()?;
+        self.show(Update::Clear);
+        Ok(())
+    }
+}
+
+impl Drop for SpectatorBackend {
+    /// Ends the broadcast once queued lines are written, which closes
+    /// every viewer's connection.
+    fn drop(&mut self) {
+        self.shared.update(|slot| slot.closed = true);
+        #[cfg(unix)]
+        if let Some(path) = &self.path {
+            fs::remove_file(path).ok();
+        }
+    }
+}
+
+/// Connects to the broadcast at `addr` and reads its updates on a separate
+/// thread. The receiver disconnects when the broadcast ends.
+pub fn subscribe(addr: &str) -> io::Result<Receiver<Update>> {
+    let stream: Box<dyn Read + Send> = if is_path(addr) {
+        #[cfg(unix)]
+        {
+            Box::new(UnixStream::connect(addr)?)
+        }
+        #[cfg(not(unix))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix sockets are not supported here",
+        ));
+    } else {
+        Box::new(TcpStream::connect(addr)?)
+    };
+
+    let (updates, received) = mpsc::channel();
+    thread::Builder::new()
+        .name("watch-reader".to_string())
+        .spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                if let Ok(update) = serde_json::from_str(&line)
+                    && updates.send(update).is_err()
+                {
+                    return;
+                }
+            }
+        })?;
+    Ok(received)
+}
+
+/// A viewer's copy of the broadcast screen.
+#[derive(Default)]
+pub struct Screen {
+    /// The last frame, text screen or clear, with diffs applied.
+    current: Option<Update>,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, update: Update) {
+        match update {
+            Update::Diff { rows: changed } => {
+                // A diff only follows the frame it was taken against.
+                if let Some(Update::Frame { rows, .. }) = &mut self.current {
+                    for (y, row) in changed {
+                        if let Some(old) = rows.get_mut(y) {
+                            *old = row;
+                        }
+                    }
+                }
+            }
+            update => self.current = Some(update),
+        }
+    }
+
+    /// Shows the screen with `backend`; nothing until the first update.
+    pub fn show(&self, backend: &mut dyn Backend) -> color_eyre::Result<()> {
+        match &self.current {
+            Some(Update::Frame {
+                width,
+                height,
+                rows,
+            }) => backend.draw(&buffer(*width, *height, rows)),
+            Some(Update::Text { lines }) => backend.show_text(lines),
+            Some(Update::Clear) => backend.clear(),
+            Some(Update::Diff { .. }) | None => Ok(()),
+        }
+    }
+}
+
+fn buffer(width: usize, height: usize, rows: &[Row]) -> Buffer {
+    let mut screen = Buffer::new(width, height, Style::default());
+    for (y, row) in rows.iter().enumerate() {
+        let mut x = 0;
+        for span in row {
+            // Continuation cells are skipped by `set`, which puts them back
+            // after their glyph.
+            for ch in span.text.chars() {
+                screen.set(x, y, ch, span.style);
+                x += 1;
+            }
+        }
+    }
+    screen
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::backend::MemoryBackend, std::time::Instant};
+
+    fn frame(text: &[&str]) -> Buffer {
+        let mut screen = Buffer::new(8, text.len(), Style::default());
+        for (y, line) in text.iter().enumerate() {
+            screen.set_str(0, y, line, Style::default());
+        }
+        screen
+    }
+
+    #[test]
+    fn late_viewers_get_a_whole_frame_then_diffs() {
+        let mut backend =
+            SpectatorBackend::bind(Box::new(MemoryBackend::new()), "127.0.0.1:0").expect("bind");
+        let addr = backend.local_addr().expect("address").to_string();
+        backend.draw(&frame(&["dino", "  #"])).expect("draw");
+
+        let updates = subscribe(&addr).expect("subscribe");
+        let first = updates.recv_timeout(WRITE_TIMEOUT).expect("first update");
+        assert!(matches!(
+            &first,
+            Update::Frame {
+                width: 8,
+                height: 2,
+                ..
+            }
+        ));
+
+        backend.draw(&frame(&["dino", " #"])).expect("draw");
+        let second = updates.recv_timeout(WRITE_TIMEOUT).expect("second update");
+        assert!(matches!(&second, Update::Diff { rows } if rows.len() == 1 && rows[0].0 == 1));
+
+        let mut screen = Screen::new();
+        screen.apply(first);
+        screen.apply(second);
+        let shown = MemoryBackend::new();
+        screen.show(&mut shown.clone()).expect("show");
+        assert_eq!(shown.frames(), vec![frame(&["dino", " #"]).lines()]);
+
+        drop(backend);
+        assert!(updates.recv_timeout(WRITE_TIMEOUT).is_err(), "closed");
+    }
+
+    #[test]
+    fn stalled_viewers_do_not_hold_up_drawing() {
+        let mut backend =
+            SpectatorBackend::bind(Box::new(MemoryBackend::new()), "127.0.0.1:0").expect("bind");
+        // Connected but never read from.
+        let _stalled = TcpStream::connect(backend.local_addr().expect("address")).expect("connect");
+
+        let start = Instant::now();
+        for tick in 0..2000 {
+            let line = format!("{:>8}", tick);
+            backend.draw(&frame(&[line.as_str(); 24])).expect("draw");
+        }
+        assert!(start.elapsed() < WRITE_TIMEOUT);
+    }
+}
@@ -0,0 +1,503 @@
+//! Sprite packs.
+//!
+//! A pack is a directory of `<name>.sprite` files, one per entry in
+//! [`SpriteSet`]. Each file starts with optional `key = value` header lines,
+//! followed by one or more frames introduced by a `---` line:
+//!
+//! ```text
+//! # One step per 3 columns travelled; `time 0.1` steps every 0.1 s.
+//! timing = distance 3
+//! # Cell of the frame placed on the entity's position.
+//! anchor = 0 0
+//! # A glyph, `space`, or `none` to draw every cell.
+//! transparent = space
+//! # Per-glyph color override.
+//! color o = #ff4040
+//!
+//! --- hold=2
+//!  ^   ^
+//! <(o.o)>
+//! ---
+//!        
+//! <(o.o)>
+//! ```
+//!
+//! Header lines starting with `#` are comments. `--- hold=<n>` keeps a frame
+//! on screen for `n` steps instead of one.
+//!
+//! Packs are looked up in `<dir>/<pack>/` on disk first, then among the
+//! embedded packs (`classic` and `ascii`), and any sprite a pack leaves out
+//! comes from the embedded `classic` pack. Frame widths are measured in
+//! terminal columns, so double-width glyphs count twice.
+
+use {
+    crate::{
+        config::SpritesConfig,
+        physics::Hitbox,
+        theme::Color,
+        types::{DinoState, ObstacleType},
+    },
+    color_eyre::eyre::{Context, Result, bail, eyre},
+    rust_embed::Embed,
+    std::{collections::HashMap, fs, path::Path},
+    unicode_width::UnicodeWidthStr,
+};
+
+#[derive(Embed)]
+#[folder = "sprites"]
+struct EmbeddedSprites;
+
+const DEFAULT_PACK: &str = "classic";
+const ASCII_PACK: &str = "ascii";
+
+/// Distance travelled and simulated time, both advanced only by physics
+/// ticks so animation speed does not depend on the render rate.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct AnimClock {
+    pub distance: f32,
+    pub elapsed: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Timing {
+    /// Advance one step every `n` columns travelled.
+    Distance(f32),
+    /// Advance one step every `n` seconds of simulated time.
+    Time(f32),
+}
+
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub lines: Vec<String>,
+    /// How many steps this frame stays on screen.
+    pub hold: u32,
+}
+
+impl Frame {
+    pub fn width(&self) -> usize {
+        self.lines.iter().map(|l| l.width()).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.lines.len()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    pub timing: Timing,
+    pub anchor: (usize, usize),
+    pub transparent: Option<char>,
+    pub colors: HashMap<char, Color>,
+}
+
+impl Animation {
+    pub fn frame(&self, clock: AnimClock) -> &Frame {
+        let (position, step) = match self.timing {
+            Timing::Distance(step) => (clock.distance, step),
+            Timing::Time(step) => (clock.elapsed, step),
+        };
+
+        let total: u64 = self.frames.iter().map(|f| f.hold.max(1) as u64).sum();
+        let mut tick = (position.max(0.0) / step.max(f32::EPSILON)) as u64 % total.max(1);
+
+        for frame in &self.frames {
+            let hold = frame.hold.max(1) as u64;
+            if tick < hold {
+                return frame;
+            }
+            tick -= hold;
+        }
+
+        &self.frames[0]
+    }
+
+    pub fn is_transparent(&self, ch: char) -> bool {
+        self.transparent == Some(ch)
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut animation = Self {
+            frames: Vec::new(),
+            timing: Timing::Time(1.0),
+            anchor: (0, 0),
+            transparent: Some(' '),
+            colors: HashMap::new(),
+        };
+
+        let mut lines = source.lines().enumerate().peekable();
+        while let Some((n, line)) = lines.next_if(|(_, l)| !l.starts_with("---")) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            animation
+                .parse_header(line)
+                .with_context(|| format!("line {}: '{}'", n + 1, line))?;
+        }
+
+        while let Some((n, marker)) = lines.next() {
+            let hold = parse_hold(marker).with_context(|| format!("line {}", n + 1))?;
+            let mut frame_lines = Vec::new();
+            while let Some((_, line)) = lines.next_if(|(_, l)| !l.starts_with("---")) {
+                frame_lines.push(line.to_string());
+            }
+
+            while frame_lines.last().is_some_and(|l| l.is_empty()) {
+                frame_lines.pop();
+            }
+
+            animation.frames.push(Frame {
+                lines: frame_lines,
+                hold,
+            });
+        }
+
+        if animation.frames.is_empty() {
+            bail!("no frames; each frame starts with a '---' line");
+        }
+
+        Ok(animation)
+    }
+
+    fn parse_header(&mut self, line: &str) -> Result<()> {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| eyre!("expected 'key = value'"))?;
+        let (key, value) = (key.trim(), value.trim());
+        let mut words = key.split_whitespace();
+
+        match (words.next(), words.next()) {
+            (Some("timing"), None) => {
+                let (kind, step) = value
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| eyre!("expected 'distance <n>' or 'time <n>'"))?;
+                let step: f32 = step.trim().parse().context("invalid timing step")?;
+                self.timing = match kind {
+                    "distance" => Timing::Distance(step),
+                    "time" => Timing::Time(step),
+                    _ => bail!("unknown timing '{}'", kind),
+                };
+            }
+            (Some("anchor"), None) => {
+                let coords: Vec<usize> = value
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .context("anchor must be two non-negative integers")?;
+                let [x, y] = coords[..] else {
+                    bail!("anchor must be two non-negative integers");
+                };
+                self.anchor = (x, y);
+            }
+            (Some("transparent"), None) => {
+                self.transparent = match value {
+                    "none" => None,
+                    "space" => Some(' '),
+                    _ => Some(single_glyph(value)?),
+                };
+            }
+            (Some("color"), Some(glyph)) => {
+                let color = Color::parse_hex(value)
+                    .ok_or_else(|| eyre!("invalid color '{}', expected #rrggbb", value))?;
+                self.colors.insert(single_glyph(glyph)?, color);
+            }
+            _ => bail!("unknown header key '{}'", key),
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_hold(marker: &str) -> Result<u32> {
+    match marker.trim_start_matches('-').trim() {
+        "" => Ok(1),
+        rest => rest
+            .strip_prefix("hold=")
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| eyre!("expected '---' or '--- hold=<n>', got '{}'", marker)),
+    }
+}
+
+fn single_glyph(value: &str) -> Result<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Ok(ch),
+        _ => bail!("expected a single glyph, got '{}'", value),
+    }
+}
+
+pub struct SpriteSet {
+    pub dino_run: Animation,
+    pub dino_crouch: Animation,
+    pub dino_jump: Animation,
+    pub dino_dead: Animation,
+    /// Drawn over the upright dino; frames are mostly empty so it only
+    /// closes the eye now and then.
+    pub dino_blink: Animation,
+    pub dino_crouch_blink: Animation,
+    pub small_cactus: Animation,
+    pub medium_cactus: Animation,
+    pub tall_cactus: Animation,
+    pub wide_cactus: Animation,
+    pub pterodactyl: Animation,
+    pub cloud: Animation,
+    pub moon: Animation,
+}
+
+impl SpriteSet {
+    /// Loads the configured pack. In ASCII mode the default braille pack is
+    /// replaced by the ASCII one; other packs are taken as the user wrote them.
+    pub fn load(cfg: &SpritesConfig, ascii: bool) -> Result<Self> {
+        let pack = if ascii && cfg.pack == DEFAULT_PACK {
+            ASCII_PACK
+        } else {
+            &cfg.pack
+        };
+        Self::load_pack(Path::new(&cfg.dir), pack)
+    }
+
+    fn load_pack(dir: &Path, pack: &str) -> Result<Self> {
+        let pack_dir = dir.join(pack);
+        let has_disk_pack = pack_dir.is_dir();
+        if !has_disk_pack && !Self::is_embedded_pack(pack) {
+            bail!(
+                "Sprite pack '{}' not found in {} or among the built-in packs",
+                pack,
+                dir.display()
+            );
+        }
+
+        let load = |name: &str| -> Result<Animation> {
+            let path = pack_dir.join(format!("{}.sprite", name));
+            let (source, origin) = if has_disk_pack && path.is_file() {
+                let source = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read sprite {}", path.display()))?;
+                (source, path.display().to_string())
+            } else {
+                Self::embedded_source(pack, name)
+                    .or_else(|| Self::embedded_source(DEFAULT_PACK, name))
+                    .ok_or_else(|| eyre!("Built-in sprite '{}' is missing", name))?
+            };
+
+            Animation::parse(&source).with_context(|| format!("Invalid sprite {}", origin))
+        };
+
+        let set = Self {
+            dino_run: load("dino_run")?,
+            dino_crouch: load("dino_crouch")?,
+            dino_jump: load("dino_jump")?,
+            dino_dead: load("dino_dead")?,
+            dino_blink: load("dino_blink")?,
+            dino_crouch_blink: load("dino_crouch_blink")?,
+            small_cactus: load("small_cactus")?,
+            medium_cactus: load("medium_cactus")?,
+            tall_cactus: load("tall_cactus")?,
+            wide_cactus: load("wide_cactus")?,
+            pterodactyl: load("pterodactyl")?,
+            cloud: load("cloud")?,
+            moon: load("moon")?,
+        };
+
+        set.validate()
+            .with_context(|| format!("Sprite pack '{}' does not match the physics", pack))?;
+        Ok(set)
+    }
+
+    fn is_embedded_pack(pack: &str) -> bool {
+        let prefix = format!("{}/", pack);
+        EmbeddedSprites::iter().any(|file| file.starts_with(&prefix))
+    }
+
+    fn embedded_source(pack: &str, name: &str) -> Option<(String, String)> {
+        let path = format!("{}/{}.sprite", pack, name);
+        let file = EmbeddedSprites::get(&path)?;
+        let source = String::from_utf8_lossy(&file.data).into_owned();
+        Some((source, format!("built-in {}", path)))
+    }
+
+    /// Checks that every frame of a sprite with a hitbox covers that hitbox
+    /// and sits on the same bottom edge, so what collides is what is drawn.
+    fn validate(&self) -> Result<()> {
+        let checks = [
+            ("dino_run", &self.dino_run, Hitbox::dino(DinoState::Running)),
+            (
+                "dino_jump",
+                &self.dino_jump,
+                Hitbox::dino(DinoState::Jumping),
+            ),
+            (
+                "dino_crouch",
+                &self.dino_crouch,
+                Hitbox::dino(DinoState::Crouching),
+            ),
+            (
+                "small_cactus",
+                &self.small_cactus,
+                Hitbox::obstacle(ObstacleType::SmallCactus),
+            ),
+            (
+                "medium_cactus",
+                &self.medium_cactus,
+                Hitbox::obstacle(ObstacleType::MediumCactus),
+            ),
+            (
+                "tall_cactus",
+                &self.tall_cactus,
+                Hitbox::obstacle(ObstacleType::TallCactus),
+            ),
+            (
+                "wide_cactus",
+                &self.wide_cactus,
+                Hitbox::obstacle(ObstacleType::WideCactus),
+            ),
+            (
+                "pterodactyl",
+                &self.pterodactyl,
+                Hitbox::obstacle(ObstacleType::PterodactylMid),
+            ),
+        ];
+
+        for (name, animation, hitbox) in checks {
+            let (ax, ay) = animation.anchor;
+            let needed_height = hitbox.y_offset + hitbox.height;
+
+            for (i, frame) in animation.frames.iter().enumerate() {
+                let (width, height) = (frame.width(), frame.height());
+                if height.checked_sub(ay) != Some(needed_height) {
+                    bail!(
+                        "sprite '{}' frame {} is {} rows tall with anchor row {}, but its {}x{} \
+                         hitbox needs {} rows from the anchor down",
+                        name,
+                        i + 1,
+                        height,
+                        ay,
+                        hitbox.width,
+                        hitbox.height,
+                        needed_height
+                    );
+                }
+                if width.saturating_sub(ax) < hitbox.width {
+                    bail!(
+                        "sprite '{}' frame {} is {} columns wide with anchor column {}, but its \
+                         hitbox needs {} columns starting at the anchor",
+                        name,
+                        i + 1,
+                        width,
+                        ax,
+                        hitbox.width
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn dino(&self, state: DinoState) -> &Animation {
+        match state {
+            DinoState::Running => &self.dino_run,
+            DinoState::Crouching => &self.dino_crouch,
+            DinoState::Jumping => &self.dino_jump,
+            DinoState::Dead => &self.dino_dead,
+        }
+    }
+
+    pub fn dino_overlay(&self, state: DinoState) -> Option<&Animation> {
+        match state {
+            DinoState::Running | DinoState::Jumping => Some(&self.dino_blink),
+            DinoState::Crouching => Some(&self.dino_crouch_blink),
+            DinoState::Dead => None,
+        }
+    }
+
+    pub fn obstacle(&self, obstacle_type: ObstacleType) -> &Animation {
+        match obstacle_type {
+            ObstacleType::SmallCactus => &self.small_cactus,
+            ObstacleType::MediumCactus => &self.medium_cactus,
+            ObstacleType::TallCactus => &self.tall_cactus,
+            ObstacleType::WideCactus => &self.wide_cactus,
+            ObstacleType::PterodactylLow
+            | ObstacleType::PterodactylMid
+            | ObstacleType::PterodactylHigh => &self.pterodactyl,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_headers_and_held_frames() {
+        let source = "# blinking eye\n\
+                      timing = distance 3\n\
+                      anchor = 1 0\n\
+                      transparent = .\n\
+                      color o = #ff4040\n\
+                      \n\
+                      --- hold=2\n\
+                      .o.\n\
+                      ---\n\
+                      .-.\n\
+                      \n";
+        let animation = Animation::parse(source).unwrap();
+
+        assert!(matches!(animation.timing, Timing::Distance(step) if step == 3.0));
+        assert_eq!(animation.anchor, (1, 0));
+        assert!(animation.is_transparent('.'));
+        assert_eq!(animation.colors[&'o'], Color::parse_hex("#ff4040").unwrap());
+        assert_eq!(animation.frames.len(), 2);
+        assert_eq!(animation.frames[0].hold, 2);
+        // Trailing blank lines are not part of the frame.
+        assert_eq!(animation.frames[1].lines, [".-."]);
+
+        // The held frame covers the first two steps of every three.
+        let at = |distance| AnimClock {
+            distance,
+            elapsed: 0.0,
+        };
+        assert_eq!(animation.frame(at(3.0)).lines, [".o."]);
+        assert_eq!(animation.frame(at(6.0)).lines, [".-."]);
+        assert_eq!(animation.frame(at(9.0)).lines, [".o."]);
+    }
+
+    #[test]
+    fn malformed_sprites_say_what_is_wrong() {
+        let error = |source: &str| format!("{:#}", Animation::parse(source).unwrap_err());
+
+        assert!(error("timing = distance\n---\nx").contains("line 1"));
+        assert!(error("anchor = 1\n---\nx").contains("two non-negative integers"));
+        assert!(error("color oo = #ffffff\n---\nx").contains("single glyph"));
+        assert!(error("# fine\nsize = 3\n---\nx").contains("line 2: 'size = 3'"));
+        assert!(error("---\nx\n--- hold=two\ny").contains("line 3"));
+        assert!(error("timing = time 0.1\n").contains("no frames"));
+    }
+
+    #[test]
+    fn packs_must_cover_their_hitboxes() {
+        let dir = std::env::temp_dir().join(format!("dino-{}-sprites", std::process::id()));
+        let pack = dir.join("broken");
+        fs::create_dir_all(&pack).unwrap();
+
+        // Built-in sprites fill in everything the pack leaves out.
+        fs::write(pack.join("small_cactus.sprite"), "---\n|||\n|||\n").unwrap();
+        assert!(SpriteSet::load_pack(&dir, "broken").is_ok());
+
+        // A small cactus collides as 3x2, so a 2-column sprite would let
+        // the dino clip an invisible column.
+        fs::write(pack.join("small_cactus.sprite"), "---\n||\n||\n").unwrap();
+        let error = format!("{:#}", SpriteSet::load_pack(&dir, "broken").err().unwrap());
+        assert!(error.contains("does not match the physics"), "{}", error);
+        assert!(error.contains("'small_cactus' frame 1 is 2 columns wide"));
+
+        // One row short of the hitbox.
+        fs::write(pack.join("small_cactus.sprite"), "---\n|||\n").unwrap();
+        let error = format!("{:#}", SpriteSet::load_pack(&dir, "broken").err().unwrap());
+        assert!(error.contains("is 1 rows tall"), "{}", error);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,240 @@
+//! Just enough telnet (RFC 854) to play over a raw TCP connection: the
+//! option handshake that puts a client in character mode, window size
+//! reports (NAWS, RFC 1073) and the keys the game uses.
+
+/// "Interpret as command": starts every telnet command.
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+/// Subnegotiation begin and end.
+const SB: u8 = 250;
+const SE: u8 = 240;
+/// "Interrupt process", which some clients send for Ctrl-C.
+const IP: u8 = 244;
+
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31;
+
+const ESC: u8 = 0x1b;
+
+/// Longest subnegotiation kept. Longer ones are cut short, which leaves
+/// them unreadable, so they are dropped.
+const MAX_SUBNEGOTIATION: usize = 64;
+
+/// Sent when a client connects. The server takes over echoing and drops
+/// go-aheads, which makes clients send each key as it is pressed, and
+/// asks for the window size.
+pub const HANDSHAKE: [u8; 9] = [IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    /// Escape, or Ctrl-C and Ctrl-D, which a character-mode client passes
+    /// on instead of acting on them.
+    Esc,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TelnetEvent {
+    Key(Key),
+    /// The client's window size, in cells.
+    Resize {
+        width: usize,
+        height: usize,
+    },
+}
+
+#[derive(Clone, Copy, Default)]
+enum State {
+    #[default]
+    Data,
+    Command,
+    /// Waiting for the option after WILL, WONT, DO or DONT.
+    Option,
+    Subnegotiation,
+    SubnegotiationCommand,
+    Escape,
+    /// Inside `ESC [` or `ESC O`, waiting for the final byte.
+    Sequence,
+}
+
+/// Turns the bytes a telnet client sends into key presses and window
+/// sizes. Option replies are read and ignored: the game works the same
+/// whether or not the client agrees.
+#[derive(Default)]
+pub struct Decoder {
+    state: State,
+    subnegotiation: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes one read's worth of bytes. Commands and escape sequences
+    /// may be split across reads, except that an escape byte ending a read
+    /// counts as the Escape key; terminals send arrow keys whole.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<TelnetEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            self.byte(byte, &mut events);
+        }
+        if let State::Escape = self.state {
+            self.state = State::Data;
+            events.push(TelnetEvent::Key(Key::Esc));
+        }
+        events
+    }
+
+    fn byte(&mut self, byte: u8, events: &mut Vec<TelnetEvent>) {
+        self.state = match self.state {
+            State::Data => return self.data(byte, events),
+            State::Command => match byte {
+                SB => {
+                    self.subnegotiation.clear();
+                    State::Subnegotiation
+                }
+                WILL | WONT | DO | DONT => State::Option,
+                IP => self.key(Key::Esc, events),
+                _ => State::Data,
+            },
+            State::Option => State::Data,
+            State::Subnegotiation => match byte {
+                IAC => State::SubnegotiationCommand,
+                _ => {
+                    self.push(byte);
+                    State::Subnegotiation
+                }
+            },
+            State::SubnegotiationCommand => match byte {
+                SE => {
+                    events.extend(self.window_size());
+                    State::Data
+                }
+                IAC => {
+                    self.push(IAC);
+                    State::Subnegotiation
+                }
+                _ => State::Subnegotiation,
+            },
+            State::Escape => match byte {
+                b'[' | b'O' => State::Sequence,
+                _ => {
+                    events.push(TelnetEvent::Key(Key::Esc));
+                    self.state = State::Data;
+                    return self.data(byte, events);
+                }
+            },
+            State::Sequence => match byte {
+                b'A' => self.key(Key::Up, events),
+                b'B' => self.key(Key::Down, events),
+                b'C' => self.key(Key::Right, events),
+                b'D' => self.key(Key::Left, events),
+                // Parameters and intermediates come before the final byte.
+                0x20..=0x3f => State::Sequence,
+                _ => State::Data,
+            },
+        };
+    }
+
+    fn data(&mut self, byte: u8, events: &mut Vec<TelnetEvent>) {
+        self.state = match byte {
+            IAC => State::Command,
+            ESC => State::Escape,
+            // Clients send Enter as CR LF or CR NUL.
+            b'\r' => self.key(Key::Enter, events),
+            0x03 | 0x04 => self.key(Key::Esc, events),
+            0x20..=0x7e => self.key(Key::Char(byte as char), events),
+            _ => State::Data,
+        };
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.subnegotiation.len() < MAX_SUBNEGOTIATION {
+            self.subnegotiation.push(byte);
+        }
+    }
+
+    fn key(&self, key: Key, events: &mut Vec<TelnetEvent>) -> State {
+        events.push(TelnetEvent::Key(key));
+        State::Data
+    }
+
+    /// A NAWS report is the option followed by the width and height as
+    /// 16-bit big-endian numbers. Zero means unknown.
+    fn window_size(&self) -> Option<TelnetEvent> {
+        let [NAWS, w1, w0, h1, h0] = self.subnegotiation[..] else {
+            return None;
+        };
+        let width = u16::from_be_bytes([w1, w0]) as usize;
+        let height = u16::from_be_bytes([h1, h0]) as usize;
+        (width > 0 && height > 0).then_some(TelnetEvent::Resize { width, height })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_keys_around_telnet_commands() {
+        let mut decoder = Decoder::new();
+        let mut bytes = vec![IAC, WILL, NAWS, IAC, SB, NAWS, 0, 100, 0, 30, IAC, SE];
+        bytes.extend(b" \x1b[A\x1bOBq\r\0");
+
+        assert_eq!(
+            decoder.feed(&bytes),
+            vec![
+                TelnetEvent::Resize {
+                    width: 100,
+                    height: 30
+                },
+                TelnetEvent::Key(Key::Char(' ')),
+                TelnetEvent::Key(Key::Up),
+                TelnetEvent::Key(Key::Down),
+                TelnetEvent::Key(Key::Char('q')),
+                TelnetEvent::Key(Key::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn commands_may_span_reads() {
+        let mut decoder = Decoder::new();
+        assert!(decoder.feed(&[IAC, SB, NAWS, 1]).is_empty());
+        // A doubled IAC inside a subnegotiation is one 255 byte.
+        assert_eq!(
+            decoder.feed(&[IAC, IAC, 0, 24, IAC, SE, ESC]),
+            vec![
+                TelnetEvent::Resize {
+                    width: 511,
+                    height: 24
+                },
+                TelnetEvent::Key(Key::Esc),
+            ]
+        );
+    }
+
+    #[test]
+    fn endless_subnegotiations_are_dropped() {
+        let mut decoder = Decoder::new();
+        let mut bytes = vec![IAC, SB, NAWS, 0, 80, 0, 24];
+        bytes.extend([7; 100_000]);
+        assert!(decoder.feed(&bytes).is_empty());
+        assert_eq!(decoder.subnegotiation.len(), MAX_SUBNEGOTIATION);
+
+        assert_eq!(
+            decoder.feed(&[IAC, SE, b'q']),
+            vec![TelnetEvent::Key(Key::Char('q'))]
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use {
+    crate::{config::ThemeConfig, types::ObstacleType},
+    serde::{Deserialize, Serialize},
+    std::env,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parses `#rrggbb` or `rrggbb`.
+    pub fn parse_hex(s: &str) -> Option<Self> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 {
+            return None;
+        }
+
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Self::rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Self::rgb(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Attrs {
+    pub bold: bool,
+    pub dim: bool,
+    pub reverse: bool,
+}
+
+impl Attrs {
+    pub const NONE: Self = Self {
+        bold: false,
+        dim: false,
+        reverse: false,
+    };
+    pub const BOLD: Self = Self {
+        bold: true,
+        dim: false,
+        reverse: false,
+    };
+    pub const DIM: Self = Self {
+        bold: false,
+        dim: true,
+        reverse: false,
+    };
+}
+
+/// Foreground, background and attributes of a cell. `None` colors mean the
+/// terminal's own default.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub attrs: Attrs,
+}
+
+impl Style {
+    pub const fn fg(color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            bg: None,
+            attrs: Attrs::NONE,
+        }
+    }
+
+    pub const fn with_attrs(self, attrs: Attrs) -> Self {
+        Self { attrs, ..self }
+    }
+
+    pub const fn with_bg(self, color: Color) -> Self {
+        Self {
+            bg: Some(color),
+            ..self
+        }
+    }
+
+    /// Blends towards `other` by `t` in `0.0..=1.0`. Terminal-default colors
+    /// are treated as light text on a black background while fading.
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        if t <= 0.0 {
+            return self;
+        }
+        if t >= 1.0 {
+            return other;
+        }
+
+        let blend = |a: Option<Color>, b: Option<Color>, default: Color| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(default).lerp(b.unwrap_or(default), t)),
+        };
+
+        Self {
+            fg: blend(self.fg, other.fg, DEFAULT_FG),
+            bg: blend(self.bg, other.bg, DEFAULT_BG),
+            attrs: if t < 0.5 { self.attrs } else { other.attrs },
+        }
+    }
+
+    pub const fn reversed(self) -> Self {
+        Self {
+            attrs: Attrs {
+                reverse: !self.attrs.reverse,
+                ..self.attrs
+            },
+            ..self
+        }
+    }
+}
+
+const DEFAULT_FG: Color = Color::rgb(200, 200, 200);
+const DEFAULT_BG: Color = Color::rgb(0, 0, 0);
+const NIGHT_SKY: Color = Color::rgb(10, 14, 36);
+const MOONLIGHT: Color = Color::rgb(150, 170, 230);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    None,
+}
+
+impl ColorSupport {
+    /// Picks the color depth from `color_mode`, falling back to `NO_COLOR`,
+    /// `COLORTERM` and `TERM` when it is `auto`.
+    pub fn detect(color_mode: &str) -> Self {
+        match color_mode.to_ascii_lowercase().as_str() {
+            "truecolor" | "24bit" => return Self::TrueColor,
+            "256" => return Self::Ansi256,
+            "16" => return Self::Ansi16,
+            "none" | "mono" => return Self::None,
+            _ => {}
+        }
+
+        if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return Self::None;
+        }
+
+        let colorterm = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return Self::TrueColor;
+        }
+
+        let term = env::var("TERM").unwrap_or_default().to_lowercase();
+        if term == "dumb" {
+            Self::None
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// The standard 16-color palette as most terminals render it, indexed like
+/// SGR 30-37 / 90-97.
+const ANSI16_PALETTE: [Color; 16] = [
+    Color::rgb(0, 0, 0),
+    Color::rgb(128, 0, 0),
+    Color::rgb(0, 128, 0),
+    Color::rgb(128, 128, 0),
+    Color::rgb(0, 0, 128),
+    Color::rgb(128, 0, 128),
+    Color::rgb(0, 128, 128),
+    Color::rgb(192, 192, 192),
+    Color::rgb(128, 128, 128),
+    Color::rgb(255, 0, 0),
+    Color::rgb(0, 255, 0),
+    Color::rgb(255, 255, 0),
+    Color::rgb(0, 0, 255),
+    Color::rgb(255, 0, 255),
+    Color::rgb(0, 255, 255),
+    Color::rgb(255, 255, 255),
+];
+
+/// Maps an RGB color onto the xterm 256-color cube or grayscale ramp.
+pub fn to_ansi256(c: Color) -> u8 {
+    if c.r == c.g && c.g == c.b {
+        return match c.r {
+            0..=7 => 16,
+            249..=255 => 231,
+            v => 232 + ((v - 8) / 10).min(23),
+        };
+    }
+
+    let level = |v: u8| ((v as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(c.r) + 6 * level(c.g) + level(c.b)
+}
+
+/// Returns the index of the nearest of the 16 basic ANSI colors.
+pub fn to_ansi16(c: Color) -> u8 {
+    let distance = |p: &Color| {
+        let dr = c.r as i32 - p.r as i32;
+        let dg = c.g as i32 - p.g as i32;
+        let db = c.b as i32 - p.b as i32;
+        dr * dr + dg * dg + db * db
+    };
+
+    ANSI16_PALETTE
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| distance(p))
+        .map(|(i, _)| i as u8)
+        .unwrap_or(7)
+}
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub background: Style,
+    pub dino: Style,
+    pub small_cactus: Style,
+    pub medium_cactus: Style,
+    pub tall_cactus: Style,
+    pub wide_cactus: Style,
+    pub ptero_low: Style,
+    pub ptero_mid: Style,
+    pub ptero_high: Style,
+    pub cloud: Style,
+    pub star: Style,
+    pub moon: Style,
+    pub ground: Style,
+    pub hud: Style,
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 4] = ["classic", "desert", "high-contrast", "mono"];
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "desert" => Some(Self::desert()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "mono" => Some(Self::mono()),
+            _ => None,
+        }
+    }
+
+    /// Builds the theme named in the config and applies any per-element color
+    /// overrides on top of it. Names and colors it cannot use are skipped
+    /// and described in the returned warnings, for the caller to show
+    /// before the terminal is taken over.
+    pub fn from_config(cfg: &ThemeConfig) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut theme = Self::by_name(&cfg.name).unwrap_or_else(|| {
+            warnings.push(format!(
+                "Unknown theme '{}', expected one of {}; using classic",
+                cfg.name,
+                Self::NAMES.join(", ")
+            ));
+            Self::classic()
+        });
+
+        for (element, value) in &cfg.colors {
+            let Some(color) = Color::parse_hex(value) else {
+                warnings.push(format!(
+                    "Invalid color '{}' for theme element '{}'",
+                    value, element
+                ));
+                continue;
+            };
+
+            match theme.element_mut(element) {
+                Some(style) if element == "background" => style.bg = Some(color),
+                Some(style) => style.fg = Some(color),
+                None => warnings.push(format!("Unknown theme element '{}'", element)),
+            }
+        }
+
+        (theme, warnings)
+    }
+
+    pub fn obstacle(&self, obstacle_type: ObstacleType) -> Style {
+        match obstacle_type {
+            ObstacleType::SmallCactus => self.small_cactus,
+            ObstacleType::MediumCactus => self.medium_cactus,
+            ObstacleType::TallCactus => self.tall_cactus,
+            ObstacleType::WideCactus => self.wide_cactus,
+            ObstacleType::PterodactylLow => self.ptero_low,
+            ObstacleType::PterodactylMid => self.ptero_mid,
+            ObstacleType::PterodactylHigh => self.ptero_high,
+        }
+    }
+
+    fn element_mut(&mut self, element: &str) -> Option<&mut Style> {
+        match element {
+            "background" => Some(&mut self.background),
+            "dino" => Some(&mut self.dino),
+            "small_cactus" => Some(&mut self.small_cactus),
+            "medium_cactus" => Some(&mut self.medium_cactus),
+            "tall_cactus" => Some(&mut self.tall_cactus),
+            "wide_cactus" => Some(&mut self.wide_cactus),
+            "ptero_low" => Some(&mut self.ptero_low),
+            "ptero_mid" => Some(&mut self.ptero_mid),
+            "ptero_high" => Some(&mut self.ptero_high),
+            "cloud" => Some(&mut self.cloud),
+            "star" => Some(&mut self.star),
+            "moon" => Some(&mut self.moon),
+            "ground" => Some(&mut self.ground),
+            "hud" => Some(&mut self.hud),
+            _ => None,
+        }
+    }
+
+    /// Derives the night palette: a dark sky with every element tinted
+    /// towards moonlight. Themes that already paint a black sky keep it.
+    pub fn night(&self) -> Self {
+        let tint = |style: Style| Style {
+            fg: Some(style.fg.unwrap_or(DEFAULT_FG).lerp(MOONLIGHT, 0.3)),
+            ..style
+        };
+        let sky = match self.background.bg {
+            Some(bg) if bg == DEFAULT_BG => bg,
+            _ => NIGHT_SKY,
+        };
+
+        Self {
+            name: self.name.clone(),
+            background: self.background.with_bg(sky),
+            dino: tint(self.dino),
+            small_cactus: tint(self.small_cactus),
+            medium_cactus: tint(self.medium_cactus),
+            tall_cactus: tint(self.tall_cactus),
+            wide_cactus: tint(self.wide_cactus),
+            ptero_low: tint(self.ptero_low),
+            ptero_mid: tint(self.ptero_mid),
+            ptero_high: tint(self.ptero_high),
+            cloud: tint(self.cloud),
+            star: self.star,
+            moon: self.moon,
+            ground: tint(self.ground),
+            hud: tint(self.hud),
+        }
+    }
+
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            name: self.name.clone(),
+            background: self.background.lerp(other.background, t),
+            dino: self.dino.lerp(other.dino, t),
+            small_cactus: self.small_cactus.lerp(other.small_cactus, t),
+            medium_cactus: self.medium_cactus.lerp(other.medium_cactus, t),
+            tall_cactus: self.tall_cactus.lerp(other.tall_cactus, t),
+            wide_cactus: self.wide_cactus.lerp(other.wide_cactus, t),
+            ptero_low: self.ptero_low.lerp(other.ptero_low, t),
+            ptero_mid: self.ptero_mid.lerp(other.ptero_mid, t),
+            ptero_high: self.ptero_high.lerp(other.ptero_high, t),
+            cloud: self.cloud.lerp(other.cloud, t),
+            star: self.star.lerp(other.star, t),
+            moon: self.moon.lerp(other.moon, t),
+            ground: self.ground.lerp(other.ground, t),
+            hud: self.hud.lerp(other.hud, t),
+        }
+    }
+
+    /// True when no element carries a color, i.e. the look relies on
+    /// attributes alone.
+    pub fn is_colorless(&self) -> bool {
+        [
+            self.background,
+            self.dino,
+            self.small_cactus,
+            self.medium_cactus,
+            self.tall_cactus,
+            self.wide_cactus,
+            self.ptero_low,
+            self.ptero_mid,
+            self.ptero_high,
+            self.cloud,
+            self.ground,
+            self.hud,
+        ]
+        .iter()
+        .all(|style| style.fg.is_none() && style.bg.is_none())
+    }
+
+    /// Inverts every element, used for night on terminals without color.
+    pub fn reversed(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            background: self.background.reversed(),
+            dino: self.dino.reversed(),
+            small_cactus: self.small_cactus.reversed(),
+            medium_cactus: self.medium_cactus.reversed(),
+            tall_cactus: self.tall_cactus.reversed(),
+            wide_cactus: self.wide_cactus.reversed(),
+            ptero_low: self.ptero_low.reversed(),
+            ptero_mid: self.ptero_mid.reversed(),
+            ptero_high: self.ptero_high.reversed(),
+            cloud: self.cloud.reversed(),
+            star: self.star.reversed(),
+            moon: self.moon.reversed(),
+            ground: self.ground.reversed(),
+            hud: self.hud.reversed(),
+        }
+    }
+
+    pub fn classic() -> Self {
+        Self {
+            name: "classic".to_string(),
+            background: Style::default(),
+            dino: Style::fg(Color::rgb(200, 200, 200)),
+            small_cactus: Style::fg(Color::rgb(90, 170, 70)),
+            medium_cactus: Style::fg(Color::rgb(70, 150, 60)),
+            tall_cactus: Style::fg(Color::rgb(50, 130, 50)),
+            wide_cactus: Style::fg(Color::rgb(110, 160, 60)),
+            ptero_low: Style::fg(Color::rgb(180, 130, 200)),
+            ptero_mid: Style::fg(Color::rgb(160, 110, 190)),
+            ptero_high: Style::fg(Color::rgb(140, 90, 180)),
+            cloud: Style::fg(Color::rgb(130, 130, 130)),
+            star: Style::fg(Color::rgb(230, 230, 200)),
+            moon: Style::fg(Color::rgb(240, 240, 210)).with_attrs(Attrs::BOLD),
+            ground: Style::fg(Color::rgb(170, 170, 170)),
+            hud: Style::fg(Color::rgb(240, 240, 240)).with_attrs(Attrs::BOLD),
+        }
+    }
+
+    pub fn desert() -> Self {
+        let sky = Color::rgb(58, 40, 28);
+        Self {
+            name: "desert".to_string(),
+            background: Style::default().with_bg(sky),
+            dino: Style::fg(Color::rgb(245, 205, 130)),
+            small_cactus: Style::fg(Color::rgb(120, 170, 70)),
+            medium_cactus: Style::fg(Color::rgb(100, 155, 60)),
+            tall_cactus: Style::fg(Color::rgb(80, 140, 55)),
+            wide_cactus: Style::fg(Color::rgb(140, 175, 80)),
+            ptero_low: Style::fg(Color::rgb(220, 110, 70)),
+            ptero_mid: Style::fg(Color::rgb(200, 90, 60)),
+            ptero_high: Style::fg(Color::rgb(180, 70, 50)),
+            cloud: Style::fg(Color::rgb(250, 235, 210)),
+            star: Style::fg(Color::rgb(255, 240, 200)),
+            moon: Style::fg(Color::rgb(255, 235, 180)).with_attrs(Attrs::BOLD),
+            ground: Style::fg(Color::rgb(210, 160, 90)),
+            hud: Style::fg(Color::rgb(255, 215, 130)).with_attrs(Attrs::BOLD),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        let black = Color::rgb(0, 0, 0);
+        let white = Color::rgb(255, 255, 255);
+        Self {
+            name: "high-contrast".to_string(),
+            background: Style::default().with_bg(black),
+            dino: Style::fg(white).with_attrs(Attrs::BOLD),
+            small_cactus: Style::fg(Color::rgb(0, 255, 0)).with_attrs(Attrs::BOLD),
+            medium_cactus: Style::fg(Color::rgb(0, 255, 0)).with_attrs(Attrs::BOLD),
+            tall_cactus: Style::fg(Color::rgb(0, 255, 0)).with_attrs(Attrs::BOLD),
+            wide_cactus: Style::fg(Color::rgb(0, 255, 0)).with_attrs(Attrs::BOLD),
+            ptero_low: Style::fg(Color::rgb(255, 0, 255)).with_attrs(Attrs::BOLD),
+            ptero_mid: Style::fg(Color::rgb(255, 0, 255)).with_attrs(Attrs::BOLD),
+            ptero_high: Style::fg(Color::rgb(255, 0, 255)).with_attrs(Attrs::BOLD),
+            cloud: Style::fg(Color::rgb(192, 192, 192)),
+            star: Style::fg(white),
+            moon: Style::fg(white).with_attrs(Attrs::BOLD),
+            ground: Style::fg(white),
+            hud: Style::fg(Color::rgb(255, 255, 0)).with_attrs(Attrs::BOLD),
+        }
+    }
+
+    pub fn mono() -> Self {
+        let plain = Style::default();
+        let bold = plain.with_attrs(Attrs::BOLD);
+        Self {
+            name: "mono".to_string(),
+            background: plain,
+            dino: bold,
+            small_cactus: bold,
+            medium_cactus: bold,
+            tall_cactus: bold,
+            wide_cactus: bold,
+            ptero_low: bold,
+            ptero_mid: bold,
+            ptero_high: bold,
+            cloud: plain.with_attrs(Attrs::DIM),
+            star: plain,
+            moon: bold,
+            ground: plain,
+            hud: bold,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_problems_come_back_as_warnings() {
+        let mut cfg = ThemeConfig {
+            name: "desert".to_string(),
+            ..ThemeConfig::default()
+        };
+        cfg.colors.insert("dino".to_string(), "#102030".to_string());
+        let (theme, warnings) = Theme::from_config(&cfg);
+        assert!(warnings.is_empty());
+        assert_eq!(theme.dino.fg, Color::parse_hex("#102030"));
+
+        cfg.name = "neon".to_string();
+        cfg.colors.insert("tail".to_string(), "#102030".to_string());
+        cfg.colors.insert("cloud".to_string(), "white".to_string());
+        let (theme, mut warnings) = Theme::from_config(&cfg);
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            [
+                "Invalid color 'white' for theme element 'cloud'",
+                "Unknown theme 'neon', expected one of classic, desert, high-contrast, mono; using classic",
+                "Unknown theme element 'tail'",
+            ]
+        );
+        assert_eq!(theme.ground, Theme::classic().ground);
+    }
+}
@@ -0,0 +1,193 @@
+//! The `train` command: evolves a [`Network`] with a genetic algorithm over
+//! headless [`Env`] episodes, spreading each generation across threads.
+
+use {
+    crate::cli::TrainArgs,
+    color_eyre::{
+        Result,
+        eyre::{WrapErr, bail},
+    },
+    dino_rs::{
+        config::{Cfg, TrainConfig},
+        env::Env,
+        network::Network,
+        physics::seeded_rng,
+    },
+    rand::{Rng, rngs::StdRng},
+    serde::{Deserialize, Serialize},
+    std::{fs, path::Path, thread, time::Instant},
+};
+
+/// Genomes drawn per tournament when picking a parent.
+const TOURNAMENT: usize = 3;
+
+/// What `train` writes and `--watch` reads.
+#[derive(Serialize, Deserialize)]
+struct Genome {
+    generation: usize,
+    /// Mean score over the generation's episodes.
+    fitness: f32,
+    network: Network,
+}
+
+pub fn run(mut cfg: Cfg, args: &TrainArgs) -> Result<()> {
+    cfg.env.render_frames = false;
+    let train = cfg.train.clone();
+    if train.population == 0 || train.episodes == 0 {
+        bail!("train.population and train.episodes must be at least 1");
+    }
+
+    let threads = match train.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let base_seed = cfg.game.seed.unwrap_or(0);
+    let mut rng = seeded_rng(cfg.game.seed, 2);
+    let mut population: Vec<Network> = (0..train.population)
+        .map(|_| Network::random(train.hidden, &mut rng))
+        .collect();
+    let mut best: Option<Genome> = None;
+
+    println!(
+        "Training {} genomes for {} generations on {} threads",
+        train.population, train.generations, threads
+    );
+
+    for generation in 1..=train.generations {
+        let start = Instant::now();
+        let seeds: Vec<u64> = (0..train.episodes as u64)
+            .map(|i| base_seed.wrapping_add(generation as u64 * train.episodes as u64 + i))
+            .collect();
+
+        let fitness = evaluate(&cfg, &population, &seeds, threads)?;
+        let mut ranked: Vec<(f32, Network)> = fitness.into_iter().zip(population).collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let scores: Vec<f32> = ranked.iter().map(|(score, _)| *score).collect();
+        let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+        println!(
+            "generation {:>3}/{}: best {:>8.1}  mean {:>8.1}  median {:>8.1}  worst {:>8.1}  ({:.1}s)",
+            generation,
+            train.generations,
+            scores[0],
+            mean,
+            scores[scores.len() / 2],
+            scores[scores.len() - 1],
+            start.elapsed().as_secs_f32()
+        );
+
+        if best.as_ref().is_none_or(|b| scores[0] > b.fitness) {
+            let genome = Genome {
+                generation,
+                fitness: scores[0],
+                network: ranked[0].1.clone(),
+            };
+            save_genome(&args.out, &genome)?;
+            best = Some(genome);
+        }
+
+        population = breed(&ranked, &train, &mut rng);
+    }
+
+    if let Some(best) = best {
+        println!(
+            "Best genome scored {:.1} in generation {}; saved to {}",
+            best.fitness,
+            best.generation,
+            args.out.display()
+        );
+    }
+    Ok(())
+}
+
+/// Mean score of each genome over `seeds`, in population order.
+fn evaluate(cfg: &Cfg, population: &[Network], seeds: &[u64], threads: usize) -> Result<Vec<f32>> {
+    let chunk = population.len().div_ceil(threads.max(1));
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = population
+            .chunks(chunk)
+            .map(|genomes| {
+                scope.spawn(move || -> Result<Vec<f32>> {
+                    let mut env = Env::new(cfg.clone())?;
+                    Ok(genomes
+                        .iter()
+                        .map(|network| fitness(&mut env, network, seeds, cfg.train.max_steps))
+                        .collect())
+                })
+            })
+            .collect();
+
+        let mut fitness = Vec::with_capacity(population.len());
+        for worker in workers {
+            match worker.join() {
+                Ok(scores) => fitness.extend(scores?),
+                Err(_) => bail!("training thread panicked"),
+            }
+        }
+        Ok(fitness)
+    })
+}
+
+fn fitness(env: &mut Env, network: &Network, seeds: &[u64], max_steps: usize) -> f32 {
+    let mut total = 0;
+    for &seed in seeds {
+        let mut observation = env.reset(seed);
+        for _ in 0..max_steps {
+            let (next, _, done, _) = env.step(network.decide(&observation));
+            observation = next;
+            if done {
+                break;
+            }
+        }
+        total += env.score();
+    }
+    total as f32 / seeds.len() as f32
+}
+
+/// The next generation: the elite unchanged, then mutated children of
+/// tournament winners. `ranked` is sorted best first.
+fn breed(ranked: &[(f32, Network)], train: &TrainConfig, rng: &mut StdRng) -> Vec<Network> {
+    let mut next: Vec<Network> = ranked
+        .iter()
+        .take(train.elite)
+        .map(|(_, network)| network.clone())
+        .collect();
+
+    while next.len() < ranked.len() {
+        let a = tournament(ranked, rng);
+        let b = tournament(ranked, rng);
+        let mut child = a.crossover(b, rng);
+        child.mutate(train.mutation_rate, train.mutation_strength, rng);
+        next.push(child);
+    }
+    next
+}
+
+/// The fittest of a few genomes picked at random. Ranks stand in for
+/// fitness since `ranked` is already sorted.
+fn tournament<'a>(ranked: &'a [(f32, Network)], rng: &mut StdRng) -> &'a Network {
+    let winner = (0..TOURNAMENT)
+        .map(|_| rng.random_range(0..ranked.len()))
+        .min()
+        .unwrap_or(0);
+    &ranked[winner].1
+}
+
+fn save_genome(path: &Path, genome: &Genome) -> Result<()> {
+    let json = serde_json::to_string_pretty(genome)?;
+    fs::write(path, json).wrap_err_with(|| format!("Failed to save {}", path.display()))
+}
+
+/// Reads a genome saved by `train`, for `--watch`.
+pub fn load_genome(path: &Path) -> Result<Network> {
+    let json =
+        fs::read_to_string(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    let genome: Genome = serde_json::from_str(&json)
+        .wrap_err_with(|| format!("{} is not a genome saved by train", path.display()))?;
+    genome
+        .network
+        .validate()
+        .wrap_err_with(|| format!("{} has the wrong shape", path.display()))?;
+    Ok(genome.network)
+}
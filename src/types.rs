@@ -1,11 +1,16 @@
-#[derive(Clone, Copy, PartialEq, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DinoState {
     Running,
     Jumping,
     Crouching,
+    Dead,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ObstacleType {
     SmallCactus,
     MediumCactus,
@@ -20,11 +25,18 @@ pub enum ObstacleType {
 pub struct Obstacle {
     pub x: f32,
     pub obstacle_type: ObstacleType,
+    /// Increases by one per spawned obstacle within a run, so an obstacle can
+    /// be matched up between two snapshots.
+    pub id: u64,
 }
 
 impl Obstacle {
     pub fn new(x: f32, obstacle_type: ObstacleType) -> Self {
-        Self { x, obstacle_type }
+        Self {
+            x,
+            obstacle_type,
+            id: 0,
+        }
     }
 }
 
@@ -40,6 +52,19 @@ impl Cloud {
     }
 }
 
+#[derive(Clone)]
+pub struct Star {
+    pub x: f32,
+    pub y: usize,
+    pub twinkle: u8,
+}
+
+impl Star {
+    pub fn new(x: f32, y: usize, twinkle: u8) -> Self {
+        Self { x, y, twinkle }
+    }
+}
+
 pub struct Dino {
     pub state: DinoState,
     pub y: f32,
@@ -61,3 +86,9 @@ impl Dino {
         self.velocity_y = 0.0;
     }
 }
+
+impl Default for Dino {
+    fn default() -> Self {
+        Self::new()
+    }
+}
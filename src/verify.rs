@@ -0,0 +1,45 @@
+//! `verify`: plays a saved replay again, without drawing it, and checks
+//! the score it claims.
+
+use {
+    crate::cli::VerifyArgs,
+    color_eyre::{
+        Result,
+        eyre::{WrapErr, bail},
+    },
+    dino_rs::{
+        config::Cfg,
+        replay::{self, Replay},
+    },
+    std::{fs, path::Path},
+};
+
+pub fn run(cfg: Cfg, args: &VerifyArgs) -> Result<()> {
+    let path = &args.replay;
+    let text =
+        fs::read_to_string(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    let replay: Replay = serde_json::from_str(&text)
+        .wrap_err_with(|| format!("{} is not a replay", path.display()))?;
+
+    let key = match &args.key {
+        Some(file) => Some(replay::load_key(file)?),
+        None if !cfg.key_file.is_empty() => Some(replay::load_key(Path::new(&cfg.key_file))?),
+        None => None,
+    };
+
+    match replay.verify(&cfg, key.as_deref()) {
+        Ok(verified) => {
+            let signature = if verified.signed {
+                "signature checked"
+            } else {
+                "no key, signature not checked"
+            };
+            println!(
+                "Verified: {} points in {} ticks ({})",
+                verified.score, verified.ticks, signature
+            );
+            Ok(())
+        }
+        Err(rejection) => bail!("Rejected {}: {}", path.display(), rejection),
+    }
+}
@@ -0,0 +1,77 @@
+//! Two players on one keyboard, each in their own lane on the same course.
+//! The dino that stays alive for more ticks takes the round.
+
+use crate::world::World;
+
+/// Rounds won by each player so far.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Tally {
+    pub wins: [usize; 2],
+}
+
+impl Tally {
+    /// Scores a finished round and returns its winner, if it had one.
+    pub fn record(&mut self, worlds: &[World; 2]) -> Option<usize> {
+        let winner = winner(worlds);
+        if let Some(player) = winner {
+            self.wins[player] += 1;
+        }
+        winner
+    }
+}
+
+/// The player whose dino outlasted the other's, or `None` when both died
+/// on the same tick.
+pub fn winner(worlds: &[World; 2]) -> Option<usize> {
+    match worlds[0].ticks().cmp(&worlds[1].ticks()) {
+        std::cmp::Ordering::Greater => Some(0),
+        std::cmp::Ordering::Less => Some(1),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{bot::Bot, config::Cfg, world::Input},
+    };
+
+    /// Both lanes on one seed, the bot playing in `bot`'s and nobody in
+    /// the other; `None` leaves both to the bot.
+    fn round(bot: Option<usize>) -> [World; 2] {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(11);
+        let mut worlds = [World::new(cfg.clone(), 80, 22), World::new(cfg, 80, 22)];
+        for (player, world) in worlds.iter_mut().enumerate() {
+            let plays = bot.is_none_or(|bot| bot == player);
+            // Even the bot falls eventually; a round needs an end.
+            while !world.is_over() && world.ticks() < 3000 {
+                let inputs = if plays {
+                    vec![Input::from(Bot::decide(world.physics(), world.height()))]
+                } else {
+                    Vec::new()
+                };
+                world.tick(&inputs);
+            }
+        }
+        worlds
+    }
+
+    #[test]
+    fn the_longer_run_wins_and_ties_score_nothing() {
+        let mut tally = Tally::default();
+
+        assert_eq!(tally.record(&round(Some(1))), Some(1));
+        assert_eq!(tally.record(&round(Some(0))), Some(0));
+        assert_eq!(tally.record(&round(Some(1))), Some(1));
+        assert_eq!(tally.wins, [1, 2]);
+
+        // The same run in both lanes ends on the same tick.
+        let tie = round(None);
+        assert_eq!(tie[0].ticks(), tie[1].ticks());
+        assert_eq!(winner(&tie), None);
+        assert_eq!(tally.record(&tie), None);
+        assert_eq!(tally.wins, [1, 2]);
+    }
+}
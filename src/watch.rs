@@ -0,0 +1,62 @@
+//! `watch`: shows a game someone is streaming with `--broadcast`, read-only.
+
+use {
+    crate::{cli::WatchArgs, input::InputHandler},
+    color_eyre::{Result, eyre::WrapErr},
+    dino_rs::{
+        backend::{Backend, BackendKind},
+        config::Cfg,
+        spectate::{self, Screen, Update},
+        theme::ColorSupport,
+    },
+    std::{
+        sync::mpsc::{Receiver, RecvTimeoutError},
+        time::Duration,
+    },
+};
+
+/// How long to wait for an update before checking the keyboard.
+const POLL: Duration = Duration::from_millis(50);
+
+pub fn run(cfg: Cfg, args: &WatchArgs) -> Result<()> {
+    let updates = spectate::subscribe(&args.addr)
+        .wrap_err_with(|| format!("Failed to watch {}", args.addr))?;
+    let kind = BackendKind::from_config(&cfg.render.backend);
+    let backend = kind.create(ColorSupport::detect(&cfg.theme.color_mode));
+
+    crate::setup_terminal(kind)?;
+    let result = watch(backend, &updates);
+    crate::cleanup_terminal(kind)?;
+    result
+}
+
+/// Shows updates as they arrive until the player quits or the broadcast
+/// ends.
+fn watch(mut backend: Box<dyn Backend>, updates: &Receiver<Update>) -> Result<()> {
+    let mut input = InputHandler::terminal();
+    let mut screen = Screen::new();
+
+    loop {
+        match updates.recv_timeout(POLL) {
+            Ok(update) => {
+                screen.apply(update);
+                // Only the newest screen is worth drawing.
+                updates.try_iter().for_each(|update| screen.apply(update));
+                screen.show(backend.as_mut())?;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if input.poll()?.is_none() {
+            return Ok(());
+        }
+    }
+
+    backend.show_text(&[
+        "The broadcast has ended".to_string(),
+        String::new(),
+        "Press any key to exit".to_string(),
+    ])?;
+    while input.wait_for_key()?.is_none() {}
+    Ok(())
+}
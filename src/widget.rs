@@ -0,0 +1,85 @@
+use {
+    crate::{
+        rendering::{DrawArgs, Scene},
+        theme::{self, Style},
+        world::World,
+    },
+    ratatui::{
+        buffer::Buffer,
+        layout::Rect,
+        style::{Color, Modifier},
+        widgets::StatefulWidget,
+    },
+};
+
+/// Draws a [`World`] into any area of a ratatui frame. The [`Scene`] kept in
+/// the widget state holds the sprites and the day/night palette between
+/// frames:
+///
+/// ```no_run
+/// # use dino_rs::{config::Cfg, rendering::Scene, widget::GameWidget, world::World};
+/// # fn draw(frame: &mut ratatui::Frame, world: &World, scene: &mut Scene) {
+/// frame.render_stateful_widget(GameWidget::new(world), frame.area(), scene);
+/// # }
+/// ```
+pub struct GameWidget<'a> {
+    world: &'a World,
+}
+
+impl<'a> GameWidget<'a> {
+    pub fn new(world: &'a World) -> Self {
+        Self { world }
+    }
+}
+
+impl StatefulWidget for GameWidget<'_> {
+    type State = Scene;
+
+    fn render(self, area: Rect, buf: &mut Buffer, scene: &mut Scene) {
+        let area = area.intersection(buf.area);
+        if area.is_empty() {
+            return;
+        }
+
+        let frame = self.world.frame();
+        scene.update_dimensions(area.width as usize, area.height as usize);
+        let screen = scene.compose(DrawArgs::from_snapshot(
+            &frame,
+            self.world.score(),
+            self.world.highscore(),
+        ));
+
+        for (y, row) in screen.rows().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let target = &mut buf[(area.x + x as u16, area.y + y as u16)];
+                if cell.is_continuation() {
+                    target.reset();
+                    continue;
+                }
+                target
+                    .set_char(cell.ch)
+                    .set_style(ratatui_style(cell.style));
+            }
+        }
+    }
+}
+
+fn ratatui_style(style: Style) -> ratatui::style::Style {
+    let color = |c: Option<theme::Color>| c.map_or(Color::Reset, |c| Color::Rgb(c.r, c.g, c.b));
+
+    let mut modifier = Modifier::empty();
+    if style.attrs.bold {
+        modifier |= Modifier::BOLD;
+    }
+    if style.attrs.dim {
+        modifier |= Modifier::DIM;
+    }
+    if style.attrs.reverse {
+        modifier |= Modifier::REVERSED;
+    }
+
+    ratatui::style::Style::default()
+        .fg(color(style.fg))
+        .bg(color(style.bg))
+        .add_modifier(modifier)
+}
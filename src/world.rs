@@ -0,0 +1,307 @@
+use {
+    crate::{
+        config::Cfg,
+        physics::{DINO_X, PhysicsEngine},
+        replay::{Replay, config_hash},
+        snapshot::Snapshot,
+        spawner::ObstacleSpawner,
+    },
+    serde::{Deserialize, Serialize},
+    std::time::Duration,
+};
+
+/// Longest stretch of time a single `step` will simulate, so a long stall
+/// (a suspended process, a debugger) does not replay seconds of ticks.
+const MAX_STEP: f32 = 0.25;
+
+/// A player action for the next step.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    Jump,
+    /// Crouch, or drop fast while in the air. Keeps the dino down until
+    /// `Stand` arrives.
+    Crouch,
+    Stand,
+}
+
+/// Something that happened during a step, for hosts to react to with sound
+/// or UI.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    Jumped,
+    /// The score passed the high score the run started with.
+    NewHighscore,
+    Died,
+}
+
+/// One game of dino: the dino, obstacles and scenery, advanced in fixed
+/// physics ticks by [`World::step`]. It never touches the terminal, so any
+/// host can drive and draw it.
+pub struct World {
+    cfg: Cfg,
+    physics: PhysicsEngine,
+    spawner: ObstacleSpawner,
+    width: usize,
+    height: usize,
+    score: usize,
+    highscore: usize,
+    run_highscore: usize,
+    accumulator: f32,
+    previous: Snapshot,
+    over: bool,
+    seed: u64,
+    ticks: u64,
+    replay: Replay,
+}
+
+impl World {
+    pub fn new(cfg: Cfg, width: usize, height: usize) -> Self {
+        let seed = run_seed(&cfg);
+        let (physics, spawner) = engines(&cfg, seed, width, height);
+        let previous = Snapshot::capture(&physics);
+        let replay = Replay {
+            config_hash: config_hash(&cfg),
+            ..Replay::new(seed, width, height)
+        };
+
+        Self {
+            cfg,
+            physics,
+            spawner,
+            width,
+            height,
+            score: 0,
+            highscore: 0,
+            run_highscore: 0,
+            accumulator: 0.0,
+            previous,
+            over: false,
+            seed,
+            ticks: 0,
+            replay,
+        }
+    }
+
+    /// Applies `inputs`, then runs as many physics ticks as fit into `dt`
+    /// plus whatever was left over from earlier steps.
+    pub fn step(&mut self, dt: Duration, inputs: &[Input]) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.over {
+            return events;
+        }
+
+        self.apply_all(inputs, &mut events);
+        self.accumulator = (self.accumulator + dt.as_secs_f32()).min(MAX_STEP);
+
+        while self.accumulator >= self.cfg.physics.fixed_timestep {
+            self.accumulator -= self.cfg.physics.fixed_timestep;
+            if !self.advance(&mut events) {
+                self.accumulator = 0.0;
+                break;
+            }
+        }
+
+        events
+    }
+
+    /// Applies `inputs` and runs exactly one physics tick, however much
+    /// time has passed. For replays and players that run faster than real
+    /// time.
+    pub fn tick(&mut self, inputs: &[Input]) -> Vec<Event> {
+        let mut events = Vec::new();
+        if !self.over {
+            self.apply_all(inputs, &mut events);
+            self.advance(&mut events);
+        }
+        events
+    }
+
+    fn apply_all(&mut self, inputs: &[Input], events: &mut Vec<Event>) {
+        for &input in inputs {
+            self.replay.record(self.ticks, input);
+            if self.apply(input) {
+                events.push(Event::Jumped);
+            }
+        }
+    }
+
+    /// Runs one physics tick. Returns `false` once the dino has died.
+    fn advance(&mut self, events: &mut Vec<Event>) -> bool {
+        self.previous = Snapshot::capture(&self.physics);
+        self.spawner.update_difficulty(self.score);
+
+        let alive =
+            self.physics
+                .update(&mut self.spawner, self.width, self.height, &mut self.score);
+        self.ticks += 1;
+
+        if self.score > self.highscore {
+            if self.highscore == self.run_highscore {
+                events.push(Event::NewHighscore);
+            }
+            self.highscore = self.score;
+        }
+
+        if !alive {
+            self.over = true;
+            self.replay.finish(self.ticks, self.score);
+            events.push(Event::Died);
+        }
+        alive
+    }
+
+    /// Returns whether the input started a jump.
+    fn apply(&mut self, input: Input) -> bool {
+        match input {
+            Input::Jump => return self.physics.jump(),
+            Input::Crouch => self.physics.crouch(),
+            Input::Stand => self.physics.stand(),
+        }
+        false
+    }
+
+    /// Positions for drawing right now, blended between the last two ticks
+    /// by how far time has moved on since the latest one.
+    pub fn frame(&self) -> Snapshot {
+        let current = Snapshot::capture(&self.physics);
+        if self.over {
+            return current;
+        }
+
+        let alpha = (self.accumulator / self.cfg.physics.fixed_timestep).clamp(0.0, 1.0);
+        self.previous.interpolate(&current, alpha, self.width)
+    }
+
+    /// Simulated time left before the next physics tick is due.
+    pub fn time_until_tick(&self) -> Duration {
+        Duration::from_secs_f32((self.cfg.physics.fixed_timestep - self.accumulator).max(0.0))
+    }
+
+    /// Starts a new run at the given size, keeping the high score. With
+    /// `game.seed` set every run plays the same course.
+    pub fn reset(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.seed = run_seed(&self.cfg);
+        (self.physics, self.spawner) = engines(&self.cfg, self.seed, width, height);
+        self.ticks = 0;
+        // The settings stay the same, and so does their hash.
+        let config_hash = std::mem::take(&mut self.replay.config_hash);
+        self.replay = Replay {
+            config_hash,
+            ..Replay::new(self.seed, width, height)
+        };
+        self.score = 0;
+        self.run_highscore = self.highscore;
+        self.accumulator = 0.0;
+        self.previous = Snapshot::capture(&self.physics);
+        self.over = false;
+    }
+
+    /// Changes the playfield size without restarting; objects off the new
+    /// edge scroll in or out naturally.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn set_highscore(&mut self, highscore: usize) {
+        self.highscore = highscore;
+        self.run_highscore = highscore;
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    pub fn highscore(&self) -> usize {
+        self.highscore
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cfg(&self) -> &Cfg {
+        &self.cfg
+    }
+
+    /// Seed of the current run: `game.seed` when set, otherwise a fresh
+    /// random one per run.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Physics ticks since the run started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// The current run's inputs so far, complete once it is over.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// The engine behind the world, for players such as
+    /// [`Bot`](crate::bot::Bot) that read its state.
+    pub fn physics(&self) -> &PhysicsEngine {
+        &self.physics
+    }
+}
+
+/// The smallest playfield a world runs on under `cfg`: room for the dino,
+/// and for clouds above its highest jump.
+pub fn min_size(cfg: &Cfg) -> (usize, usize) {
+    (DINO_X * 2, cfg.physics.ground_height + 16)
+}
+
+fn run_seed(cfg: &Cfg) -> u64 {
+    cfg.game.seed.unwrap_or_else(rand::random)
+}
+
+/// Physics and spawner for a run, both seeded from `seed`.
+fn engines(cfg: &Cfg, seed: u64, width: usize, height: usize) -> (PhysicsEngine, ObstacleSpawner) {
+    let mut cfg = cfg.clone();
+    cfg.game.seed = Some(seed);
+    (
+        PhysicsEngine::new(cfg.clone(), width, height),
+        ObstacleSpawner::new(cfg),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worlds_on_one_seed_share_the_obstacle_stream() {
+        let mut cfg = Cfg::default();
+        cfg.game.seed = Some(21);
+        let mut jumper = World::new(cfg.clone(), 80, 24);
+        let mut croucher = World::new(cfg, 80, 24);
+        let obstacles = |world: &World| -> Vec<_> {
+            world
+                .physics()
+                .obstacles
+                .iter()
+                .map(|obstacle| (obstacle.id, obstacle.obstacle_type, obstacle.x))
+                .collect()
+        };
+
+        while !jumper.is_over() && !croucher.is_over() {
+            jumper.tick(&[Input::Jump]);
+            croucher.tick(&[Input::Crouch]);
+            assert_eq!(obstacles(&jumper), obstacles(&croucher));
+        }
+        assert!(!obstacles(&jumper).is_empty());
+    }
+}